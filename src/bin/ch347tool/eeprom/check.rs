@@ -0,0 +1,40 @@
+use std::{cmp, error::Error, fs};
+
+use clap::Parser;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Check eeprom chip memory")]
+pub struct CmdEepromCheck {
+    /// compare with file
+    #[clap(value_parser)]
+    file: String,
+}
+
+pub fn cli_eeprom_check(
+    eeprom_args: &super::CmdEeprom,
+    args: &CmdEepromCheck,
+) -> Result<(), Box<dyn Error>> {
+    let file_buf = fs::read(args.file.as_str())?;
+    let eeprom = eeprom_args.init()?;
+
+    let wsize = cmp::min(file_buf.len(), eeprom.eeprom_type().capacity());
+
+    println!("Checking...");
+    let mut rbuf: Vec<u8> = vec![0; wsize];
+    eeprom.read(0, &mut rbuf)?;
+
+    if let Some(x) = (0..wsize).find(|&x| rbuf[x] != file_buf[x]) {
+        return Err(format!(
+            "diff 0x{:04X}_{:04X} {:02X} => {:02X}",
+            x >> 16,
+            x & 0xFFFF,
+            file_buf[x],
+            rbuf[x]
+        )
+        .into());
+    }
+
+    println!("Check done, {} bytes match", wsize);
+
+    Ok(())
+}
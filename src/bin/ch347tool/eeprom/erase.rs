@@ -0,0 +1,42 @@
+use std::{
+    error::Error,
+    fmt::Write,
+    time::{Duration, SystemTime},
+};
+
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Erase eeprom chip, fill with 0xFF")]
+pub struct CmdEepromErase {}
+
+pub fn cli_eeprom_erase(
+    eeprom_args: &super::CmdEeprom,
+    _args: &CmdEepromErase,
+) -> Result<(), Box<dyn Error>> {
+    let eeprom = eeprom_args.init()?;
+
+    let pb = ProgressBar::new(eeprom.eeprom_type().capacity() as u64);
+    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}) ({eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
+
+    println!("Start Erase Full Chip ...");
+    let start_time = SystemTime::now();
+
+    eeprom.erase_with_callback(|e| {
+        if let ch347_rs::WriteEvent::Block(_, count) = e {
+            pb.inc(count as u64);
+        }
+        true
+    })?;
+    pb.finish_and_clear();
+
+    let take_time = start_time.elapsed().unwrap().as_millis();
+    let take_time = Duration::from_millis(take_time as u64);
+    println!("Done, Take time: {}", humantime::format_duration(take_time));
+
+    Ok(())
+}
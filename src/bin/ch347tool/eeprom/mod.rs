@@ -0,0 +1,133 @@
+use std::error::Error;
+
+use ch347_rs::{Ch347Device, Eeprom, EepromType, I2cSpeed, SoftEeprom, WriteEvent};
+use clap::{Parser, Subcommand};
+
+use crate::i2c::parse_addr;
+use crate::spi_flash::utils::format_byte_unit;
+
+mod check;
+mod erase;
+mod read;
+mod write;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Operate i2c eeprom chip")]
+pub struct CmdEeprom {
+    /// device number
+    #[clap(value_parser, default_value_t = 0)]
+    index: u32,
+
+    /// eeprom model
+    #[clap(short = 't', long = "type", value_enum, value_parser)]
+    eeprom_type: EepromType,
+
+    /// 20kHz, 100kHz, 400kHz, 750kHz
+    #[clap(short, long, value_enum, value_parser, default_value_t = I2cSpeed::Std)]
+    speed: I2cSpeed,
+
+    /// Drive the chip with plain I2C transfers instead of the vendor helpers
+    #[clap(long, value_parser, action)]
+    soft: bool,
+
+    /// 7-bit device address with the block select bits zero, only used
+    /// with --soft
+    #[clap(short, long, value_parser, default_value = "0x50")]
+    addr: String,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Read(read::CmdEepromRead),
+    Write(write::CmdEepromWrite),
+    Check(check::CmdEepromCheck),
+    Erase(erase::CmdEepromErase),
+}
+
+/// Vendor or software EEPROM driver selected by `--soft`
+pub enum EepromDev {
+    Vendor(Eeprom),
+    Soft(SoftEeprom<Ch347Device>),
+}
+
+impl EepromDev {
+    pub fn eeprom_type(&self) -> EepromType {
+        match self {
+            EepromDev::Vendor(e) => e.eeprom_type,
+            EepromDev::Soft(e) => e.eeprom_type,
+        }
+    }
+
+    pub fn read(&self, addr: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        match self {
+            EepromDev::Vendor(e) => e.read(addr, buf),
+            EepromDev::Soft(e) => e.read(addr, buf),
+        }
+    }
+
+    pub fn write_with_callback<F>(&self, cbk: F, addr: u32, buf: &[u8]) -> Result<(), &'static str>
+    where
+        F: FnMut(WriteEvent) -> bool,
+    {
+        match self {
+            EepromDev::Vendor(e) => e.write_with_callback(cbk, addr, buf),
+            EepromDev::Soft(e) => e.write_with_callback(cbk, addr, buf),
+        }
+    }
+
+    pub fn erase_with_callback<F>(&self, cbk: F) -> Result<(), &'static str>
+    where
+        F: FnMut(WriteEvent) -> bool,
+    {
+        match self {
+            EepromDev::Vendor(e) => e.erase_with_callback(cbk),
+            EepromDev::Soft(e) => e.erase_with_callback(cbk),
+        }
+    }
+}
+
+impl CmdEeprom {
+    pub fn init(&self) -> Result<EepromDev, Box<dyn Error>> {
+        let device = Ch347Device::new(self.index)?;
+        device.i2c_set(self.speed);
+
+        let eeprom = if self.soft {
+            let mut eeprom = SoftEeprom::new(device, self.eeprom_type);
+            eeprom.set_addr(parse_addr(&self.addr)?)?;
+            EepromDev::Soft(eeprom)
+        } else {
+            EepromDev::Vendor(device.eeprom(self.eeprom_type))
+        };
+
+        println!("EEPROM:");
+        println!("      Name: {}", self.eeprom_type);
+        println!(
+            "  Capacity: {}",
+            format_byte_unit(self.eeprom_type.capacity())
+        );
+        println!(
+            "  PageSize: {}",
+            format_byte_unit(self.eeprom_type.page_size())
+        );
+        println!("     Speed: {}", self.speed);
+        if let EepromDev::Soft(e) = &eeprom {
+            println!("   Address: 0x{:02X} (soft)", e.addr);
+        }
+
+        Ok(eeprom)
+    }
+}
+
+pub fn cli_eeprom(args: &CmdEeprom) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Commands::Read(sub_args) => read::cli_eeprom_read(args, sub_args)?,
+        Commands::Write(sub_args) => write::cli_eeprom_write(args, sub_args)?,
+        Commands::Check(sub_args) => check::cli_eeprom_check(args, sub_args)?,
+        Commands::Erase(sub_args) => erase::cli_eeprom_erase(args, sub_args)?,
+    };
+
+    Ok(())
+}
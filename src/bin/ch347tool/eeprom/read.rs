@@ -0,0 +1,41 @@
+use std::{
+    error::Error,
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use clap::Parser;
+
+use crate::spi_flash::utils::format_byte_per_sec;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Read eeprom chip")]
+pub struct CmdEepromRead {
+    /// output to file
+    #[clap(value_parser)]
+    file: String,
+}
+
+pub fn cli_eeprom_read(
+    eeprom_args: &super::CmdEeprom,
+    args: &CmdEepromRead,
+) -> Result<(), Box<dyn Error>> {
+    let eeprom = eeprom_args.init()?;
+
+    let mut all_buf: Vec<u8> = vec![0; eeprom.eeprom_type().capacity()];
+
+    println!("Reading ...");
+    let start_time = SystemTime::now();
+
+    eeprom.read(0, &mut all_buf)?;
+
+    let take_time = start_time.elapsed().unwrap().as_millis();
+    let take_time = Duration::from_millis(take_time as u64);
+    fs::write(args.file.as_str(), &all_buf)?;
+
+    println!("Done, Take time: {}", humantime::format_duration(take_time));
+    let speed = (all_buf.len() as f64) / take_time.as_secs_f64();
+    println!("{}", format_byte_per_sec(speed));
+
+    Ok(())
+}
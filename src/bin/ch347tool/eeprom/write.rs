@@ -0,0 +1,93 @@
+use std::{
+    cmp,
+    error::Error,
+    fmt::Write,
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+
+use crate::spi_flash::utils::{format_byte_per_sec, format_byte_unit};
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Write eeprom chip")]
+pub struct CmdEepromWrite {
+    /// Check after writing
+    #[clap(short, long, value_parser, action)]
+    check: bool,
+
+    /// input file
+    #[clap(value_parser)]
+    file: String,
+}
+
+pub fn cli_eeprom_write(
+    eeprom_args: &super::CmdEeprom,
+    args: &CmdEepromWrite,
+) -> Result<(), Box<dyn Error>> {
+    let file_buf = fs::read(args.file.as_str())?;
+    let eeprom = eeprom_args.init()?;
+
+    let chip_capacity = eeprom.eeprom_type().capacity();
+    let wsize = cmp::min(file_buf.len(), chip_capacity);
+
+    if file_buf.len() > chip_capacity {
+        println!(
+            "{} File size is too large, the last {} will be lost",
+            console::style("Warn:").yellow(),
+            console::style(format_byte_unit(file_buf.len() - chip_capacity)).yellow(),
+        );
+    }
+
+    let pb = ProgressBar::new(wsize as u64);
+    pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}) ({eta})")
+        .unwrap()
+        .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+        .progress_chars("#>-"));
+
+    println!("Writing ...");
+    let start_time = SystemTime::now();
+
+    eeprom.write_with_callback(
+        |e| {
+            if let ch347_rs::WriteEvent::Block(_, count) = e {
+                pb.inc(count as u64);
+            }
+            true
+        },
+        0,
+        &file_buf[0..wsize],
+    )?;
+    pb.finish_and_clear();
+
+    let take_time = start_time.elapsed().unwrap().as_millis();
+    let take_time = Duration::from_millis(take_time as u64);
+    let speed = (wsize as f64) / take_time.as_secs_f64();
+    println!(
+        "Write done, Take time: {} Speed: {}",
+        humantime::format_duration(take_time),
+        format_byte_per_sec(speed)
+    );
+
+    if args.check {
+        let mut rbuf: Vec<u8> = vec![0; wsize];
+        eeprom.read(0, &mut rbuf)?;
+
+        if let Some(x) = (0..wsize).find(|&x| rbuf[x] != file_buf[x]) {
+            return Err(format!(
+                "diff 0x{:04X}_{:04X} {:02X} => {:02X}",
+                x >> 16,
+                x & 0xFFFF,
+                file_buf[x],
+                rbuf[x]
+            )
+            .into());
+        }
+
+        println!("Verify done");
+    }
+
+    Ok(())
+}
@@ -1,17 +1,26 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::BufWriter,
+    time::{Duration, SystemTime},
+};
+
+use ch347_rs::{GpioDrive, GpioSequence, PwmChannel, PwmConfig, PwmOutput, VcdWriter};
 use clap::{Parser, Subcommand};
-use std::thread::sleep;
-use std::time::Duration;
+
+use crate::spi_flash::utils::parse_cli_arg_number;
 
 #[derive(Parser, Debug)]
 #[clap(about = "Operate gpio")]
 pub struct CmdGpio {
     /// device number
-    #[clap(value_parser)]
+    #[clap(value_parser, default_value_t = 0)]
     index: u32,
 
-    /// gpio mask, eg. hex: 0xFF or FFH dec:64 bin:0b0000_0011
+    /// gpio mask, eg. hex: 0xFF or FFH dec:64 bin:0b0000_0011,
+    /// or pin list: 0,3,5 GPIO4, not used by status/read/run
     #[clap(value_parser)]
-    gpio_mask: String,
+    gpio_mask: Option<String>,
 
     #[clap(subcommand, value_enum)]
     command: Commands,
@@ -27,6 +36,33 @@ pub struct SubCmdPWM {
     /// range 0~1, eg. 0.5(50%), 0.25(25%)
     #[clap(default_value_t = 0.5)]
     duty: f32,
+
+    /// own duty for a pin, eg. --pin 3=0.25, can be repeated
+    #[clap(long = "pin", value_parser = parse_pin_duty)]
+    pins: Vec<(u8, f32)>,
+
+    /// stop after this time, eg. 10s
+    #[clap(long, value_parser = humantime::parse_duration)]
+    duration: Option<Duration>,
+
+    /// stop after this many periods
+    #[clap(long)]
+    cycles: Option<u64>,
+}
+
+fn parse_pin_duty(input: &str) -> Result<(u8, f32), String> {
+    let (pin, duty) = input
+        .split_once('=')
+        .ok_or_else(|| format!("Expect PIN=DUTY, got {:?}", input))?;
+    let pin = parse_gpio_pins(pin).map_err(|e| e.to_string())?;
+    if pin.count_ones() != 1 {
+        return Err(format!("Expect a single pin, got {:?}", input));
+    }
+    let duty = duty
+        .parse()
+        .map_err(|_| format!("Invalid duty {:?}", duty))?;
+
+    Ok((pin.trailing_zeros() as u8, duty))
 }
 
 // #[derive(ValueEnum, Subcommand, Clone, Debug)]
@@ -34,9 +70,74 @@ pub struct SubCmdPWM {
 pub enum Commands {
     Status,
     Pwm(SubCmdPWM),
+    /// Output high
     High,
+    /// Output low
     Low,
+    /// Invert the output level
+    Toggle,
+    /// Switch to input
+    Input,
+    /// Output a value on the masked pins, eg. 0b0101
+    Set {
+        value: String,
+    },
     Read,
+    Watch(SubCmdWatch),
+    /// Run a sequence file
+    Run {
+        file: String,
+    },
+}
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Print the edges of the masked pins until Ctrl-C")]
+pub struct SubCmdWatch {
+    /// poll interval, eg. 1ms 200us
+    #[clap(long, default_value = "1ms", value_parser = humantime::parse_duration)]
+    interval: Duration,
+
+    /// stop after this time, eg. 10s
+    #[clap(long, value_parser = humantime::parse_duration)]
+    duration: Option<Duration>,
+
+    /// also write the capture to a VCD file
+    #[clap(long)]
+    vcd: Option<String>,
+}
+
+/// Pin list like `0,3,5` or `GPIO4`, or a mask number
+pub fn parse_gpio_pins(input: &str) -> Result<u8, Box<dyn Error>> {
+    let is_list = input.contains(',') || input.to_lowercase().starts_with("gpio");
+
+    if !is_list {
+        return parse_cli_arg_number(input, false);
+    }
+
+    let mut mask: u8 = 0;
+    for item in input.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let lower = item.to_lowercase();
+        let num = lower.strip_prefix("gpio").unwrap_or(&lower);
+
+        match num.parse::<u8>() {
+            Ok(n) if n < ch347_rs::GPIO_PIN_COUNT => mask |= 1 << n,
+            _ => return Err(format!("Invalid gpio pin: {:?}", item).into()),
+        }
+    }
+
+    Ok(mask)
+}
+
+/// Run the sequence in `path` on device `index`, printing each step
+pub fn run_sequence_file(index: u32, path: &str) -> Result<(), Box<dyn Error>> {
+    let seq = GpioSequence::load(path)?;
+    let gpio = ch347_rs::Ch347Device::new(index)?.gpio()?;
+
+    println!("Run gpio sequence: {}", path);
+    gpio.sequencer()
+        .run_with_callback(|step| println!("  {}", step), &seq)?;
+
+    Ok(())
 }
 
 fn parse_gpio_dir(a: u8, bit: u8) -> &'static str {
@@ -53,15 +154,34 @@ fn parse_gpio_data(a: u8, bit: u8) -> &'static str {
     "Low"
 }
 
-pub fn cli_operator_gpio(args: &CmdGpio) {
+pub fn cli_operator_gpio(args: &CmdGpio) -> Result<(), Box<dyn Error>> {
     println!("Select device index: {}", args.index);
-    println!("Select gpio mask: {}", args.gpio_mask);
+
+    let mask = match args.command {
+        Commands::Status | Commands::Read | Commands::Run { .. } => 0,
+        _ => {
+            let mask = match &args.gpio_mask {
+                Some(m) => parse_gpio_pins(m)?,
+                None => return Err("No gpio mask given".into()),
+            };
+            println!("Select gpio mask: 0x{:02X}", mask);
+            if mask == 0 {
+                return Err("No gpio selected".into());
+            }
+            mask
+        }
+    };
+
+    if let Commands::Run { file } = &args.command {
+        return run_sequence_file(args.index, file);
+    }
+
+    let dev = ch347_rs::Ch347Device::new(args.index)?;
+    let gpio = dev.gpio()?;
+
     match &args.command {
         Commands::Status => {
-            let dev = ch347_rs::Ch347Device::new(args.index).expect("error opening device");
-
-            let (gpio_dir, gpio_data) =
-                ch347_rs::gpio_get(dev.get_dev_index()).expect("GPIO status error");
+            let (gpio_dir, gpio_data) = gpio.drive.gpio_read()?;
             println!("Dir: 0x{:02X} Data: 0x{:02X}", gpio_dir, gpio_data);
 
             for i in 0..=7 {
@@ -74,51 +194,103 @@ pub fn cli_operator_gpio(args: &CmdGpio) {
             }
         }
         Commands::Pwm(sub_cmd) => {
-            let mask = args.gpio_mask.parse::<u8>().unwrap();
-            let dev = ch347_rs::Ch347Device::new(args.index).expect("error opening device");
-            let (curr_dir, curr_data) = ch347_rs::gpio_get(dev.get_dev_index()).unwrap();
-            let dir = curr_dir | mask;
-
-            let freq = sub_cmd.freq as f64;
-            let duty = sub_cmd.duty as f64;
-            let on_period = Duration::from_micros((duty * 1_000_000.0 / freq) as u64);
-            let off_period = Duration::from_micros(((1.0 - duty) * 1_000_000.0 / freq) as u64);
-
-            println!("on_period: {:?}, off_period: {:?}", &on_period, &off_period);
-
-            loop {
-                let data = curr_data | mask;
-                let _ = ch347_rs::gpio_set(dev.get_dev_index(), mask, dir, data);
-                sleep(on_period);
-                let data = curr_data & !mask;
-                let _ = ch347_rs::gpio_set(dev.get_dev_index(), mask, dir, data);
-                sleep(off_period);
+            let mut channels: Vec<PwmChannel> = (0..8)
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|pin| PwmChannel {
+                    pin,
+                    duty: sub_cmd.duty,
+                })
+                .collect();
+            for &(pin, duty) in &sub_cmd.pins {
+                channels.retain(|c| c.pin != pin);
+                channels.push(PwmChannel { pin, duty });
+            }
+
+            let mut config = PwmConfig::new(sub_cmd.freq as f64, channels);
+            config.duration = sub_cmd.duration;
+            config.cycles = sub_cmd.cycles;
+
+            for c in &config.channels {
+                println!("GPIO{} duty {:.1}%", c.pin, c.duty * 100.0);
             }
+            println!("Output {} Hz, Ctrl-C to stop", config.freq);
+
+            let pwm = PwmOutput::start(gpio, config)?;
+            let stop = pwm.stop_handle();
+            ctrlc::set_handler(move || stop.stop())?;
+
+            let (_, report) = pwm.wait()?;
+            println!(
+                "Output {} periods in {}, measured {:.3} Hz, {} late",
+                report.cycles,
+                humantime::format_duration(report.elapsed),
+                report.frequency(),
+                report.late,
+            );
         }
-        Commands::High => {
-            let mask = args.gpio_mask.parse::<u8>().unwrap();
-            let dev = ch347_rs::Ch347Device::new(args.index).expect("error opening device");
-            let (curr_dir, curr_data) = ch347_rs::gpio_get(dev.get_dev_index()).unwrap();
-            let dir = curr_dir | mask;
-            let data = curr_data | mask;
-            let res = ch347_rs::gpio_set(dev.get_dev_index(), mask, dir, data);
-            println!("gpio set result {:?}", res);
-            ch347_rs::close_device(args.index);
+        Commands::High => gpio.write_masked(mask, mask, mask)?,
+        Commands::Low => gpio.write_masked(mask, mask, 0)?,
+        Commands::Toggle => {
+            let (_, data) = gpio.cached();
+            gpio.write_masked(mask, mask, !data)?;
         }
-        Commands::Low => {
-            let mask = args.gpio_mask.parse::<u8>().unwrap();
-            let dev = ch347_rs::Ch347Device::new(args.index).expect("error opening device");
-            let (curr_dir, curr_data) = ch347_rs::gpio_get(dev.get_dev_index()).unwrap();
-            let dir = curr_dir | mask;
-            let data = curr_data & !mask;
-            let res = ch347_rs::gpio_set(dev.get_dev_index(), mask, dir, data);
-            println!("gpio set result {:?}", res);
-            ch347_rs::close_device(args.index);
+        Commands::Input => gpio.write_masked(mask, 0, 0)?,
+        Commands::Set { value } => {
+            let value = parse_cli_arg_number(value, false)?;
+            gpio.write_masked(mask, mask, value)?;
         }
         Commands::Read => {
-            let dev = ch347_rs::Ch347Device::new(args.index).expect("error opening device");
-            let res = ch347_rs::gpio_get(dev.get_dev_index());
-            println!("gpio get {:?}", res);
+            println!("gpio get 0x{:02X}", gpio.read()?);
+        }
+        Commands::Run { .. } => {}
+        Commands::Watch(sub_cmd) => {
+            let mut watch = gpio.watch(mask, sub_cmd.interval)?;
+            watch.duration = sub_cmd.duration;
+
+            let initial = watch.level();
+            println!(
+                "Start at {}, interval {}",
+                humantime::format_rfc3339_micros(SystemTime::now()),
+                humantime::format_duration(sub_cmd.interval),
+            );
+            for i in (0..8).filter(|i| mask & (1 << i) != 0) {
+                println!("GPIO{} {}", i, parse_gpio_data(initial, i));
+            }
+
+            let mut vcd = match &sub_cmd.vcd {
+                Some(path) => Some(VcdWriter::new(
+                    BufWriter::new(File::create(path)?),
+                    mask,
+                    initial,
+                )?),
+                None => None,
+            };
+
+            for e in watch {
+                let e = e?;
+                println!("[{:>12.6}] GPIO{} {}", e.time.as_secs_f64(), e.pin, e.edge);
+
+                if let Some(vcd) = &mut vcd {
+                    vcd.event(&e)?;
+                    // keep the file usable when stopped with Ctrl-C
+                    vcd.flush()?;
+                }
+            }
         }
     }
+
+    Ok(())
+}
+
+#[test]
+pub fn test_parse_gpio_pins() {
+    assert_eq!(parse_gpio_pins("0xFF").unwrap(), 0xFF);
+    assert_eq!(parse_gpio_pins("FFH").unwrap(), 0xFF);
+    assert_eq!(parse_gpio_pins("64").unwrap(), 64);
+    assert_eq!(parse_gpio_pins("0b0000_0011").unwrap(), 0x03);
+    assert_eq!(parse_gpio_pins("0,3,5").unwrap(), 0b0010_1001);
+    assert_eq!(parse_gpio_pins("GPIO4").unwrap(), 0x10);
+    assert_eq!(parse_gpio_pins("gpio1, GPIO2").unwrap(), 0x06);
+    assert!(parse_gpio_pins("0,8").is_err());
+    assert!(parse_gpio_pins("zz").is_err());
 }
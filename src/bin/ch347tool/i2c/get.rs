@@ -0,0 +1,64 @@
+use std::error::Error;
+
+use ch347_rs::{DataWidth, Endian, RegFormat};
+use clap::Parser;
+
+use crate::spi_flash::utils::parse_cli_arg_number;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Read a register, like i2cget")]
+pub struct CmdI2cGet {
+    /// slave address, eg. 0x50
+    #[clap(value_parser)]
+    addr: String,
+
+    /// register address, read without setting it if omitted
+    #[clap(value_parser)]
+    reg: Option<String>,
+
+    /// value width, b: byte, w: word
+    #[clap(short, long, value_enum, value_parser, default_value_t = DataWidth::Byte)]
+    width: DataWidth,
+
+    /// register address width, b: 8-bit, w: 16-bit
+    #[clap(short, long, value_enum, value_parser, default_value_t = DataWidth::Byte)]
+    reg_width: DataWidth,
+
+    /// byte order of word values
+    #[clap(short, long, value_enum, value_parser, default_value_t = Endian::Little)]
+    endian: Endian,
+}
+
+pub fn cli_i2c_get(i2c_args: &super::CmdI2c, args: &CmdI2cGet) -> Result<(), Box<dyn Error>> {
+    let addr = i2c_args.parse_addr(&args.addr)?;
+    let reg = args
+        .reg
+        .as_deref()
+        .map(|v| parse_cli_arg_number(v, false))
+        .transpose()?;
+
+    let bus = i2c_args.init()?;
+
+    let value = match reg {
+        Some(reg) => {
+            if reg > args.reg_width.max_value() {
+                return Err(format!("Register address out of range: 0x{:X}", reg).into());
+            }
+            let format = RegFormat {
+                addr_width: args.reg_width,
+                value_width: args.width,
+                endian: args.endian,
+            };
+            bus.read_reg(addr, reg, format)?
+        }
+        None => {
+            let mut rbuf = vec![0; args.width.bytes()];
+            bus.read(addr, &mut rbuf)?;
+            args.endian.from_bytes(&rbuf)
+        }
+    };
+
+    println!("0x{:0w$x}", value, w = args.width.bytes() * 2);
+
+    Ok(())
+}
@@ -0,0 +1,353 @@
+use std::error::Error;
+
+use ch347_rs::{
+    DataWidth, Endian, I2cAddress, I2cRecoveryPins, I2cSpeed, ScanMode, I2C_SCAN_RANGE,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::spi_flash::utils::parse_cli_arg_number;
+
+mod get;
+mod reg;
+mod set;
+mod xfer;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Operate i2c peripherals")]
+pub struct CmdI2c {
+    /// device number
+    #[clap(value_parser, default_value_t = 0)]
+    index: u32,
+
+    /// 20kHz, 100kHz, 400kHz, 750kHz
+    #[clap(short, long, value_enum, value_parser, default_value_t = I2cSpeed::Std)]
+    speed: I2cSpeed,
+
+    /// use 10-bit slave addresses
+    #[clap(long, value_parser, action)]
+    ten_bit: bool,
+
+    /// GPIO wired to SCL, enables bus recovery together with --sda
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..8))]
+    scl: Option<u8>,
+
+    /// GPIO wired to SDA, enables bus recovery together with --scl
+    #[clap(long, value_parser = clap::value_parser!(u8).range(0..8))]
+    sda: Option<u8>,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Get(get::CmdI2cGet),
+    /// Clock out a slave holding SDA low, needs --scl and --sda
+    Recover,
+    Reg(reg::CmdI2cReg),
+    Set(set::CmdI2cSet),
+    Xfer(xfer::CmdI2cXfer),
+}
+
+impl CmdI2c {
+    fn init(&self) -> Result<ch347_rs::I2cBus<ch347_rs::Ch347Device>, Box<dyn Error>> {
+        let device = ch347_rs::Ch347Device::new(self.index)?;
+        device.i2c_set(self.speed);
+
+        let mut bus = device.i2c();
+        bus.recovery = match (self.scl, self.sda) {
+            (Some(scl), Some(sda)) => Some(I2cRecoveryPins { scl, sda }),
+            (None, None) => None,
+            _ => return Err("--scl and --sda must be given together".into()),
+        };
+
+        Ok(bus)
+    }
+
+    /// slave address in hex(0x50, 50h), binary or decimal
+    fn parse_addr(&self, input: &str) -> Result<I2cAddress, Box<dyn Error>> {
+        if self.ten_bit {
+            Ok(I2cAddress::Ten(parse_cli_arg_number(input, false)?))
+        } else {
+            Ok(I2cAddress::Seven(parse_addr(input)?))
+        }
+    }
+}
+
+/// 7-bit slave address in hex(0x50, 50h), binary or decimal
+pub fn parse_addr(input: &str) -> Result<u8, Box<dyn Error>> {
+    match parse_cli_arg_number(input, false)? {
+        addr @ 0..=0x7F => Ok(addr),
+        _ => Err(format!("Invalid 7-bit address: {}", input).into()),
+    }
+}
+
+pub fn cli_i2c(args: &CmdI2c) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Commands::Get(sub_args) => get::cli_i2c_get(args, sub_args)?,
+        Commands::Recover => cli_i2c_recover(args)?,
+        Commands::Reg(sub_args) => reg::cli_i2c_reg(args, sub_args)?,
+        Commands::Set(sub_args) => set::cli_i2c_set(args, sub_args)?,
+        Commands::Xfer(sub_args) => xfer::cli_i2c_xfer(args, sub_args)?,
+    };
+
+    Ok(())
+}
+
+fn cli_i2c_recover(args: &CmdI2c) -> Result<(), Box<dyn Error>> {
+    let bus = args.init()?;
+    if bus.recovery.is_none() {
+        return Err("--scl and --sda are required".into());
+    }
+
+    if bus.recover()? {
+        println!("Bus recovered, SCL and SDA are high");
+        Ok(())
+    } else {
+        Err("SDA is still held low".into())
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum DetectFormat {
+    Table,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+#[clap(about = "Detects all device address on the I2C bus")]
+pub struct CmdI2cDetect {
+    /// device number
+    #[clap(value_parser)]
+    index: u32,
+
+    /// 20kHz, 100kHz, 400kHz, 750kHz
+    #[clap(value_parser)]
+    #[clap(default_value_t = I2cSpeed::Std, value_enum)]
+    speed_level: I2cSpeed,
+
+    /// probe method, auto: read byte on eeprom addresses, quick write elsewhere
+    #[clap(short, long, value_enum, value_parser, default_value_t = ScanMode::Auto)]
+    mode: ScanMode,
+
+    /// also probe the reserved addresses 0x00-0x02 and 0x78-0x7F
+    #[clap(short, long, value_parser, action)]
+    all: bool,
+
+    /// json prints the responding addresses only
+    #[clap(short, long, value_enum, value_parser, default_value_t = DetectFormat::Table)]
+    format: DetectFormat,
+}
+
+pub fn cli_i2c_detect(args: &CmdI2cDetect) -> Result<(), Box<dyn Error>> {
+    let dev = ch347_rs::Ch347Device::new(args.index)?;
+    dev.i2c_set(args.speed_level);
+    let bus = dev.i2c();
+
+    let range = if args.all {
+        0x00..=0x7F
+    } else {
+        I2C_SCAN_RANGE
+    };
+    let found = bus.scan_range(range.clone(), args.mode)?;
+
+    if let DetectFormat::Json = args.format {
+        println!("{}", serde_json::to_string_pretty(&found)?);
+        return Ok(());
+    }
+
+    println!("speed: {}", args.speed_level);
+    println!("     0  1  2  3  4  5  6  7  8  9  A  B  C  D  E  F");
+
+    for y in 0..8 {
+        let mut s = format!("{:02X}:", y * 0x10);
+        for x in 0..16 {
+            let addr = y * 0x10 + x;
+
+            if !range.contains(&addr) {
+                s.push_str("   ");
+            } else if found.contains(&addr) {
+                s.push_str(&format!(" {:02X}", addr));
+            } else {
+                s.push_str(" --");
+            }
+        }
+        println!("{}", s);
+    }
+
+    Ok(())
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum DumpPage {
+    Byte,
+    Word,
+    Full,
+}
+
+#[derive(Parser, Debug)]
+pub struct CmdI2cDump {
+    /// device number
+    #[clap(value_parser)]
+    index: u32,
+
+    /// i2c device addr(shifted), eg. 0x3C
+    #[clap(value_parser)]
+    addr: String,
+
+    /// 20kHz, 100kHz, 400kHz, 750kHz
+    #[clap(value_parser)]
+    #[clap(default_value_t = I2cSpeed::Std, value_enum)]
+    speed_level: I2cSpeed,
+
+    #[clap(value_parser)]
+    #[clap(default_value_t = DumpPage::Byte, value_enum)]
+    page: DumpPage,
+
+    /// register address width, b: 8-bit, w: 16-bit
+    #[clap(short, long, value_enum, value_parser, default_value_t = DataWidth::Byte)]
+    reg_width: DataWidth,
+
+    /// byte order of word values
+    #[clap(short, long, value_enum, value_parser, default_value_t = Endian::Little)]
+    endian: Endian,
+
+    /// first register
+    #[clap(short, long, value_parser, default_value = "0x00")]
+    start: String,
+
+    /// last register, default 0xFF
+    #[clap(long, value_parser)]
+    end: Option<String>,
+}
+
+pub fn cli_i2c_dump(args: &CmdI2cDump) {
+    println!("speed: {}", args.speed_level);
+
+    let device_addr: u8 = match parse_cli_arg_number(&args.addr, false) {
+        Ok(addr) => addr,
+        Err(err) => {
+            println!("parse device_addr error: {}", err);
+            return;
+        }
+    };
+
+    println!(
+        "device_addr: 0x{:02X}(w:0x{:02X}, r:0x{:02X})",
+        device_addr,
+        device_addr << 1,
+        (device_addr << 1) + 1
+    );
+
+    let dev = match ch347_rs::Ch347Device::new(args.index) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    let start = match parse_cli_arg_number(&args.start, false) {
+        Ok(start) => start,
+        Err(err) => {
+            println!("parse start error: {}", err);
+            return;
+        }
+    };
+    let end = match &args.end {
+        None => args.reg_width.max_value().min(0xFF),
+        Some(end) => match parse_cli_arg_number(end, false) {
+            Ok(end) => end,
+            Err(err) => {
+                println!("parse end error: {}", err);
+                return;
+            }
+        },
+    };
+    if (end < start) || (end > args.reg_width.max_value()) {
+        println!("invalid register range 0x{:X}..0x{:X}", start, end);
+        return;
+    }
+
+    dev.i2c_set(args.speed_level);
+    let bus = dev.i2c();
+    let reg_bytes = |reg: u16| Endian::Big.to_bytes(reg, args.reg_width);
+
+    let (values, value_width): (Vec<Option<u16>>, DataWidth) = match args.page {
+        DumpPage::Byte | DumpPage::Word => {
+            let value_width = match args.page {
+                DumpPage::Word => DataWidth::Word,
+                _ => DataWidth::Byte,
+            };
+
+            let values = (start..=end)
+                .map(|reg| {
+                    let mut rbuf = vec![0; value_width.bytes()];
+
+                    // register addr, then a separate read transfer
+                    bus.write(device_addr, &reg_bytes(reg)).ok()?;
+                    bus.read(device_addr, &mut rbuf).ok()?;
+
+                    Some(args.endian.from_bytes(&rbuf))
+                })
+                .collect();
+
+            (values, value_width)
+        }
+        DumpPage::Full => {
+            if let Err(e) = bus.write(device_addr, &reg_bytes(start)) {
+                println!("{}", e);
+                return;
+            }
+
+            let mut rbuf = vec![0; (end - start) as usize + 1];
+            if let Err(e) = bus.read(device_addr, &mut rbuf) {
+                println!("{}", e);
+                return;
+            }
+
+            let values = rbuf.iter().map(|v| Some(*v as u16)).collect();
+            (values, DataWidth::Byte)
+        }
+    };
+
+    print_dump(&values, start, args.reg_width, value_width);
+}
+
+/// Table of register values, 16 bytes or 8 words a row
+fn print_dump(values: &[Option<u16>], start: u16, reg_width: DataWidth, value_width: DataWidth) {
+    let cols: usize = match value_width {
+        DataWidth::Byte => 16,
+        DataWidth::Word => 8,
+    };
+    let label_width = reg_width.bytes() * 2;
+    let cell_width = value_width.bytes() * 2;
+
+    let mut s = " ".repeat(label_width + 1);
+    for x in 0..cols {
+        s.push_str(&format!(" {:>w$X}", x, w = cell_width));
+    }
+    println!("{}", s);
+
+    let start = start as usize;
+    let end = start + values.len();
+    let mut row = start - (start % cols);
+
+    while row < end {
+        let mut s = format!("{:0w$X}:", row, w = label_width);
+        for reg in row..(row + cols) {
+            let cell = if (reg < start) || (reg >= end) {
+                " ".repeat(cell_width)
+            } else {
+                match values[reg - start] {
+                    Some(v) => format!("{:0w$X}", v, w = cell_width),
+                    None => "X".repeat(cell_width),
+                }
+            };
+            s.push(' ');
+            s.push_str(&cell);
+        }
+        println!("{}", s);
+        row += cols;
+    }
+}
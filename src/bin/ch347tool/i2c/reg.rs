@@ -0,0 +1,172 @@
+use std::{
+    error::Error,
+    io::{stdin, stdout, Write},
+};
+
+use ch347_rs::{
+    Ch347Device, FindRegister, I2cAddress, I2cBus, RegisterAccess, RegisterDef, RegisterMap,
+};
+use clap::Parser;
+use cli_table::{
+    format::{Align, Justify},
+    Cell, CellStruct, Style, Table,
+};
+
+use crate::reg_table;
+use crate::spi_flash::utils::parse_cli_arg_number;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Operate named registers described in a register map file")]
+pub struct CmdI2cReg {
+    /// slave address, eg. 0x48
+    #[clap(value_parser)]
+    addr: String,
+
+    /// register map, json or yaml
+    #[clap(short, long, value_parser)]
+    map: String,
+
+    /// register or field name, eg: status, PGOOD, 0x01
+    #[clap(value_parser)]
+    register: Option<String>,
+
+    /// write value, eg: 1, 0x02
+    #[clap(value_parser)]
+    value: Option<String>,
+}
+
+pub fn cli_i2c_reg(i2c_args: &super::CmdI2c, args: &CmdI2cReg) -> Result<(), Box<dyn Error>> {
+    let addr = i2c_args.parse_addr(&args.addr)?;
+    let map = RegisterMap::load(&args.map)?;
+
+    let find_result = match &args.register {
+        None => None,
+        Some(name) => match map.find(name) {
+            None => return Err(format!("Not Found Reg: {:?}", name).into()),
+            Some(a) => Some(a),
+        },
+    };
+
+    let bus = i2c_args.init()?;
+
+    match (find_result, &args.value) {
+        (None, _) => show_registers(&bus, addr, &map, &map.registers.iter().collect::<Vec<_>>())?,
+        (Some(FindRegister::Reg(r)), None) => show_registers(&bus, addr, &map, &[r])?,
+        (Some(FindRegister::Field(r, f)), None) => {
+            let v = bus.read_register(addr, &map, r)?;
+
+            println!(
+                "{}({:?}) <= {}({}..{})",
+                f.name,
+                f.alias.join(","),
+                r.name,
+                f.offset + f.width - 1,
+                f.offset,
+            );
+            println!("Desc: {}", f.describe);
+            println!("Access: {:?}({})", f.access, f.access);
+            println!("Value: {}", reg_table::format_field_value(f, v));
+        }
+        (Some(FindRegister::Reg(r)), Some(value)) => {
+            let new_v = parse_cli_arg_number(value, false)?;
+            if new_v > map.value_width.max_value() {
+                return Err(format!("Value out of range: 0x{:X}", new_v).into());
+            }
+
+            let old_v = bus.read_register(addr, &map, r)?;
+            println!("{} Val:", r.name);
+            println!("  Old: {}", format_value(&map, old_v));
+            println!("  New: {}", format_value(&map, new_v));
+
+            bus.write_register(addr, &map, r, new_v)?;
+            println!(
+                "  Chk: {}",
+                format_value(&map, bus.read_register(addr, &map, r)?)
+            );
+        }
+        (Some(FindRegister::Field(r, f)), Some(value)) => {
+            if let RegisterAccess::ReadOnly = f.access {
+                return Err("This field is Read-only, cannot be write".into());
+            }
+
+            let new_f = parse_cli_arg_number(value, false)?;
+            let old_v = bus.read_register(addr, &map, r)?;
+            let new_v = f.insert(old_v, new_f)?;
+
+            println!(
+                "{} Val: {} => {}",
+                f.name,
+                reg_table::format_field_value(f, old_v),
+                reg_table::format_field_value(f, new_v),
+            );
+            println!("{} Val:", r.name);
+            println!("  Old: {}", format_value(&map, old_v));
+            println!("  New: {}", format_value(&map, new_v));
+
+            if let RegisterAccess::ReadWriteOTP = f.access {
+                stdout().write_all(b"OTP Reg must be confirmed(Y): ")?;
+                stdout().flush()?;
+                let mut s = String::new();
+                stdin().read_line(&mut s)?;
+                if !s.trim().to_lowercase().eq("y") {
+                    return Err("Operation must be confirmed".into());
+                }
+            }
+
+            bus.write_register(addr, &map, r, new_v)?;
+            println!(
+                "  Chk: {}",
+                format_value(&map, bus.read_register(addr, &map, r)?)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn format_value(map: &RegisterMap, v: u16) -> String {
+    let width = map.value_width.bytes() * 2;
+    format!("0x{:0width$X}", v, width = width)
+}
+
+fn show_registers(
+    bus: &I2cBus<Ch347Device>,
+    addr: I2cAddress,
+    map: &RegisterMap,
+    registers: &[&RegisterDef],
+) -> Result<(), Box<dyn Error>> {
+    let mut table = Vec::new();
+
+    for r in registers {
+        let v = bus.read_register(addr, map, r);
+
+        let value_cell: CellStruct = match &v {
+            Ok(v) => format_value(map, *v).cell(),
+            Err(e) => console::style(e.to_string()).red().cell(),
+        };
+
+        table.push(vec![
+            r.name.as_str().cell().align(Align::Center),
+            format!("0x{:02X}", r.addr).cell().align(Align::Center),
+            value_cell.align(Align::Center),
+            match (&v, r.items.is_empty()) {
+                (Ok(v), false) => reg_table::show_register_field_table(&r.items, *v)
+                    .display()?
+                    .cell()
+                    .justify(Justify::Left),
+                _ => r.describe.as_str().cell(),
+            },
+        ]);
+    }
+
+    let table = table.table().title(vec![
+        "Name".cell().bold(true),
+        "Addr".cell().bold(true),
+        "Value".cell().bold(true),
+        "Item".cell().bold(true),
+    ]);
+
+    println!("{}", table.display()?);
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::error::Error;
+
+use ch347_rs::{DataWidth, Endian, RegFormat};
+use clap::Parser;
+
+use crate::spi_flash::utils::parse_cli_arg_number;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Write a register, like i2cset")]
+pub struct CmdI2cSet {
+    /// slave address, eg. 0x50
+    #[clap(value_parser)]
+    addr: String,
+
+    /// register address
+    #[clap(value_parser)]
+    reg: String,
+
+    /// value to write, only the register address is sent if omitted
+    #[clap(value_parser)]
+    value: Option<String>,
+
+    /// value width, b: byte, w: word
+    #[clap(short, long, value_enum, value_parser, default_value_t = DataWidth::Byte)]
+    width: DataWidth,
+
+    /// register address width, b: 8-bit, w: 16-bit
+    #[clap(short, long, value_enum, value_parser, default_value_t = DataWidth::Byte)]
+    reg_width: DataWidth,
+
+    /// byte order of word values
+    #[clap(short, long, value_enum, value_parser, default_value_t = Endian::Little)]
+    endian: Endian,
+}
+
+pub fn cli_i2c_set(i2c_args: &super::CmdI2c, args: &CmdI2cSet) -> Result<(), Box<dyn Error>> {
+    let addr = i2c_args.parse_addr(&args.addr)?;
+    let reg = parse_cli_arg_number(&args.reg, false)?;
+    if reg > args.reg_width.max_value() {
+        return Err(format!("Register address out of range: 0x{:X}", reg).into());
+    }
+
+    let value = args
+        .value
+        .as_deref()
+        .map(|v| parse_cli_arg_number(v, false))
+        .transpose()?;
+    if let Some(value) = value {
+        if value > args.width.max_value() {
+            return Err(format!("Value out of range: 0x{:X}", value).into());
+        }
+    }
+
+    let bus = i2c_args.init()?;
+    let format = RegFormat {
+        addr_width: args.reg_width,
+        value_width: args.width,
+        endian: args.endian,
+    };
+
+    match value {
+        Some(value) => bus.write_reg(addr, reg, value, format)?,
+        None => bus.write(addr, &Endian::Big.to_bytes(reg, args.reg_width))?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use std::error::Error;
+
+use ch347_rs::I2cOp;
+use clap::Parser;
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Run a scripted transaction, prints the data of every read step")]
+pub struct CmdI2cXfer {
+    /// slave address, eg. 0x40
+    #[clap(value_parser)]
+    addr: String,
+
+    /// steps: w:<hex> write, r:<len> read, delay:<ms> hardware delay before
+    /// the next step. A write directly followed by a read uses a repeated start.
+    /// eg. w:F3 delay:20 r:3
+    #[clap(value_parser, required = true)]
+    steps: Vec<String>,
+}
+
+pub fn cli_i2c_xfer(i2c_args: &super::CmdI2c, args: &CmdI2cXfer) -> Result<(), Box<dyn Error>> {
+    let addr = i2c_args.parse_addr(&args.addr)?;
+    let ops = args
+        .steps
+        .iter()
+        .map(|s| s.parse::<I2cOp>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let bus = i2c_args.init()?;
+    let ret = bus.transaction(addr, &ops)?;
+
+    for (i, rbuf) in ret.iter().enumerate() {
+        println!("R{}: {:02X?}", i, rbuf);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::error::Error;
+
+use clap::{Parser, Subcommand};
+use cli_table::{Cell, Style, Table};
+
+#[derive(Parser, Debug)]
+#[clap(about = "JTAG of a CH347 in mode 3")]
+pub struct CmdJtag {
+    /// device number
+    #[clap(value_parser)]
+    index: u32,
+
+    /// TCK level 0~5: 1.875MHz, 3.75MHz, 7.5MHz, 15MHz, 30MHz, 60MHz
+    #[clap(short, long, value_parser, default_value_t = 0)]
+    clock: u8,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Detect the chain and decode the IDCODEs, the device nearest TDO first
+    Scan,
+}
+
+pub fn cli_jtag(args: &CmdJtag) -> Result<(), Box<dyn Error>> {
+    let jtag = ch347_rs::Ch347Device::new(args.index)?.jtag(args.clock)?;
+
+    match &args.command {
+        Commands::Scan => {
+            let devices = jtag.detect_chain()?;
+            if devices.is_empty() {
+                println!("No JTAG device found");
+                return Ok(());
+            }
+
+            let table = devices
+                .iter()
+                .enumerate()
+                .map(|(i, d)| {
+                    let ir_len = match d.ir_len {
+                        None => "unknown".to_string(),
+                        Some(len) if d.ir_len_guessed => format!("{}?", len),
+                        Some(len) => len.to_string(),
+                    };
+
+                    match d.idcode {
+                        Some(id) => vec![
+                            i.cell(),
+                            id.to_string().cell(),
+                            id.manufacturer_name().unwrap_or("Unknown").cell(),
+                            d.chip
+                                .as_ref()
+                                .map_or(format!("0x{:04X}", id.part()), |c| c.name.clone())
+                                .cell(),
+                            id.version().cell(),
+                            ir_len.cell(),
+                        ],
+                        None => vec![
+                            i.cell(),
+                            "BYPASS".cell(),
+                            "".cell(),
+                            "".cell(),
+                            "".cell(),
+                            ir_len.cell(),
+                        ],
+                    }
+                })
+                .collect::<Vec<_>>()
+                .table()
+                .title(vec![
+                    "#".cell().bold(true),
+                    "IDCODE".cell().bold(true),
+                    "Manufacturer".cell().bold(true),
+                    "Part".cell().bold(true),
+                    "Ver".cell().bold(true),
+                    "IR len".cell().bold(true),
+                ]);
+
+            println!("{}", table.display()?);
+            if devices.iter().any(|d| d.ir_len_guessed) {
+                println!("?: IR length taken from the capture pattern");
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -55,7 +55,7 @@ impl Into<DeviceInfo> for ch347_rs::DeviceInfo {
 pub fn cli_list_device(args: &CmdListDevice) {
     let mut l: Vec<DeviceInfo> = Vec::new();
 
-    for i in ch347_rs::enum_ch347_device() {
+    for i in ch347_rs::enum_device() {
         if let Some(info) = i.get_raw_info() {
             l.push(info.into());
         }
@@ -65,6 +65,11 @@ pub fn cli_list_device(args: &CmdListDevice) {
         close_device(i.index as u32);
     }
 
+    // listed without opening, a tty open would toggle DTR/RTS
+    for info in ch347_rs::enum_uart_info() {
+        l.push(info.into());
+    }
+
     match args.format {
         ListFormat::Tree => {
             println!("'Ch347 device list:");
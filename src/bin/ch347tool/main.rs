@@ -1,9 +1,17 @@
 use std::error::Error;
 
+mod eeprom;
 mod gpio;
 mod i2c;
+mod jtag;
 mod list;
+mod onewire;
+mod pmbus;
+mod reg_table;
+mod smbus;
+mod spi;
 mod spi_flash;
+mod uart;
 
 use clap::{Parser, Subcommand};
 use shadow_rs::shadow;
@@ -21,21 +29,36 @@ struct Cli {
 enum Commands {
     List(list::CmdListDevice),
     Info,
-    Spi,
+    Spi(spi::CmdSpi),
     SpiFlash(spi_flash::CmdSpiFlash),
+    I2c(i2c::CmdI2c),
+    Smbus(smbus::CmdSmbus),
+    Pmbus(pmbus::CmdPmbus),
     I2cDetect(i2c::CmdI2cDetect),
     I2cDump(i2c::CmdI2cDump),
     Gpio(gpio::CmdGpio),
+    Onewire(onewire::CmdOneWire),
+    Jtag(jtag::CmdJtag),
+    Eeprom(eeprom::CmdEeprom),
+    Uart(uart::CmdUart),
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
     match &cli.command {
         Commands::List(args) => list::cli_list_device(args),
-        Commands::Gpio(args) => gpio::cli_operator_gpio(args),
-        Commands::I2cDetect(args) => i2c::cli_i2c_detect(args),
+        Commands::Gpio(args) => gpio::cli_operator_gpio(args)?,
+        Commands::Onewire(args) => onewire::cli_onewire(args)?,
+        Commands::Jtag(args) => jtag::cli_jtag(args)?,
+        Commands::I2c(args) => i2c::cli_i2c(args)?,
+        Commands::Smbus(args) => smbus::cli_smbus(args)?,
+        Commands::Pmbus(args) => pmbus::cli_pmbus(args)?,
+        Commands::I2cDetect(args) => i2c::cli_i2c_detect(args)?,
         Commands::I2cDump(args) => i2c::cli_i2c_dump(args),
+        Commands::Spi(args) => spi::cli_spi(args)?,
         Commands::SpiFlash(args) => spi_flash::cli_spi_flash(args)?,
+        Commands::Eeprom(args) => eeprom::cli_eeprom(args)?,
+        Commands::Uart(args) => uart::cli_uart(args)?,
         _ => {
             return Err("undefined command".into());
         }
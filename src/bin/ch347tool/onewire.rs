@@ -0,0 +1,85 @@
+use std::error::Error;
+
+use ch347_rs::{Ch347Uart, OneWire, UartConfig, UartOneWire, DS18B20_FAMILY};
+use clap::{Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[clap(about = "1-Wire bus on a uart, TXD drives DQ open drain and RXD reads it back")]
+pub struct CmdOneWire {
+    /// uart number, see `list`
+    #[clap(value_parser, default_value_t = 0)]
+    index: u32,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// List the ROM codes of all devices
+    Search,
+    /// Read DS18B20 temperatures, all found ones if no ROM code is given
+    Temp {
+        /// ROM code as printed by search, eg. 28FF641E0F1A0317
+        #[clap(long)]
+        rom: Option<String>,
+    },
+}
+
+/// ROM bytes in bus order, family code first
+fn format_rom(rom: u64) -> String {
+    hex::encode_upper(rom.to_le_bytes())
+}
+
+fn parse_rom(input: &str) -> Result<u64, Box<dyn Error>> {
+    let bytes: [u8; 8] = hex::decode(input)?
+        .try_into()
+        .map_err(|_| "ROM code must be 8 bytes")?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub fn cli_onewire(args: &CmdOneWire) -> Result<(), Box<dyn Error>> {
+    let uart = Ch347Uart::open(args.index, &UartConfig::default())?;
+    let bus = OneWire::new(UartOneWire::new(uart)?);
+
+    match &args.command {
+        Commands::Search => {
+            let roms = bus.search()?;
+            if roms.is_empty() {
+                println!("No 1-Wire device found");
+            }
+            for rom in roms {
+                println!("{}", format_rom(rom));
+            }
+        }
+        Commands::Temp { rom } => {
+            let roms = match rom {
+                Some(rom) => vec![parse_rom(rom)?],
+                None => bus
+                    .search()?
+                    .into_iter()
+                    .filter(|&r| r as u8 == DS18B20_FAMILY)
+                    .collect(),
+            };
+
+            if roms.is_empty() {
+                return Err("No DS18B20 found".into());
+            }
+
+            for rom in roms {
+                match bus.ds18b20_temperature(Some(rom)) {
+                    Ok(t) => println!("{} {:.4} °C", format_rom(rom), t),
+                    Err(e) => println!(
+                        "{} {}: {}",
+                        format_rom(rom),
+                        console::style("error").red(),
+                        e
+                    ),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+use std::error::Error;
+
+use ch347_rs::{DirectCoefficients, I2cSpeed, Pmbus, PmbusError, PMBUS_STATUS_WORD_BITS};
+use clap::{Parser, Subcommand};
+use cli_table::{format::Justify, Cell, Style, Table};
+
+use crate::{
+    i2c::parse_addr,
+    spi_flash::utils::{self, parse_cli_arg_number},
+};
+
+#[derive(Parser, Debug)]
+#[clap(about = "PMBus power supply telemetry")]
+pub struct CmdPmbus {
+    /// device number
+    #[clap(value_parser, default_value_t = 0)]
+    index: u32,
+
+    /// 20kHz, 100kHz, 400kHz, 750kHz
+    #[clap(short, long, value_enum, value_parser, default_value_t = I2cSpeed::Std)]
+    speed: I2cSpeed,
+
+    /// append and verify Packet Error Checking bytes
+    #[clap(short, long, value_parser, action)]
+    pec: bool,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Status(CmdPmbusStatus),
+}
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Print telemetry and decoded STATUS_WORD")]
+pub struct CmdPmbusStatus {
+    /// slave address, eg. 0x40
+    #[clap(value_parser)]
+    addr: String,
+
+    /// DIRECT format coefficients of a command, <cmd>=<m>,<b>,<R>
+    /// eg. 0x8C=200,0,-2, may be repeated
+    #[clap(short, long, value_parser)]
+    direct: Vec<String>,
+}
+
+/// "<cmd>=<m>,<b>,<R>"
+fn parse_direct(input: &str) -> Result<(u8, DirectCoefficients), Box<dyn Error>> {
+    let err = || format!("Invalid coefficients: {}", input);
+
+    let (cmd, coeff) = input.split_once('=').ok_or_else(err)?;
+    let coeff: Vec<&str> = coeff.split(',').map(|s| s.trim()).collect();
+    if coeff.len() != 3 {
+        return Err(err().into());
+    }
+
+    let cmd = parse_cli_arg_number(cmd.trim(), false)?;
+
+    Ok((
+        cmd,
+        DirectCoefficients {
+            m: coeff[0].parse()?,
+            b: coeff[1].parse()?,
+            r: coeff[2].parse()?,
+        },
+    ))
+}
+
+pub fn cli_pmbus(args: &CmdPmbus) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Commands::Status(sub_args) => cli_pmbus_status(args, sub_args)?,
+    };
+
+    Ok(())
+}
+
+fn cli_pmbus_status(pmbus_args: &CmdPmbus, args: &CmdPmbusStatus) -> Result<(), Box<dyn Error>> {
+    let addr = parse_addr(&args.addr)?;
+
+    let device = ch347_rs::Ch347Device::new(pmbus_args.index)?;
+    device.i2c_set(pmbus_args.speed);
+
+    let mut smbus = device.smbus();
+    smbus.pec = pmbus_args.pec;
+
+    let mut pmbus = Pmbus::new(smbus, addr);
+    for d in &args.direct {
+        let (cmd, coeff) = parse_direct(d)?;
+        pmbus.direct.insert(cmd, coeff);
+    }
+
+    let value_cell = |v: Result<f64, PmbusError>, unit: &str| match v {
+        Ok(v) => format!("{:.3} {}", v, unit).cell().justify(Justify::Right),
+        Err(e) => console::style(e.to_string()).red().cell(),
+    };
+
+    let table = vec![
+        vec![
+            "VOUT_MODE".cell(),
+            match pmbus.vout_mode() {
+                Ok(m) => m.to_string().cell(),
+                Err(e) => console::style(e.to_string()).red().cell(),
+            },
+        ],
+        vec!["READ_VIN".cell(), value_cell(pmbus.read_vin(), "V")],
+        vec!["READ_VOUT".cell(), value_cell(pmbus.read_vout(), "V")],
+        vec!["READ_IOUT".cell(), value_cell(pmbus.read_iout(), "A")],
+        vec![
+            "READ_TEMPERATURE_1".cell(),
+            value_cell(pmbus.read_temperature_1(), "°C"),
+        ],
+    ]
+    .table()
+    .title(vec!["Name".cell().bold(true), "Value".cell().bold(true)]);
+
+    println!("{}", table.display()?);
+
+    let status = pmbus.status_word()?;
+    println!("STATUS_WORD: 0x{:04X}", status);
+
+    let table = PMBUS_STATUS_WORD_BITS
+        .iter()
+        .map(|(bit, name)| {
+            vec![
+                bit.cell(),
+                name.cell(),
+                utils::display_bool_with_color(status & (1 << bit) != 0).cell(),
+            ]
+        })
+        .collect::<Vec<_>>()
+        .table()
+        .title(vec![
+            "Bit".cell().bold(true),
+            "Name".cell().bold(true),
+            "Val".cell().bold(true),
+        ]);
+
+    println!("{}", table.display()?);
+
+    Ok(())
+}
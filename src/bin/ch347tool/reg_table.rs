@@ -0,0 +1,78 @@
+use ch347_rs::{RegisterAccess, RegisterField};
+use cli_table::{Cell, CellStruct, Style, Table, TableStruct};
+
+use crate::spi_flash::utils;
+
+/// Field value, colored bool for single bits, binary otherwise
+pub fn format_field_value(field: &RegisterField, reg_value: u16) -> String {
+    let v = field.extract(reg_value);
+
+    if field.width == 1 {
+        utils::display_bool_with_color(v != 0)
+    } else {
+        let width = field.width as usize;
+        format!("{:0>width$b}'b{}", v, width)
+    }
+}
+
+pub fn access_cell(access: RegisterAccess) -> CellStruct {
+    console::style(access.to_string())
+        .bg(match access {
+            RegisterAccess::ReadOnly => console::Color::Black,
+            RegisterAccess::ReadWrite => console::Color::Blue,
+            RegisterAccess::ReadWriteOTP => console::Color::Yellow,
+        })
+        .cell()
+}
+
+/// One column per field, most significant first
+pub fn show_register_field_table(r: &[RegisterField], v: u16) -> TableStruct {
+    let mut items_table = Vec::new();
+
+    // name line
+    let mut items_name_table = Vec::new();
+    items_name_table.push("Name".cell().bold(true));
+    for ri in r.iter().rev() {
+        items_name_table.push(ri.name.as_str().cell());
+    }
+    items_table.push(items_name_table);
+
+    // bit line
+    let mut items_posion_table = Vec::new();
+    items_posion_table.push("Bit".cell().bold(true));
+    for ri in r.iter().rev() {
+        items_posion_table.push(
+            if ri.width == 1 {
+                format!("{}", ri.offset)
+            } else {
+                format!("{}..{}", ri.offset + ri.width - 1, ri.offset)
+            }
+            .cell(),
+        );
+    }
+    items_table.push(items_posion_table);
+
+    let mut items_desc_table = Vec::new();
+    items_desc_table.push("Desc".cell().bold(true));
+    for ri in r.iter().rev() {
+        items_desc_table.push(ri.describe.as_str().cell());
+    }
+    items_table.push(items_desc_table);
+
+    // value line
+    let mut items_val_table = Vec::new();
+    items_val_table.push("Val".cell().bold(true));
+    for ri in r.iter().rev() {
+        items_val_table.push(format_field_value(ri, v).cell());
+    }
+    items_table.push(items_val_table);
+
+    let mut items_access_table = Vec::new();
+    items_access_table.push("Access".cell().bold(true));
+    for ri in r.iter().rev() {
+        items_access_table.push(access_cell(ri.access));
+    }
+    items_table.push(items_access_table);
+
+    items_table.table()
+}
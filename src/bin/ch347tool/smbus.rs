@@ -0,0 +1,125 @@
+use std::error::Error;
+
+use ch347_rs::I2cSpeed;
+use clap::{Parser, Subcommand};
+
+use crate::{i2c::parse_addr, spi_flash::utils::parse_cli_arg_number};
+
+#[derive(Parser, Debug)]
+#[clap(about = "SMBus transactions")]
+pub struct CmdSmbus {
+    /// device number
+    #[clap(value_parser, default_value_t = 0)]
+    index: u32,
+
+    /// 20kHz, 100kHz, 400kHz, 750kHz
+    #[clap(short, long, value_enum, value_parser, default_value_t = I2cSpeed::Std)]
+    speed: I2cSpeed,
+
+    /// append and verify Packet Error Checking bytes
+    #[clap(short, long, value_parser, action)]
+    pec: bool,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Quick command, address only
+    Quick { addr: String },
+    /// Send byte without command code
+    Send { addr: String, value: String },
+    /// Receive byte without command code
+    Recv { addr: String },
+    /// Write byte data
+    WriteByte {
+        addr: String,
+        cmd: String,
+        value: String,
+    },
+    /// Read byte data
+    ReadByte { addr: String, cmd: String },
+    /// Write word data, little endian
+    WriteWord {
+        addr: String,
+        cmd: String,
+        value: String,
+    },
+    /// Read word data, little endian
+    ReadWord { addr: String, cmd: String },
+    /// Process call, write a word and read the reply word
+    Call {
+        addr: String,
+        cmd: String,
+        value: String,
+    },
+    /// Block write, data in hex eg. 0102ff
+    BlockWrite {
+        addr: String,
+        cmd: String,
+        data: String,
+    },
+    /// Block read
+    BlockRead { addr: String, cmd: String },
+}
+
+pub fn cli_smbus(args: &CmdSmbus) -> Result<(), Box<dyn Error>> {
+    let device = ch347_rs::Ch347Device::new(args.index)?;
+    device.i2c_set(args.speed);
+
+    let mut smbus = device.smbus();
+    smbus.pec = args.pec;
+
+    match &args.command {
+        Commands::Quick { addr } => smbus.quick(parse_addr(addr)?)?,
+        Commands::Send { addr, value } => {
+            smbus.send_byte(parse_addr(addr)?, parse_cli_arg_number(value, false)?)?
+        }
+        Commands::Recv { addr } => {
+            println!("0x{:02x}", smbus.receive_byte(parse_addr(addr)?)?);
+        }
+        Commands::WriteByte { addr, cmd, value } => smbus.write_byte_data(
+            parse_addr(addr)?,
+            parse_cli_arg_number(cmd, false)?,
+            parse_cli_arg_number(value, false)?,
+        )?,
+        Commands::ReadByte { addr, cmd } => {
+            println!(
+                "0x{:02x}",
+                smbus.read_byte_data(parse_addr(addr)?, parse_cli_arg_number(cmd, false)?)?
+            );
+        }
+        Commands::WriteWord { addr, cmd, value } => smbus.write_word_data(
+            parse_addr(addr)?,
+            parse_cli_arg_number(cmd, false)?,
+            parse_cli_arg_number(value, false)?,
+        )?,
+        Commands::ReadWord { addr, cmd } => {
+            println!(
+                "0x{:04x}",
+                smbus.read_word_data(parse_addr(addr)?, parse_cli_arg_number(cmd, false)?)?
+            );
+        }
+        Commands::Call { addr, cmd, value } => {
+            println!(
+                "0x{:04x}",
+                smbus.process_call(
+                    parse_addr(addr)?,
+                    parse_cli_arg_number(cmd, false)?,
+                    parse_cli_arg_number(value, false)?
+                )?
+            );
+        }
+        Commands::BlockWrite { addr, cmd, data } => {
+            let data = hex::decode(data.replace(' ', "").trim_start_matches("0x"))?;
+            smbus.block_write(parse_addr(addr)?, parse_cli_arg_number(cmd, false)?, &data)?
+        }
+        Commands::BlockRead { addr, cmd } => {
+            let data = smbus.block_read(parse_addr(addr)?, parse_cli_arg_number(cmd, false)?)?;
+            println!("{} bytes: {:02X?}", data.len(), data);
+        }
+    };
+
+    Ok(())
+}
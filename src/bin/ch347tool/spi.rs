@@ -0,0 +1,91 @@
+use std::error::Error;
+
+use ch347_rs::{BitOrder, ChipSelect, SpiMode};
+use clap::{Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[clap(about = "Operate generic spi peripherals")]
+pub struct CmdSpi {
+    /// device number
+    #[clap(value_parser, default_value_t = 0)]
+    index: u32,
+
+    /// chip select
+    #[clap(value_enum, value_parser, default_value_t = ChipSelect::CS0)]
+    cs: ChipSelect,
+
+    /// chip select is active high
+    #[clap(long, value_parser, action)]
+    cs_high: bool,
+
+    /// clock freq, 0=60MHz 1=30MHz 2=15MHz 3=7.5MHz 4=3.75MHz 5=1.875MHz 6=937.5KHz 7=468.75KHz
+    #[clap(short, long, value_parser, default_value_t = 2)]
+    freq: u8,
+
+    /// spi mode
+    #[clap(short, long, value_enum, value_parser, default_value_t = SpiMode::Mode0)]
+    mode: SpiMode,
+
+    /// bit order
+    #[clap(short, long, value_enum, value_parser, default_value_t = BitOrder::MsbFirst)]
+    bit_order: BitOrder,
+
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Xfer(CmdSpiXfer),
+}
+
+#[derive(Parser, Clone, Debug)]
+#[clap(about = "Full duplex transfer, prints the bytes received on MISO")]
+pub struct CmdSpiXfer {
+    /// hex bytes to send on MOSI, eg. 9f00000000 or "9f 00 00"
+    #[clap(value_parser)]
+    data: String,
+}
+
+impl CmdSpi {
+    fn init(&self) -> Result<ch347_rs::Ch347Spi, Box<dyn Error>> {
+        let clock_level = match ch347_rs::SpiClockLevel::from_byte(self.freq) {
+            None => {
+                return Err(format!("Unknow SPI clock level: {}", self.freq).into());
+            }
+            Some(level) => level,
+        };
+
+        let device = ch347_rs::Ch347Device::new(self.index)?;
+
+        let mut spi = device.spi()?;
+        spi.set_chip_select(self.cs, self.cs_high)?;
+        spi.set_clock(clock_level)?;
+        spi.set_mode(self.mode)?;
+        spi.set_bit_order(self.bit_order)?;
+
+        Ok(spi)
+    }
+}
+
+pub fn cli_spi(args: &CmdSpi) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Commands::Xfer(sub_args) => {
+            let data: String = sub_args
+                .data
+                .trim_start_matches("0x")
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != '_')
+                .collect();
+            let mut iobuf = hex::decode(data)?;
+
+            let spi = args.init()?;
+
+            println!("MOSI: {:02X?}", iobuf);
+            spi.transfer(&mut iobuf)?;
+            println!("MISO: {:02X?}", iobuf);
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use std::error::Error;
 
-use clap::{Parser, Subcommand, ValueEnum};
+use ch347_rs::ChipSelect;
+use clap::{Parser, Subcommand};
 
-mod utils;
+use crate::gpio;
+
+pub mod utils;
 
 mod check;
 mod detect;
@@ -19,23 +22,30 @@ pub struct CmdSpiFlash {
     index: u32,
 
     /// chip select
-    #[clap(value_enum, value_parser,default_value_t=CS::CS0)]
-    cs: CS,
+    #[clap(value_enum, value_parser,default_value_t=ChipSelect::CS0)]
+    cs: ChipSelect,
+
+    /// chip select is active high
+    #[clap(long, value_parser, action)]
+    cs_high: bool,
+
+    /// clock freq, 0=60MHz 1=30MHz 2=15MHz 3=7.5MHz 4=3.75MHz 5=1.875MHz 6=937.5KHz 7=468.75KHz,
+    /// auto=fastest clock at which the chip reads back consistently
+    #[clap(short, long, value_parser, default_value = "2")]
+    freq: String,
 
-    /// clock freq, 0=60MHz 1=30MHz 2=15MHz 3=7.5MHz 4=3.75MHz 5=1.875MHz 6=937.5KHz 7=468.75KHz
-    #[clap(short, long, value_parser, default_value_t = 2)]
-    freq: u8,
+    /// gpio sequence file to run before the command, eg. to hold the target in reset
+    #[clap(long)]
+    pre_seq: Option<String>,
+
+    /// gpio sequence file to run after the command, also when it failed
+    #[clap(long)]
+    post_seq: Option<String>,
 
     #[clap(subcommand)]
     command: Commands,
 }
 
-#[derive(ValueEnum, Subcommand, Clone, Debug)]
-enum CS {
-    CS0,
-    CS1,
-}
-
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     Detect(detect::CmdSpiFlashDetect),
@@ -50,20 +60,45 @@ impl CmdSpiFlash {
     pub fn init(
         &self,
     ) -> Result<(ch347_rs::SpiFlash<ch347_rs::Ch347Device>, ch347_rs::Chip), Box<dyn Error>> {
-        let clock_level = match ch347_rs::SpiClockLevel::from_byte(self.freq) {
-            None => {
-                return Err(format!("Unknow SPI clock level: {}", self.freq).into());
+        let auto_freq = self.freq.eq_ignore_ascii_case("auto");
+
+        let clock_level = if auto_freq {
+            ch347_rs::SpiClockLevel::S60M
+        } else {
+            let level = self
+                .freq
+                .parse::<u8>()
+                .ok()
+                .and_then(ch347_rs::SpiClockLevel::from_byte);
+
+            match level {
+                None => {
+                    return Err(format!("Unknow SPI clock level: {}", self.freq).into());
+                }
+                Some(level) => level,
             }
-            Some(level) => level,
         };
-        println!("Select SPI Clock: {}", clock_level);
+
+        if !auto_freq {
+            println!("Select SPI Clock: {}", clock_level);
+        }
+
+        println!("Select Chip Select: {}", self.cs);
 
         let mut device = ch347_rs::Ch347Device::new(self.index)?;
+        device.set_chip_select(self.cs, self.cs_high);
         device.change_spi_raw_config(|spi_cfg| {
             spi_cfg.byte_order = 1;
-            spi_cfg.clock = self.freq;
+            spi_cfg.clock = clock_level.to_byte();
         })?;
-        let device = device.spi_flash()?;
+        let mut device = device.spi_flash()?;
+
+        if auto_freq {
+            const SAMPLE_SIZE: usize = 4096;
+
+            let clock_level = device.auto_clock(SAMPLE_SIZE)?;
+            println!("Auto Select SPI Clock: {}", clock_level);
+        }
 
         let chip_info = match device.detect() {
             Err(e) => return Err(e.into()),
@@ -86,6 +121,31 @@ impl CmdSpiFlash {
 }
 
 pub fn cli_spi_flash(args: &CmdSpiFlash) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = &args.pre_seq {
+        gpio::run_sequence_file(args.index, path)?;
+    }
+
+    let ret = run_command(args);
+
+    // the post sequence always runs, but the command error comes first
+    let post_ret = match &args.post_seq {
+        Some(path) => gpio::run_sequence_file(args.index, path),
+        None => Ok(()),
+    };
+    match (ret, post_ret) {
+        (Err(e), Err(post_e)) => {
+            println!(
+                "{} post sequence: {}",
+                console::style("Error:").red(),
+                post_e
+            );
+            Err(e)
+        }
+        (ret, post_ret) => ret.and(post_ret),
+    }
+}
+
+fn run_command(args: &CmdSpiFlash) -> Result<(), Box<dyn Error>> {
     match &args.command {
         Commands::Detect(sub_args) => detect::cli_spi_flash_detect(args, sub_args)?,
         Commands::Erase(sub_args) => erase::cli_spi_flash_erase(args, sub_args)?,
@@ -16,6 +16,14 @@ pub struct CmdSpiFlashRead {
     /// output to file
     #[clap(value_parser)]
     file: String,
+
+    /// Read each sector N times and compare, unstable bits are reported to <file>.unstable
+    #[clap(short, long, value_parser, default_value_t = 1)]
+    passes: usize,
+
+    /// Max re-reads of a sector whose passes disagree
+    #[clap(short, long, value_parser, default_value_t = 3)]
+    retries: usize,
 }
 
 pub fn cli_spi_flash_read(
@@ -26,24 +34,44 @@ pub fn cli_spi_flash_read(
 
     let chip_capacity: usize = chip_info.capacity.into();
 
-    let mut all_buf: Vec<u8> = Vec::new();
+    let mut all_buf: Vec<u8> = vec![0; chip_capacity];
     let pb = ProgressBar::new(chip_capacity as u64);
     pb.set_style(ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}) ({eta})")
         .unwrap()
         .with_key("eta", |state: &ProgressState, w: &mut dyn Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
         .progress_chars("#>-"));
 
-    println!("Reading ...");
+    if args.passes > 1 {
+        println!("Reading with {} passes ...", args.passes);
+    } else {
+        println!("Reading ...");
+    }
     let start_time = SystemTime::now();
 
-    const BLOCK_SIZE: usize = 4096;
+    let unstable = device.read_consistent(
+        |e| match e {
+            ch347_rs::ReadEvent::Block(addr, count) => pb.set_position((addr + count) as u64),
+            ch347_rs::ReadEvent::Retry(addr, cnt) => pb.println(format!(
+                "{} sector 0x{:04X}_{:04X} differs between passes, re-read #{}",
+                console::style("Warn:").yellow(),
+                addr >> 16,
+                addr & 0xFFFF,
+                cnt
+            )),
+            ch347_rs::ReadEvent::Unstable(addr, cnt) => pb.println(format!(
+                "{} sector 0x{:04X}_{:04X} has {} unstable bytes",
+                console::style("Error:").red(),
+                addr >> 16,
+                addr & 0xFFFF,
+                cnt
+            )),
+        },
+        0,
+        &mut all_buf,
+        args.passes,
+        args.retries,
+    )?;
 
-    for i in 0..(chip_capacity / BLOCK_SIZE) {
-        let mut rbuf: [u8; BLOCK_SIZE] = [0; BLOCK_SIZE];
-        device.read((i * BLOCK_SIZE) as u32, &mut rbuf);
-        all_buf.extend_from_slice(&rbuf);
-        pb.set_position((i * BLOCK_SIZE) as u64);
-    }
     let take_time = start_time.elapsed().unwrap().as_millis();
     let take_time = Duration::from_millis(take_time as u64);
     pb.finish_and_clear();
@@ -53,5 +81,18 @@ pub fn cli_spi_flash_read(
     let speed = (all_buf.len() as f64) / take_time.as_secs_f64();
     println!("{}", format_byte_per_sec(speed));
 
+    if !unstable.is_empty() {
+        let report_file = format!("{}.unstable", args.file);
+        let report: Vec<String> = unstable.iter().map(|i| i.to_string()).collect();
+        fs::write(report_file.as_str(), report.join("\n") + "\n")?;
+
+        return Err(format!(
+            "{} unstable bytes could not be read consistently, see {}",
+            unstable.len(),
+            report_file
+        )
+        .into());
+    }
+
     Ok(())
 }
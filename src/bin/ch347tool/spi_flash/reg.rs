@@ -10,6 +10,7 @@ use cli_table::{
 };
 
 use super::utils;
+use crate::reg_table;
 
 #[derive(Parser, Clone, Debug)]
 #[clap(about = "Operate chip registers")]
@@ -67,86 +68,14 @@ pub fn cli_main(flash_args: &super::CmdSpiFlash, args: &CmdReg) -> Result<(), Bo
 }
 
 fn show_register_item_table(r: &[ch347_rs::RegisterItem], v: ch347_rs::RegReadRet) -> TableStruct {
-    let mut items_table = Vec::new();
+    let fields: Vec<ch347_rs::RegisterField> = r.iter().map(|ri| ri.into()).collect();
 
-    // name line
-    let mut items_name_table = Vec::new();
-    items_name_table.push("Name".cell().bold(true));
-    for ri in r.iter().rev() {
-        items_name_table.push(ri.name.cell());
-    }
-    items_table.push(items_name_table);
-
-    // bit line
-    let mut items_posion_table = Vec::new();
-    items_posion_table.push("Bit".cell().bold(true));
-    for ri in r.iter().rev() {
-        items_posion_table.push(
-            if ri.width == 1 {
-                format!("{}", ri.offset)
-            } else {
-                format!("{}..{}", ri.offset + ri.width - 1, ri.offset)
-            }
-            .cell(),
-        );
-    }
-    items_table.push(items_posion_table);
-
-    let mut items_desc_table = Vec::new();
-    items_desc_table.push("Desc".cell().bold(true));
-    for ri in r.iter().rev() {
-        items_desc_table.push(ri.describe.cell());
-    }
-    items_table.push(items_desc_table);
-
-    // value line
-    let mut items_val_table = Vec::new();
-    items_val_table.push("Val".cell().bold(true));
-    for ri in r.iter().rev() {
-        items_val_table.push(
-            match v {
-                ch347_rs::RegReadRet::One(a) => {
-                    if ri.width == 1 {
-                        utils::display_bool_with_color(a & (1 << ri.offset) != 0)
-                    } else {
-                        let mut v: u8 = 0;
-
-                        for i in (ri.offset..(ri.offset + ri.width)).rev() {
-                            v <<= 1;
-                            if a & (1 << i) != 0 {
-                                v |= 1;
-                            }
-                        }
-
-                        let width = ri.width as usize;
-                        format!("{:0>width$b}'b{}", v, width)
-                    }
-                }
-                ch347_rs::RegReadRet::Muti(_) => {
-                    panic!();
-                }
-            }
-            .cell(),
-        );
-    }
-    items_table.push(items_val_table);
-
-    let mut items_access_table = Vec::new();
-    items_access_table.push("Access".cell().bold(true));
-    for ri in r.iter().rev() {
-        items_access_table.push(
-            console::style(ri.access.to_string())
-                .bg(match ri.access {
-                    ch347_rs::RegisterAccess::ReadOnly => console::Color::Black,
-                    ch347_rs::RegisterAccess::ReadWrite => console::Color::Blue,
-                    ch347_rs::RegisterAccess::ReadWriteOTP => console::Color::Yellow,
-                })
-                .cell(),
-        );
+    match v {
+        ch347_rs::RegReadRet::One(a) => reg_table::show_register_field_table(&fields, a as u16),
+        ch347_rs::RegReadRet::Muti(_) => {
+            panic!();
+        }
     }
-    items_table.push(items_access_table);
-
-    items_table.table()
 }
 
 fn show_all_registers(
@@ -394,7 +323,7 @@ fn write_registers(
                 return Err("This RegItem is Read-only, cannot be write".into());
             }
 
-            let new_ri_val = utils::parse_cli_arg_number(input_str, width == 1)?;
+            let new_ri_val: u8 = utils::parse_cli_arg_number(input_str, width == 1)?;
 
             let v = match (r.reader)(&spi_flash)? {
                 ch347_rs::RegReadRet::One(a) => a,
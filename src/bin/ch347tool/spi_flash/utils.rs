@@ -85,31 +85,34 @@ pub fn find_reg_by_name<'a>(
     None
 }
 
-pub fn parse_cli_arg_number(input: &str, is_bool: bool) -> Result<u8, Box<dyn Error>> {
+/// Number in hex(0x1F, 1Fh), binary(0b0001_1111) or decimal, case
+/// insensitive, `_` and `-` may group digits. `is_bool` also takes true/false
+pub fn parse_cli_arg_number<T: TryFrom<u64>>(
+    input: &str,
+    is_bool: bool,
+) -> Result<T, Box<dyn Error>> {
     let input = input.to_lowercase();
 
-    if is_bool {
-        if input.eq("true") || input.eq("t") {
-            return Ok(1);
-        } else if input.eq("false") || input.eq("f") {
-            return Ok(0);
-        }
-    }
-
-    let ret = if input.starts_with("0b") {
-        let input_str = input.trim_start_matches("0b");
-        let input_str = input_str.replace("-", "");
-
-        u8::from_str_radix(&input_str, 2)?
-    } else if input.starts_with("0x") {
-        let input_str = input.trim_start_matches("0x");
-
-        u8::from_str_radix(input_str, 16)?
+    let ret = if is_bool && (input.eq("true") || input.eq("t")) {
+        Ok(1)
+    } else if is_bool && (input.eq("false") || input.eq("f")) {
+        Ok(0)
     } else {
-        return Err("Cannot parse input value".into());
+        let input = input.replace(['-', '_'], "");
+
+        if let Some(input_str) = input.strip_prefix("0x") {
+            u64::from_str_radix(input_str, 16)
+        } else if let Some(input_str) = input.strip_suffix('h') {
+            u64::from_str_radix(input_str, 16)
+        } else if let Some(input_str) = input.strip_prefix("0b") {
+            u64::from_str_radix(input_str, 2)
+        } else {
+            input.parse::<u64>()
+        }
     };
 
-    Ok(ret)
+    let ret = ret.map_err(|e| format!("Cannot parse input value {:?}: {}", input, e))?;
+    T::try_from(ret).map_err(|_| format!("Input value {:?} out of range", input).into())
 }
 
 pub fn display_bool_with_color(v: bool) -> String {
@@ -124,3 +127,24 @@ pub fn display_bool_with_color(v: bool) -> String {
 pub fn display_u8_hex(a: u8) -> String {
     format!("0x{:02X}(0b{:04b}_{:04b})", a, a >> 4, a & 0x0F)
 }
+
+#[test]
+pub fn test_parse_cli_arg_number() {
+    assert_eq!(parse_cli_arg_number::<u8>("0X50", false).unwrap(), 0x50);
+    assert_eq!(parse_cli_arg_number::<u8>("50h", false).unwrap(), 0x50);
+    assert_eq!(
+        parse_cli_arg_number::<u8>("0b0101_0000", false).unwrap(),
+        0x50
+    );
+    assert_eq!(parse_cli_arg_number::<u8>("t", true).unwrap(), 1);
+    assert_eq!(
+        parse_cli_arg_number::<u16>("0x12_34", false).unwrap(),
+        0x1234
+    );
+    assert_eq!(
+        parse_cli_arg_number::<u32>("65536", false).unwrap(),
+        0x10000
+    );
+    assert!(parse_cli_arg_number::<u8>("0x100", false).is_err());
+    assert!(parse_cli_arg_number::<u16>("true", false).is_err());
+}
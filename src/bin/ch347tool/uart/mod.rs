@@ -0,0 +1,23 @@
+use std::error::Error;
+
+use clap::{Parser, Subcommand};
+
+mod term;
+
+#[derive(Parser, Debug)]
+#[clap(about = "Use the UART interfaces")]
+pub struct CmdUart {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Term(term::CmdUartTerm),
+}
+
+pub fn cli_uart(args: &CmdUart) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Commands::Term(args) => term::cli_uart_term(args),
+    }
+}
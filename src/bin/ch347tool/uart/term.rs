@@ -0,0 +1,466 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use ch347_rs::{Ch347Uart, FlowControl, UartConfig};
+use clap::{Parser, ValueEnum};
+
+/// Ctrl-]
+const ESCAPE_KEY: u8 = 0x1D;
+
+const BREAK_TIME: Duration = Duration::from_millis(250);
+
+const MENU_HELP: &str =
+    "b: baud, k: break, d: DTR, r: RTS, e: echo, h: hex, q: quit, Ctrl-]: send Ctrl-]";
+
+#[derive(Parser, Debug)]
+#[clap(about = "Interactive serial terminal, Ctrl-] opens the menu")]
+pub struct CmdUartTerm {
+    /// UART number, see the list command
+    #[clap(value_parser)]
+    index: u32,
+
+    #[clap(short, long, value_parser, default_value_t = 115200)]
+    baud: u32,
+
+    /// data bits, parity and stop bits, eg. 8N1, 7E1
+    #[clap(long, value_parser, default_value = "8N1")]
+    frame: String,
+
+    #[clap(long, value_enum, value_parser, default_value_t = FlowArg::None)]
+    flow: FlowArg,
+
+    /// Show typed characters locally
+    #[clap(short, long, action)]
+    echo: bool,
+
+    /// Show received bytes as hex
+    #[clap(long, action)]
+    hex: bool,
+
+    /// Append received lines with timestamps to a file
+    #[clap(long, value_parser)]
+    log: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum FlowArg {
+    None,
+    Rtscts,
+    Xonxoff,
+}
+
+/// Writes every received line prefixed with the wall clock time
+struct LineLog<W: Write> {
+    out: W,
+    line: Vec<u8>,
+}
+
+impl<W: Write> LineLog<W> {
+    fn new(out: W) -> LineLog<W> {
+        LineLog {
+            out,
+            line: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) -> io::Result<()> {
+        for &b in data {
+            match b {
+                b'\n' => self.write_line()?,
+                b'\r' => (),
+                _ => self.line.push(b),
+            }
+        }
+
+        self.out.flush()
+    }
+
+    fn write_line(&mut self) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "[{}] {}",
+            humantime::format_rfc3339_millis(SystemTime::now()),
+            String::from_utf8_lossy(&self.line)
+        )?;
+        self.line.clear();
+
+        Ok(())
+    }
+
+    /// Write a pending incomplete line
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+
+        self.out.flush()
+    }
+}
+
+/// Hex dump of the received stream, 16 bytes per row
+struct HexView {
+    column: usize,
+}
+
+impl HexView {
+    fn format(&mut self, data: &[u8]) -> String {
+        let mut s = String::new();
+
+        for b in data {
+            s += &format!("{:02X} ", b);
+            self.column += 1;
+            if self.column == 16 {
+                s += "\r\n";
+                self.column = 0;
+            }
+        }
+
+        s
+    }
+}
+
+/// Raw keyboard input on a tty, restored on drop
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    /// None if stdin is not a terminal
+    fn enable() -> io::Result<Option<RawMode>> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+            return Ok(None);
+        }
+
+        let mut tio: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut tio) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = tio;
+
+        unsafe { libc::cfmakeraw(&mut tio) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &tio) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Some(RawMode { original }))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// Send keyboard input as bytes until stdin closes
+#[cfg(unix)]
+fn spawn_key_reader(tx: mpsc::Sender<Vec<u8>>) {
+    thread::spawn(move || {
+        let mut buf = [0; 64];
+        let mut stdin = io::stdin();
+
+        while let Ok(n) = stdin.read(&mut buf) {
+            if n == 0 || tx.send(buf[..n].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// The console reads keys in raw mode, they are turned back into the bytes
+/// a VT100 terminal sends
+#[cfg(windows)]
+fn spawn_key_reader(tx: mpsc::Sender<Vec<u8>>) {
+    use console::Key;
+
+    thread::spawn(move || {
+        let term = console::Term::stdout();
+
+        while let Ok(key) = term.read_key() {
+            let bytes = match key {
+                Key::Char(c) => c.to_string().into_bytes(),
+                Key::Enter => vec![b'\r'],
+                Key::Backspace => vec![0x7F],
+                Key::Tab => vec![b'\t'],
+                Key::Escape => vec![0x1B],
+                Key::ArrowUp => b"\x1b[A".to_vec(),
+                Key::ArrowDown => b"\x1b[B".to_vec(),
+                Key::ArrowRight => b"\x1b[C".to_vec(),
+                Key::ArrowLeft => b"\x1b[D".to_vec(),
+                Key::Home => b"\x1b[H".to_vec(),
+                Key::End => b"\x1b[F".to_vec(),
+                Key::Del => b"\x1b[3~".to_vec(),
+                _ => continue,
+            };
+            if tx.send(bytes).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+enum Input {
+    Normal,
+    /// Ctrl-] was pressed, the next key is a menu command
+    Menu,
+    /// Typing a new baud rate
+    Baud(String),
+}
+
+struct Terminal {
+    uart: Ch347Uart,
+    echo: bool,
+    hex: Option<HexView>,
+    log: Option<LineLog<BufWriter<File>>>,
+    dtr: bool,
+    rts: bool,
+    input: Input,
+    quit: bool,
+}
+
+fn note(msg: &str) {
+    print!("\r\n*** {}\r\n", msg);
+    io::stdout().flush().ok();
+}
+
+fn on_off(b: bool) -> &'static str {
+    if b {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+impl Terminal {
+    fn show(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        match &mut self.hex {
+            Some(view) => stdout.write_all(view.format(data).as_bytes())?,
+            None => stdout.write_all(data)?,
+        }
+        stdout.flush()
+    }
+
+    fn received(&mut self, data: &[u8]) -> io::Result<()> {
+        self.show(data)?;
+
+        if let Some(log) = &mut self.log {
+            log.feed(data)?;
+        }
+
+        Ok(())
+    }
+
+    fn keys(&mut self, keys: &[u8]) -> io::Result<()> {
+        let mut send = Vec::new();
+
+        for &k in keys {
+            match &mut self.input {
+                Input::Normal if k == ESCAPE_KEY => {
+                    self.input = Input::Menu;
+                    note(MENU_HELP);
+                }
+                Input::Normal => send.push(k),
+                Input::Menu => {
+                    self.input = Input::Normal;
+                    if k == ESCAPE_KEY {
+                        send.push(k);
+                    } else {
+                        self.menu(k)?;
+                    }
+                }
+                Input::Baud(s) => match k {
+                    b'\r' | b'\n' => {
+                        let baud = s.parse::<u32>();
+                        self.input = Input::Normal;
+                        match baud {
+                            Ok(baud) => match self.uart.set_baud(baud) {
+                                Ok(()) => note(&format!("{}", self.uart.config())),
+                                Err(e) => note(&format!("baud not changed: {}", e)),
+                            },
+                            Err(_) => note("baud not changed"),
+                        }
+                    }
+                    0x1B | 0x03 => {
+                        self.input = Input::Normal;
+                        note("baud not changed");
+                    }
+                    0x08 | 0x7F if s.pop().is_some() => print!("\x08 \x08"),
+                    b'0'..=b'9' => {
+                        s.push(k as char);
+                        print!("{}", k as char);
+                    }
+                    _ => (),
+                },
+            }
+        }
+        io::stdout().flush()?;
+
+        if !send.is_empty() {
+            self.uart.write_all(&send)?;
+            // local echo is not logged
+            if self.echo {
+                self.show(&send)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn menu(&mut self, k: u8) -> io::Result<()> {
+        match k.to_ascii_lowercase() {
+            b'b' => {
+                self.input = Input::Baud(String::new());
+                print!("\r\n*** baud ({}): ", self.uart.config().baud);
+            }
+            b'k' => match self.uart.send_break(BREAK_TIME) {
+                Ok(()) => note("break sent"),
+                Err(e) => note(&format!("break failed: {}", e)),
+            },
+            b'd' => match self.uart.set_dtr(!self.dtr) {
+                Ok(()) => {
+                    self.dtr = !self.dtr;
+                    note(&format!("DTR {}", on_off(self.dtr)));
+                }
+                Err(e) => note(&format!("DTR failed: {}", e)),
+            },
+            b'r' => match self.uart.set_rts(!self.rts) {
+                Ok(()) => {
+                    self.rts = !self.rts;
+                    note(&format!("RTS {}", on_off(self.rts)));
+                }
+                Err(e) => note(&format!("RTS failed: {}", e)),
+            },
+            b'e' => {
+                self.echo = !self.echo;
+                note(&format!("local echo {}", on_off(self.echo)));
+            }
+            b'h' => {
+                self.hex = match self.hex {
+                    Some(_) => None,
+                    None => Some(HexView { column: 0 }),
+                };
+                note(&format!("hex {}", on_off(self.hex.is_some())));
+            }
+            b'q' | b'x' => self.quit = true,
+            _ => note(MENU_HELP),
+        }
+
+        Ok(())
+    }
+
+    fn run(&mut self, rx: mpsc::Receiver<Vec<u8>>) -> io::Result<()> {
+        let mut buf = [0; 4096];
+
+        while !self.quit {
+            match self.uart.read(&mut buf) {
+                Ok(n) => self.received(&buf[..n])?,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => (),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+
+            loop {
+                match rx.try_recv() {
+                    Ok(keys) => self.keys(&keys)?,
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        self.quit = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn cli_uart_term(args: &CmdUartTerm) -> Result<(), Box<dyn Error>> {
+    let mut config = UartConfig::new(args.baud);
+    config.set_frame(&args.frame)?;
+    config.flow_control = match args.flow {
+        FlowArg::None => FlowControl::None,
+        FlowArg::Rtscts => FlowControl::Hardware,
+        FlowArg::Xonxoff => FlowControl::Software,
+    };
+
+    let mut uart = Ch347Uart::open(args.index, &config)?;
+    // short reads keep the keyboard responsive
+    uart.set_read_timeout(Some(Duration::from_millis(10)))?;
+    uart.set_write_timeout(Some(Duration::from_secs(1)))?;
+
+    let log = match &args.log {
+        Some(path) => Some(LineLog::new(BufWriter::new(
+            File::options().create(true).append(true).open(path)?,
+        ))),
+        None => None,
+    };
+
+    println!(
+        "Connected to UART {} at {}, Ctrl-] for the menu",
+        args.index, config
+    );
+
+    let mut term = Terminal {
+        uart,
+        echo: args.echo,
+        hex: if args.hex {
+            Some(HexView { column: 0 })
+        } else {
+            None
+        },
+        log,
+        dtr: true,
+        rts: true,
+        input: Input::Normal,
+        quit: false,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    #[cfg(unix)]
+    let raw = RawMode::enable()?;
+    spawn_key_reader(tx);
+
+    let ret = term.run(rx);
+    #[cfg(unix)]
+    drop(raw);
+    println!();
+
+    if let Some(log) = &mut term.log {
+        log.finish()?;
+    }
+
+    Ok(ret?)
+}
+
+#[test]
+pub fn test_uart_term_views() {
+    let mut log = LineLog::new(Vec::new());
+    log.feed(b"U-Boot 2024.01\r\n=> ").unwrap();
+    log.feed(b"boot\r\n").unwrap();
+    log.feed(b"Starting").unwrap();
+    log.finish().unwrap();
+
+    let text = String::from_utf8(log.out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|l| l.starts_with('[')));
+    assert!(lines[0].ends_with("] U-Boot 2024.01"));
+    assert!(lines[1].ends_with("] => boot"));
+    assert!(lines[2].ends_with("] Starting"));
+
+    let mut view = HexView { column: 0 };
+    assert_eq!(view.format(b"AB\r"), "41 42 0D ");
+    let row = view.format(&[0; 14]);
+    assert_eq!(row.matches("\r\n").count(), 1);
+    assert!(row.ends_with("00 \r\n00 "));
+}
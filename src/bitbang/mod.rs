@@ -0,0 +1,16 @@
+use std::time::{Duration, Instant};
+
+use crate::gpio::sleep_until;
+
+mod one_wire;
+mod soft_i2c;
+mod soft_spi;
+
+pub use one_wire::*;
+pub use soft_i2c::*;
+pub use soft_spi::*;
+
+/// Busy wait for short delays, most bit timings are far below a scheduler tick
+fn delay_us(us: u64) {
+    sleep_until(Instant::now() + Duration::from_micros(us));
+}
@@ -0,0 +1,336 @@
+use std::time::{Duration, Instant};
+
+pub const ONE_WIRE_SEARCH_ROM: u8 = 0xF0;
+pub const ONE_WIRE_READ_ROM: u8 = 0x33;
+pub const ONE_WIRE_MATCH_ROM: u8 = 0x55;
+pub const ONE_WIRE_SKIP_ROM: u8 = 0xCC;
+
+pub const DS18B20_FAMILY: u8 = 0x28;
+pub const DS18B20_CONVERT_T: u8 = 0x44;
+pub const DS18B20_READ_SCRATCHPAD: u8 = 0xBE;
+
+/// Longest 12-bit temperature conversion
+const DS18B20_CONVERT_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// Dallas/Maxim CRC-8 of 1-Wire ROM codes and scratchpads
+pub fn one_wire_crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |mut crc, &b| {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x01 != 0 {
+                (crc >> 1) ^ 0x8C
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+/// Temperature of a DS18B20 scratchpad, unit: °C
+pub fn ds18b20_decode(scratchpad: &[u8; 9]) -> Result<f32, &'static str> {
+    if one_wire_crc8(&scratchpad[..8]) != scratchpad[8] {
+        return Err("DS18B20 scratchpad CRC error");
+    }
+
+    Ok(i16::from_le_bytes([scratchpad[0], scratchpad[1]]) as f32 / 16.0)
+}
+
+/// Time slots of a 1-Wire bus master. Standard speed write 1 and read slots
+/// release the line within 15us, too fast for the CH347 GPIO where every
+/// line change is a USB round trip, `UartOneWire` makes them with a UART
+pub trait OneWireDrive {
+    /// Reset pulse, true if any device answered with a presence pulse
+    fn ow_reset(&self) -> Result<bool, &'static str>;
+
+    fn ow_write_bit(&self, bit: bool) -> Result<(), &'static str>;
+
+    fn ow_read_bit(&self) -> Result<bool, &'static str>;
+
+    /// LSB first
+    fn ow_write_byte(&self, b: u8) -> Result<(), &'static str> {
+        for i in 0..8 {
+            self.ow_write_bit(b & (1 << i) != 0)?;
+        }
+
+        Ok(())
+    }
+
+    fn ow_read_byte(&self) -> Result<u8, &'static str> {
+        let mut b = 0;
+        for i in 0..8 {
+            if self.ow_read_bit()? {
+                b |= 1 << i;
+            }
+        }
+
+        Ok(b)
+    }
+}
+
+/// 1-Wire devices addressed by their 64-bit ROM code, the family code is
+/// the low byte
+pub struct OneWire<T: OneWireDrive> {
+    pub drive: T,
+}
+
+impl<T: OneWireDrive> OneWire<T> {
+    pub fn new(drive: T) -> OneWire<T> {
+        OneWire { drive }
+    }
+
+    pub fn reset(&self) -> Result<bool, &'static str> {
+        self.drive.ow_reset()
+    }
+
+    /// LSB first
+    pub fn write_byte(&self, b: u8) -> Result<(), &'static str> {
+        self.drive.ow_write_byte(b)
+    }
+
+    pub fn read_byte(&self) -> Result<u8, &'static str> {
+        self.drive.ow_read_byte()
+    }
+
+    /// Reset and address one device, or every device with `None`
+    pub fn select(&self, rom: Option<u64>) -> Result<(), &'static str> {
+        if !self.reset()? {
+            return Err("No 1-Wire device present");
+        }
+
+        match rom {
+            Some(rom) => {
+                self.write_byte(ONE_WIRE_MATCH_ROM)?;
+                for b in rom.to_le_bytes() {
+                    self.write_byte(b)?;
+                }
+            }
+            None => self.write_byte(ONE_WIRE_SKIP_ROM)?,
+        }
+
+        Ok(())
+    }
+
+    /// ROM code of the only device on the bus
+    pub fn read_rom(&self) -> Result<u64, &'static str> {
+        if !self.reset()? {
+            return Err("No 1-Wire device present");
+        }
+        self.write_byte(ONE_WIRE_READ_ROM)?;
+
+        let mut rom = [0; 8];
+        for b in rom.iter_mut() {
+            *b = self.read_byte()?;
+        }
+        if one_wire_crc8(&rom[..7]) != rom[7] {
+            return Err("1-Wire ROM CRC error");
+        }
+
+        Ok(u64::from_le_bytes(rom))
+    }
+
+    /// ROM codes of all devices on the bus, in the order of the search tree
+    pub fn search(&self) -> Result<Vec<u64>, &'static str> {
+        let mut roms = Vec::new();
+        let mut rom: u64 = 0;
+        // bit where the last pass took the 0 branch, -1 when done
+        let mut last_discrepancy: i32 = -1;
+
+        loop {
+            if !self.reset()? {
+                return Ok(roms);
+            }
+            self.write_byte(ONE_WIRE_SEARCH_ROM)?;
+
+            let mut last_zero = -1;
+            for bit in 0..64 {
+                let id = self.drive.ow_read_bit()?;
+                let complement = self.drive.ow_read_bit()?;
+
+                let dir = match (id, complement) {
+                    (true, true) => return Err("1-Wire search lost all devices"),
+                    (false, true) => false,
+                    (true, false) => true,
+                    // devices disagree on this bit
+                    (false, false) => {
+                        let dir = match bit.cmp(&last_discrepancy) {
+                            std::cmp::Ordering::Less => rom & (1 << bit) != 0,
+                            std::cmp::Ordering::Equal => true,
+                            std::cmp::Ordering::Greater => false,
+                        };
+                        if !dir {
+                            last_zero = bit;
+                        }
+                        dir
+                    }
+                };
+
+                if dir {
+                    rom |= 1 << bit;
+                } else {
+                    rom &= !(1 << bit);
+                }
+                self.drive.ow_write_bit(dir)?;
+            }
+
+            let bytes = rom.to_le_bytes();
+            if one_wire_crc8(&bytes[..7]) != bytes[7] {
+                return Err("1-Wire ROM CRC error");
+            }
+            roms.push(rom);
+
+            last_discrepancy = last_zero;
+            if last_discrepancy < 0 {
+                return Ok(roms);
+            }
+        }
+    }
+
+    /// Convert and read a DS18B20, `None` needs it to be the only device
+    pub fn ds18b20_temperature(&self, rom: Option<u64>) -> Result<f32, &'static str> {
+        self.select(rom)?;
+        self.write_byte(DS18B20_CONVERT_T)?;
+
+        // the device holds the bus low while converting
+        let start = Instant::now();
+        while !self.drive.ow_read_bit()? {
+            if start.elapsed() > DS18B20_CONVERT_TIMEOUT {
+                return Err("DS18B20 conversion timeout");
+            }
+        }
+
+        self.select(rom)?;
+        self.write_byte(DS18B20_READ_SCRATCHPAD)?;
+        let mut scratchpad = [0; 9];
+        for b in scratchpad.iter_mut() {
+            *b = self.read_byte()?;
+        }
+
+        ds18b20_decode(&scratchpad)
+    }
+}
+
+#[test]
+pub fn test_one_wire() {
+    use std::cell::{Cell, RefCell};
+
+    /// Devices answering SEARCH ROM and READ SCRATCHPAD
+    struct MockBus {
+        roms: Vec<u64>,
+        active: RefCell<Vec<bool>>,
+        written: RefCell<Vec<bool>>,
+        /// bits sent by the devices after the command
+        reply: RefCell<Vec<bool>>,
+        search_step: Cell<u32>,
+    }
+
+    impl MockBus {
+        fn byte(&self, offset: usize) -> Option<u8> {
+            let w = self.written.borrow();
+            (w.len() >= offset + 8).then(|| (0..8).fold(0, |b, i| b | ((w[offset + i] as u8) << i)))
+        }
+
+        fn command(&self) -> Option<u8> {
+            self.byte(0)
+        }
+
+        /// Bits written up to the function command, which follows the ROM
+        fn function_offset(&self) -> usize {
+            match self.command() {
+                Some(ONE_WIRE_MATCH_ROM) => 72,
+                _ => 8,
+            }
+        }
+
+        /// wired AND of a ROM bit over the active devices
+        fn search_bit(&self, bit: u32, complement: bool) -> bool {
+            self.roms
+                .iter()
+                .zip(self.active.borrow().iter())
+                .filter(|(_, &a)| a)
+                .all(|(rom, _)| ((rom >> bit) & 1 != 0) != complement)
+        }
+    }
+
+    impl OneWireDrive for MockBus {
+        fn ow_reset(&self) -> Result<bool, &'static str> {
+            self.active.replace(vec![true; self.roms.len()]);
+            self.written.borrow_mut().clear();
+            self.search_step.set(0);
+            Ok(!self.roms.is_empty())
+        }
+
+        fn ow_write_bit(&self, bit: bool) -> Result<(), &'static str> {
+            self.written.borrow_mut().push(bit);
+
+            match self.command() {
+                Some(ONE_WIRE_SEARCH_ROM) if self.written.borrow().len() > 8 => {
+                    let n = self.search_step.get() / 3;
+                    for (rom, a) in self.roms.iter().zip(self.active.borrow_mut().iter_mut()) {
+                        *a &= ((rom >> n) & 1 != 0) == bit;
+                    }
+                    self.search_step.set(self.search_step.get() + 1);
+                }
+                Some(ONE_WIRE_MATCH_ROM) | Some(ONE_WIRE_SKIP_ROM)
+                    if self.written.borrow().len() == self.function_offset() + 8
+                        && self.byte(self.function_offset()) == Some(DS18B20_READ_SCRATCHPAD) =>
+                {
+                    // +25.0625°C
+                    let mut pad = [0x91, 0x01, 0x4B, 0x46, 0x7F, 0xFF, 0x0F, 0x10, 0];
+                    pad[8] = one_wire_crc8(&pad[..8]);
+                    let bits = pad
+                        .iter()
+                        .flat_map(|b| (0..8).map(move |i| b & (1 << i) != 0))
+                        .rev()
+                        .collect();
+                    self.reply.replace(bits);
+                }
+                _ => {}
+            }
+
+            Ok(())
+        }
+
+        fn ow_read_bit(&self) -> Result<bool, &'static str> {
+            if let Some(ONE_WIRE_SEARCH_ROM) = self.command() {
+                let step = self.search_step.get();
+                self.search_step.set(step + 1);
+                return Ok(self.search_bit(step / 3, step % 3 == 1));
+            }
+
+            Ok(self.reply.borrow_mut().pop().unwrap_or(true))
+        }
+    }
+
+    let with_crc = |rom: u64| {
+        let crc = one_wire_crc8(&rom.to_le_bytes()[..7]) as u64;
+        rom | (crc << 56)
+    };
+
+    assert_eq!(one_wire_crc8(&[0x02, 0x1C, 0xB8, 0x01, 0, 0, 0]), 0xA2);
+
+    let roms = vec![
+        with_crc(0x0000_0004_5678_9A28),
+        with_crc(0x0000_0004_5678_9B28),
+        with_crc(0x0000_0000_1234_5610),
+    ];
+    let bus = OneWire::new(MockBus {
+        roms: roms.clone(),
+        active: RefCell::new(Vec::new()),
+        written: RefCell::new(Vec::new()),
+        reply: RefCell::new(Vec::new()),
+        search_step: Cell::new(0),
+    });
+
+    let mut found = bus.search().unwrap();
+    found.sort();
+    let mut expect = roms.clone();
+    expect.sort();
+    assert_eq!(found, expect);
+
+    assert_eq!(bus.ds18b20_temperature(Some(roms[0])).unwrap(), 25.0625);
+    assert_eq!(
+        ds18b20_decode(&[0x5E, 0xFF, 0, 0, 0, 0, 0, 0, 0]),
+        Err("DS18B20 scratchpad CRC error")
+    );
+}
@@ -0,0 +1,308 @@
+use std::time::{Duration, Instant};
+
+use super::delay_us;
+use crate::{Gpio, GpioDrive, I2cDrive, Pin};
+
+/// Longest time a slave may stretch the clock
+const CLOCK_STRETCH_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// I2C master bit-banged on two GPIOs with pull-ups, usable as the drive
+/// of an `I2cBus` on any pins
+pub struct SoftI2c<'a, T: GpioDrive> {
+    gpio: &'a Gpio<T>,
+    scl: Pin<'a, T>,
+    sda: Pin<'a, T>,
+    /// half of the SCL period, unit: us
+    pub half_period_us: u64,
+}
+
+impl<'a, T: GpioDrive> SoftI2c<'a, T> {
+    pub fn new(gpio: &'a Gpio<T>, scl: u8, sda: u8) -> Result<SoftI2c<'a, T>, &'static str> {
+        let scl = gpio.pin(scl)?;
+        let sda = gpio.pin(sda)?;
+
+        let i2c = SoftI2c {
+            gpio,
+            scl,
+            sda,
+            half_period_us: 5,
+        };
+        i2c.gpio.open_drain(i2c.scl_mask() | i2c.sda_mask(), true)?;
+
+        Ok(i2c)
+    }
+
+    fn scl_mask(&self) -> u8 {
+        1 << self.scl.num()
+    }
+
+    fn sda_mask(&self) -> u8 {
+        1 << self.sda.num()
+    }
+
+    fn delay(&self) {
+        delay_us(self.half_period_us);
+    }
+
+    fn sda(&self, release: bool) -> Result<(), &'static str> {
+        self.gpio.open_drain(self.sda_mask(), release)
+    }
+
+    fn scl_low(&self) -> Result<(), &'static str> {
+        self.gpio.open_drain(self.scl_mask(), false)
+    }
+
+    /// Release SCL and wait while a slave stretches the clock
+    fn scl_release(&self) -> Result<(), &'static str> {
+        self.gpio.open_drain(self.scl_mask(), true)?;
+
+        let start = Instant::now();
+        while self.scl.is_low()? {
+            if start.elapsed() > CLOCK_STRETCH_TIMEOUT {
+                return Err("SCL held low");
+            }
+        }
+        self.delay();
+
+        Ok(())
+    }
+
+    /// Also a repeated START when the bus is not idle
+    fn start(&self) -> Result<(), &'static str> {
+        self.sda(true)?;
+        self.scl_release()?;
+        if self.sda.is_low()? {
+            return Err("SDA held low");
+        }
+        self.sda(false)?;
+        self.delay();
+        self.scl_low()
+    }
+
+    fn stop(&self) -> Result<(), &'static str> {
+        self.sda(false)?;
+        self.delay();
+        self.scl_release()?;
+        self.sda(true)?;
+        self.delay();
+
+        Ok(())
+    }
+
+    /// MSB first, true if the slave acknowledged
+    fn write_byte(&self, b: u8) -> Result<bool, &'static str> {
+        for i in (0..8).rev() {
+            self.sda(b & (1 << i) != 0)?;
+            self.delay();
+            self.scl_release()?;
+            self.scl_low()?;
+        }
+
+        self.sda(true)?;
+        self.delay();
+        self.scl_release()?;
+        let ack = self.sda.is_low()?;
+        self.scl_low()?;
+
+        Ok(ack)
+    }
+
+    fn read_byte(&self, ack: bool) -> Result<u8, &'static str> {
+        let mut b = 0;
+
+        self.sda(true)?;
+        for _ in 0..8 {
+            self.delay();
+            self.scl_release()?;
+            b = (b << 1) | self.sda.is_high()? as u8;
+            self.scl_low()?;
+        }
+
+        self.sda(!ack)?;
+        self.delay();
+        self.scl_release()?;
+        self.scl_low()?;
+        self.sda(true)?;
+
+        Ok(b)
+    }
+
+    fn transfer(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+        self.start()?;
+        for &b in wbuf {
+            if !self.write_byte(b)? {
+                return Err("I2C no acknowledge");
+            }
+        }
+
+        if rbuf.is_empty() {
+            return Ok(());
+        }
+
+        // a lone address byte with the read bit set starts reading directly
+        if wbuf.len() != 1 || wbuf[0] & 0x01 == 0 {
+            self.start()?;
+            if !self.write_byte(wbuf[0] | 0x01)? {
+                return Err("I2C no acknowledge");
+            }
+        }
+
+        let last = rbuf.len() - 1;
+        for (i, b) in rbuf.iter_mut().enumerate() {
+            *b = self.read_byte(i != last)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: GpioDrive> I2cDrive for SoftI2c<'_, T> {
+    fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+        if wbuf.is_empty() {
+            return Err("I2C address missing");
+        }
+
+        let ret = self.transfer(wbuf, rbuf);
+        // always leave the bus idle, except when a line is stuck
+        match ret {
+            Err("SCL held low") | Err("SDA held low") => ret,
+            _ => self.stop().and(ret),
+        }
+    }
+
+    fn bus_check(&self) -> Result<(), &'static str> {
+        self.gpio.read().map(|_| ())
+    }
+
+    fn i2c_line_set(&self, pin: u8, release: bool) -> Result<(), &'static str> {
+        if pin != self.scl.num() && pin != self.sda.num() {
+            return Err("Not a pin of this I2C bus");
+        }
+
+        self.gpio.open_drain(1 << pin, release)
+    }
+
+    fn i2c_line_get(&self, pin: u8) -> Result<bool, &'static str> {
+        if pin != self.scl.num() && pin != self.sda.num() {
+            return Err("Not a pin of this I2C bus");
+        }
+
+        Ok(self.gpio.read()? & (1 << pin) != 0)
+    }
+}
+
+#[test]
+pub fn test_soft_i2c() {
+    use std::cell::{Cell, RefCell};
+
+    /// Open drain bus with one slave at 0x50 that returns 0xA5 on reads,
+    /// SCL is GPIO0 and SDA is GPIO1
+    struct MockBus {
+        dir: Cell<u8>,
+        bits: RefCell<Vec<bool>>,
+        bytes: RefCell<Vec<u8>>,
+        reading: Cell<bool>,
+        slave_sda_low: Cell<bool>,
+        starts: Cell<u32>,
+        stops: Cell<u32>,
+    }
+
+    impl MockBus {
+        fn scl(&self) -> bool {
+            self.dir.get() & 0x01 == 0
+        }
+
+        fn sda(&self) -> bool {
+            self.dir.get() & 0x02 == 0 && !self.slave_sda_low.get()
+        }
+
+        /// slave output for the clock after `bits` have been shifted
+        fn slave_drive(&self) {
+            let n = self.bits.borrow().len();
+            let addressed = self.bytes.borrow().first().map(|a| a >> 1) == Some(0x50);
+            let reading = self.reading.get();
+            let low = if n == 8 || (n % 9 == 8 && !reading) {
+                addressed
+            } else if reading && n > 8 && n % 9 < 8 {
+                (0xA5 >> (7 - n % 9)) & 1 == 0
+            } else {
+                false
+            };
+            self.slave_sda_low.set(low);
+        }
+    }
+
+    impl GpioDrive for MockBus {
+        fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+            Ok((self.dir.get(), self.scl() as u8 | (self.sda() as u8) << 1))
+        }
+
+        fn gpio_write(&self, _enable: u8, dir: u8, _data: u8) -> Result<(), &'static str> {
+            let (scl, sda) = (self.scl(), self.sda());
+            self.dir.set(dir);
+
+            if scl && self.scl() && sda != self.sda() {
+                // SDA change while SCL is high
+                if self.sda() {
+                    self.stops.set(self.stops.get() + 1);
+                } else {
+                    self.starts.set(self.starts.get() + 1);
+                    self.bytes.borrow_mut().clear();
+                }
+                self.bits.borrow_mut().clear();
+                self.reading.set(false);
+                self.slave_drive();
+            } else if !scl && self.scl() {
+                self.bits.borrow_mut().push(self.sda());
+            } else if scl && !self.scl() {
+                let bits = self.bits.borrow().clone();
+                if bits.len() % 9 == 8 {
+                    let b = bits[bits.len() - 8..]
+                        .iter()
+                        .fold(0, |b, &bit| (b << 1) | bit as u8);
+                    if bits.len() == 8 {
+                        self.reading.set(b & 0x01 != 0);
+                    }
+                    if !self.reading.get() || bits.len() == 8 {
+                        self.bytes.borrow_mut().push(b);
+                    }
+                }
+                self.slave_drive();
+            }
+
+            Ok(())
+        }
+    }
+
+    let gpio = Gpio::new(MockBus {
+        dir: Cell::new(0),
+        bits: RefCell::new(Vec::new()),
+        bytes: RefCell::new(Vec::new()),
+        reading: Cell::new(false),
+        slave_sda_low: Cell::new(false),
+        starts: Cell::new(0),
+        stops: Cell::new(0),
+    })
+    .unwrap();
+    let mut i2c = SoftI2c::new(&gpio, 0, 1).unwrap();
+    i2c.half_period_us = 0;
+
+    i2c.i2c_stream(&[0xA0, 0x12, 0x34], &mut []).unwrap();
+    assert_eq!(*gpio.drive.bytes.borrow(), vec![0xA0, 0x12, 0x34]);
+    assert_eq!((gpio.drive.starts.get(), gpio.drive.stops.get()), (1, 1));
+
+    let mut rbuf = [0; 2];
+    i2c.i2c_stream(&[0xA0, 0x00], &mut rbuf).unwrap();
+    assert_eq!(rbuf, [0xA5, 0xA5]);
+    assert_eq!(gpio.drive.starts.get(), 3);
+    assert_eq!(*gpio.drive.bytes.borrow(), vec![0xA1]);
+
+    assert!(i2c.i2c_stream(&[0xA2], &mut []).is_err());
+    assert_eq!(gpio.drive.stops.get(), 3);
+    assert!(!i2c.i2c_probe(0x51));
+    assert!(i2c.i2c_probe(0x50));
+
+    assert!(i2c.i2c_line_get(1).unwrap());
+    assert!(i2c.i2c_line_get(9).is_err());
+    assert!(i2c.i2c_line_set(2, true).is_err());
+}
@@ -0,0 +1,214 @@
+use super::delay_us;
+use crate::{Gpio, GpioDrive, Pin, SpiDrive};
+
+/// GPIOs of a software SPI bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SoftSpiPins {
+    pub sck: u8,
+    pub mosi: u8,
+    pub miso: u8,
+    /// active low
+    pub cs: u8,
+}
+
+/// SPI master bit-banged on GPIOs, MSB first. Every clock edge is a GPIO
+/// transfer, so it is only good for slow peripherals, but works as the
+/// drive of a `SpiFlash` too.
+pub struct SoftSpi<'a, T: GpioDrive> {
+    gpio: &'a Gpio<T>,
+    sck: Pin<'a, T>,
+    mosi: Pin<'a, T>,
+    miso: Pin<'a, T>,
+    cs: Pin<'a, T>,
+    /// SPI mode 0~3, bit 1 is CPOL and bit 0 is CPHA
+    mode: u8,
+    /// half of the SCK period, unit: us
+    pub half_period_us: u64,
+}
+
+impl<'a, T: GpioDrive> SoftSpi<'a, T> {
+    pub fn new(
+        gpio: &'a Gpio<T>,
+        pins: SoftSpiPins,
+        mode: u8,
+    ) -> Result<SoftSpi<'a, T>, &'static str> {
+        if mode > 3 {
+            return Err("SPI mode must be 0~3");
+        }
+
+        let spi = SoftSpi {
+            gpio,
+            sck: gpio.pin(pins.sck)?,
+            mosi: gpio.pin(pins.mosi)?,
+            miso: gpio.pin(pins.miso)?,
+            cs: gpio.pin(pins.cs)?,
+            mode,
+            half_period_us: 10,
+        };
+
+        spi.miso.set_input()?;
+        spi.cs.set_output(true)?;
+        spi.mosi.set_output(false)?;
+        spi.sck.set_output(spi.cpol())?;
+
+        Ok(spi)
+    }
+
+    fn cpol(&self) -> bool {
+        self.mode & 0x02 != 0
+    }
+
+    fn cpha(&self) -> bool {
+        self.mode & 0x01 != 0
+    }
+
+    /// Set SCK and MOSI in one GPIO transfer
+    fn drive(&self, sck: bool, mosi: bool) -> Result<(), &'static str> {
+        let sck_mask = 1 << self.sck.num();
+        let mosi_mask = 1 << self.mosi.num();
+        let mask = sck_mask | mosi_mask;
+        let data = if sck { sck_mask } else { 0 } | if mosi { mosi_mask } else { 0 };
+
+        self.gpio.write_masked(mask, mask, data)
+    }
+
+    fn transfer_byte(&self, out: u8) -> Result<u8, &'static str> {
+        let idle = self.cpol();
+        let mut input = 0;
+
+        for i in (0..8).rev() {
+            let bit = out & (1 << i) != 0;
+
+            // data is sampled on the first edge with CPHA 0, the second with CPHA 1
+            if self.cpha() {
+                self.drive(!idle, bit)?;
+                delay_us(self.half_period_us);
+                self.drive(idle, bit)?;
+            } else {
+                self.drive(idle, bit)?;
+                delay_us(self.half_period_us);
+                self.drive(!idle, bit)?;
+            }
+
+            input = (input << 1) | self.miso.is_high()? as u8;
+            delay_us(self.half_period_us);
+        }
+
+        if !self.cpha() {
+            self.drive(idle, false)?;
+        }
+
+        Ok(input)
+    }
+
+    /// Select the device, run `f`, deselect even if `f` failed
+    fn with_cs<F>(&self, f: F) -> Result<(), &'static str>
+    where
+        F: FnOnce() -> Result<(), &'static str>,
+    {
+        self.cs.set_low()?;
+        let ret = f();
+        self.cs.set_high().and(ret)
+    }
+}
+
+impl<T: GpioDrive> SpiDrive for SoftSpi<'_, T> {
+    fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+        self.with_cs(|| {
+            for b in iobuf.iter_mut() {
+                *b = self.transfer_byte(*b)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_after_read(
+        &self,
+        write_len: u32,
+        read_len: u32,
+        iobuf: &mut [u8],
+    ) -> Result<(), &'static str> {
+        let (write_len, read_len) = (write_len as usize, read_len as usize);
+        if write_len > iobuf.len() || read_len > iobuf.len() {
+            return Err("SPI buffer too small");
+        }
+
+        self.with_cs(|| {
+            for &b in iobuf[..write_len].iter() {
+                self.transfer_byte(b)?;
+            }
+            for b in iobuf[..read_len].iter_mut() {
+                *b = self.transfer_byte(0xFF)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[test]
+pub fn test_soft_spi() {
+    use std::cell::{Cell, RefCell};
+
+    /// MISO wired to MOSI, counts SCK edges while CS is low
+    struct Loopback {
+        data: Cell<u8>,
+        edges: Cell<u32>,
+        cs_low: RefCell<Vec<bool>>,
+    }
+
+    impl GpioDrive for Loopback {
+        fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+            let mosi = self.data.get() & 0x02 != 0;
+            Ok((0x0B, self.data.get() | if mosi { 0x04 } else { 0 }))
+        }
+
+        fn gpio_write(&self, enable: u8, _dir: u8, data: u8) -> Result<(), &'static str> {
+            let new = (self.data.get() & !enable) | (data & enable);
+            if (new ^ self.data.get()) & 0x01 != 0 && new & 0x08 == 0 {
+                self.edges.set(self.edges.get() + 1);
+            }
+            if enable & 0x08 != 0 {
+                self.cs_low.borrow_mut().push(data & 0x08 == 0);
+            }
+            self.data.set(new);
+            Ok(())
+        }
+    }
+
+    let pins = SoftSpiPins {
+        sck: 0,
+        mosi: 1,
+        miso: 2,
+        cs: 3,
+    };
+
+    for mode in 0..4 {
+        let gpio = Gpio::new(Loopback {
+            data: Cell::new(0),
+            edges: Cell::new(0),
+            cs_low: RefCell::new(Vec::new()),
+        })
+        .unwrap();
+        let mut spi = SoftSpi::new(&gpio, pins, mode).unwrap();
+        spi.half_period_us = 0;
+
+        let mut buf = [0x9F, 0x5A, 0x00];
+        spi.transfer(&mut buf).unwrap();
+        assert_eq!(buf, [0x9F, 0x5A, 0x00]);
+        assert_eq!(gpio.drive.edges.get(), 3 * 16);
+        assert_eq!(*gpio.drive.cs_low.borrow(), vec![false, true, false]);
+
+        let mut buf = [0x03, 0x00];
+        spi.write_after_read(1, 2, &mut buf).unwrap();
+        // the loopback reads the dummy bytes back
+        assert_eq!(buf, [0xFF, 0xFF]);
+    }
+
+    let gpio = Gpio::new(Loopback {
+        data: Cell::new(0),
+        edges: Cell::new(0),
+        cs_low: RefCell::new(Vec::new()),
+    })
+    .unwrap();
+    assert!(SoftSpi::new(&gpio, pins, 4).is_err());
+}
@@ -1,4 +1,5 @@
 use crate::windows::basetsd::*;
+use clap::ValueEnum;
 use std::ffi::CStr;
 use std::fmt;
 
@@ -32,7 +33,7 @@ pub enum FuncType {
     JtagI2c,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SpiClockLevel {
     S60M,
     S30M,
@@ -58,6 +59,10 @@ impl SpiClockLevel {
             _ => None,
         }
     }
+
+    pub fn to_byte(&self) -> u8 {
+        *self as u8
+    }
 }
 
 impl fmt::Display for SpiClockLevel {
@@ -79,6 +84,28 @@ impl fmt::Display for SpiClockLevel {
     }
 }
 
+/// SPI 片选
+///
+/// CS0/CS1 correspond to the CS1/CS2 pins in the vendor documentation
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ChipSelect {
+    CS0 = 0x00,
+    CS1 = 0x01,
+}
+
+impl ChipSelect {
+    /// `iChipSelect` argument of the SPI stream functions, bit7 enables chip select control
+    pub fn to_raw(&self) -> ULONG {
+        0x80 | (*self as ULONG)
+    }
+}
+
+impl fmt::Display for ChipSelect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 /// 设备信息
 #[repr(C)]
 #[derive(Debug)]
@@ -169,6 +196,18 @@ pub struct SpiConfig {
     pub delay_deactive: ULONG,
 }
 
+impl SpiConfig {
+    /// Select which pin the controller drives and how it is asserted
+    pub fn set_chip_select(&mut self, cs: ChipSelect, active_high: bool) {
+        self.chip_select = cs.to_raw();
+
+        match cs {
+            ChipSelect::CS0 => self.cs1_polarity = active_high as UCHAR,
+            ChipSelect::CS1 => self.cs2_polarity = active_high as UCHAR,
+        }
+    }
+}
+
 impl DeviceInfo {
     pub fn default() -> DeviceInfo {
         DeviceInfo {
@@ -194,6 +233,36 @@ impl DeviceInfo {
         }
     }
 
+    /// Info of a UART tty found in sysfs, the vendor library has none on Linux
+    #[cfg(target_os = "linux")]
+    pub(crate) fn from_uart_port(index: u8, port: &crate::UartPort) -> DeviceInfo {
+        fn copy_str(dst: &mut [UCHAR], src: &str) {
+            let len = src.len().min(dst.len() - 1);
+            dst[..len].copy_from_slice(&src.as_bytes()[..len]);
+        }
+
+        let path = port.path.to_string_lossy();
+        let mut info = DeviceInfo::default();
+
+        info.index = index;
+        info.usb_class = 3;
+        info.func_type = 0;
+        info.ch347_if_num = port.interface;
+        copy_str(&mut info.device_path, &path);
+        copy_str(
+            &mut info.device_id,
+            &format!(
+                "USB\\VID_1A86&PID_{:04X}&MI_{:02X}",
+                port.product_id, port.interface
+            ),
+        );
+        copy_str(&mut info.rpoduct_string, &port.product);
+        copy_str(&mut info.manufacturer_string, &port.manufacturer);
+        copy_str(&mut info.func_desc_str, &format!("UART {}", path));
+
+        info
+    }
+
     pub fn get_device_path(&self) -> String {
         unsafe {
             let str = CStr::from_bytes_with_nul_unchecked(&self.device_path);
@@ -438,6 +507,50 @@ extern "C" {
     /// ```
     pub fn CH347Uart_Close(DevI: ULONG) -> HANDLE;
 
+    /// 初始化串口参数
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Uart_Init(ULONG iIndex,
+    ///     DWORD BaudRate,    // 波特率
+    ///     UCHAR ByteSize,    // 数据位数(5,6,7,8,16)
+    ///     UCHAR Parity,      // 校验位(0：None; 1：Odd; 2：Even; 3：Mark; 4：Space)
+    ///     UCHAR StopBits,    // 停止位数(0：停止位1; 1：停止位1.5; 2：停止位2)
+    ///     UCHAR ByteTimeout);// 字节超时,单位100uS
+    /// ```
+    #[cfg(target_os = "windows")]
+    pub fn CH347Uart_Init(
+        iIndex: ULONG,
+        BaudRate: ULONG,
+        ByteSize: UCHAR,
+        Parity: UCHAR,
+        StopBits: UCHAR,
+        ByteTimeout: UCHAR,
+    ) -> BOOL;
+
+    /// 设置串口读写超时,单位mS, 0xFFFFFFFF 为一直等待
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Uart_SetTimeout(ULONG iIndex, ULONG iWriteTimeout, ULONG iReadTimeout);
+    /// ```
+    #[cfg(target_os = "windows")]
+    pub fn CH347Uart_SetTimeout(iIndex: ULONG, iWriteTimeout: ULONG, iReadTimeout: ULONG) -> BOOL;
+
+    /// 读取串口数据, ioLength 输入为准备读取的长度, 返回后为实际读取的长度
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Uart_Read(ULONG iIndex, PVOID oBuffer, PULONG ioLength);
+    /// ```
+    #[cfg(target_os = "windows")]
+    pub fn CH347Uart_Read(iIndex: ULONG, oBuffer: PVOID, ioLength: PULONG) -> BOOL;
+
+    /// 写出串口数据, ioLength 输入为准备写出的长度, 返回后为实际写出的长度
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Uart_Write(ULONG iIndex, PVOID iBuffer, PULONG ioLength);
+    /// ```
+    #[cfg(target_os = "windows")]
+    pub fn CH347Uart_Write(iIndex: ULONG, iBuffer: PVOID, ioLength: PULONG) -> BOOL;
+
     /// ```c
     /// BOOL WINAPI CH347Uart_GetDeviceInfor(ULONG iIndex,mDeviceInforS *DevInformation);
     /// ```
@@ -479,6 +592,15 @@ extern "C" {
     /// ```
     pub fn CH347I2C_Set(iIndex: ULONG, iMode: ULONG) -> BOOL;
 
+    /// 设置硬件异步延时,调用后很快返回,而在下一个流操作之前延时指定毫秒数
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347I2C_SetDelaymS(
+    ///     ULONG iIndex,  // 指定设备序号
+    ///     ULONG iDelay); // 指定延时的毫秒数
+    /// ```
+    pub fn CH347I2C_SetDelaymS(iIndex: ULONG, iDelay: ULONG) -> BOOL;
+
     /// 处理I2C数据流,2线接口,时钟线为SCL引脚,数据线为SDA引脚
     ///
     /// ```c
@@ -497,6 +619,42 @@ extern "C" {
         oReadBuffer: PVOID,
     ) -> BOOL;
 
+    /// 从EEPROM中读取数据块,速度约56K字节
+    ///
+    /// ```c
+    /// BOOL CH347ReadEEPROM(
+    ///     ULONG iIndex,          // 指定CH341设备序号
+    ///     EEPROM_TYPE iEepromID, // 指定EEPROM型号
+    ///     ULONG iAddr,           // 指定数据单元的地址
+    ///     ULONG iLength,         // 准备读取的数据字节数
+    ///     PUCHAR oBuffer);       // 指向一个缓冲区,返回后是读入的数据
+    /// ```
+    pub fn CH347ReadEEPROM(
+        iIndex: ULONG,
+        iEepromID: libc::c_int,
+        iAddr: ULONG,
+        iLength: ULONG,
+        oBuffer: PUCHAR,
+    ) -> BOOL;
+
+    /// 向EEPROM中写入数据块
+    ///
+    /// ```c
+    /// BOOL CH347WriteEEPROM(
+    ///     ULONG iIndex,          // 指定设备序号
+    ///     EEPROM_TYPE iEepromID, // 指定EEPROM型号
+    ///     ULONG iAddr,           // 指定数据单元的地址
+    ///     ULONG iLength,         // 准备写出的数据字节数
+    ///     PUCHAR iBuffer);       // 指向一个缓冲区,放置准备写出的数据
+    /// ```
+    pub fn CH347WriteEEPROM(
+        iIndex: ULONG,
+        iEepromID: libc::c_int,
+        iAddr: ULONG,
+        iLength: ULONG,
+        iBuffer: PUCHAR,
+    ) -> BOOL;
+
     /// SPI控制器初始化
     ///
     /// ``` c
@@ -510,9 +668,24 @@ extern "C" {
     /// ```
     pub fn CH347SPI_GetCfg(iIndex: ULONG, mSpiCfgS: *mut SpiConfig) -> BOOL;
 
-    pub fn CH347SPI_ChangeCS(iIndex: ULONG, iStatus: UCHAR);
+    /// 设置片选状态,使用前需先调用CH347SPI_Init对CS进行设置
+    ///
+    /// ```c
+    /// BOOL CH347SPI_ChangeCS(ULONG iIndex,   // 指定设备序号
+    ///                        UCHAR iStatus); // 0=撤消片选,1=设置片选
+    /// ```
+    pub fn CH347SPI_ChangeCS(iIndex: ULONG, iStatus: UCHAR) -> BOOL;
 
     /// 该函数用于设置 SPI 片选
+    ///
+    /// ```c
+    /// BOOL CH347SPI_SetChipSelect(ULONG iIndex,           // 指定设备序号
+    ///                             USHORT iEnableSelect,   // 低八位为CS1，高八位为CS2; 字节值为1=设置CS,为0=忽略此CS设置
+    ///                             USHORT iChipSelect,     // 低八位为CS1，高八位为CS2;片选输出,0=撤消片选,1=设置片选
+    ///                             ULONG iIsAutoDeativeCS, // 低16位为CS1，高16位为CS2;操作完成后是否自动撤消片选
+    ///                             ULONG iActiveDelay,     // 低16位为CS1，高16位为CS2;设置片选后执行读写操作的延时时间,单位us
+    ///                             ULONG iDelayDeactive);  // 低16位为CS1，高16位为CS2;撤消片选后执行读写操作的延时时间,单位us
+    /// ```
     pub fn CH347SPI_SetChipSelect(
         iIndex: ULONG,
         iEnableSelect: USHORT,
@@ -520,7 +693,7 @@ extern "C" {
         iIsAutoDeativeCS: ULONG,
         iActiveDelay: ULONG,
         iDelayDeactive: ULONG,
-    );
+    ) -> BOOL;
 
     /// 该函数用于 SPI 写数据
     pub fn CH347SPI_Write(
@@ -555,4 +728,169 @@ extern "C" {
         iLength: ULONG,
         ioBuffer: PVOID,
     ) -> BOOL;
+
+    /// 读取USB数据块
+    ///
+    /// ```c
+    /// BOOL CH347ReadData(ULONG iIndex, PVOID oBuffer, PULONG ioLength);
+    /// ```
+    pub fn CH347ReadData(iIndex: ULONG, oBuffer: PVOID, ioLength: PULONG) -> BOOL;
+
+    /// 写取USB数据块
+    ///
+    /// ```c
+    /// BOOL CH347WriteData(ULONG iIndex, PVOID iBuffer, PULONG ioLength);
+    /// ```
+    pub fn CH347WriteData(iIndex: ULONG, iBuffer: PVOID, ioLength: PULONG) -> BOOL;
+
+    /// JTAG接口初始化，设置模式及速度
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_INIT(ULONG iIndex,
+    ///     UCHAR iClockRate); // 通信速度；有效值为0-5，值越大通信速度越快
+    /// ```
+    pub fn CH347Jtag_INIT(iIndex: ULONG, iClockRate: UCHAR) -> BOOL;
+
+    /// 获取Jtag速度设置
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_GetCfg(ULONG iIndex,
+    ///     UCHAR *ClockRate); // 通信速度；有效值为0-5，值越大通信速度越快
+    /// ```
+    pub fn CH347Jtag_GetCfg(iIndex: ULONG, ClockRate: PUCHAR) -> BOOL;
+
+    /// 切换JTAG状态机
+    ///
+    /// - 0: Test-Logic Reset
+    /// - 1: Run-Test/Idle
+    /// - 2: Run-Test/Idle -> Shift-DR
+    /// - 3: Shift-DR -> Run-Test/Idle
+    /// - 4: Run-Test/Idle -> Shift-IR
+    /// - 5: Shift-IR -> Run-Test/Idle
+    /// - 6: Exit1-DR/IR -> Update-DR/IR -> Run-Test/Idle
+    ///
+    /// ```c
+    /// // Linux
+    /// BOOL CH347Jtag_SwitchTapState(ULONG iIndex, UCHAR TapState);
+    /// // Windows
+    /// BOOL WINAPI CH347Jtag_SwitchTapStateEx(ULONG iIndex, UCHAR TapState);
+    /// ```
+    #[cfg(target_os = "linux")]
+    pub fn CH347Jtag_SwitchTapState(iIndex: ULONG, TapState: UCHAR) -> BOOL;
+    #[cfg(target_os = "windows")]
+    #[link_name = "CH347Jtag_SwitchTapStateEx"]
+    pub fn CH347Jtag_SwitchTapState(iIndex: ULONG, TapState: UCHAR) -> BOOL;
+
+    /// 位带方式JTAG IR/DR数据读写，适用于少量数据的读写。如指令操作、状态机切换等控制类传输。
+    /// 如批量数据传输，建议使用 CH347Jtag_WriteRead_Fast
+    ///
+    /// 状态机: Run-Test -> Shift-IR/DR..-> Exit IR/DR -> Run-Test
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_WriteRead(ULONG iIndex,
+    ///     BOOL IsDR,               // =TRUE: DR数据读写, =FALSE:IR数据读写
+    ///     ULONG iWriteBitLength,   // 写长度，准备写出的长度
+    ///     PVOID iWriteBitBuffer,   // 指向一个缓冲区，放置准备写出的数据
+    ///     PULONG oReadBitLength,   // 指向长度单元，返回后为实际读取的长度
+    ///     PVOID oReadBitBuffer);   // 指向一个足够大的缓冲区，用于保存读取的数据
+    /// ```
+    pub fn CH347Jtag_WriteRead(
+        iIndex: ULONG,
+        IsDR: BOOL,
+        iWriteBitLength: ULONG,
+        iWriteBitBuffer: PVOID,
+        oReadBitLength: PULONG,
+        oReadBitBuffer: PVOID,
+    ) -> BOOL;
+
+    /// 批量方式JTAG IR/DR数据读写，用于多字节连续读写
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_WriteRead_Fast(ULONG iIndex,
+    ///     BOOL IsDR,
+    ///     ULONG iWriteBitLength,
+    ///     PVOID iWriteBitBuffer,
+    ///     PULONG oReadBitLength,
+    ///     PVOID oReadBitBuffer);
+    /// ```
+    pub fn CH347Jtag_WriteRead_Fast(
+        iIndex: ULONG,
+        IsDR: BOOL,
+        iWriteBitLength: ULONG,
+        iWriteBitBuffer: PVOID,
+        oReadBitLength: PULONG,
+        oReadBitBuffer: PVOID,
+    ) -> BOOL;
+
+    /// JTAG DR写，以字节为单位，用于多字节连续读写。如JTAG固件下载操作
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_ByteWriteDR(ULONG iIndex, ULONG iWriteLength, PVOID iWriteBuffer);
+    /// ```
+    pub fn CH347Jtag_ByteWriteDR(iIndex: ULONG, iWriteLength: ULONG, iWriteBuffer: PVOID) -> BOOL;
+
+    /// JTAG DR读，以字节为单位，多字节连续读
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_ByteReadDR(ULONG iIndex, PULONG oReadLength, PVOID oReadBuffer);
+    /// ```
+    pub fn CH347Jtag_ByteReadDR(iIndex: ULONG, oReadLength: PULONG, oReadBuffer: PVOID) -> BOOL;
+
+    /// JTAG IR写，以字节为单位，多字节连续写
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_ByteWriteIR(ULONG iIndex, ULONG iWriteLength, PVOID iWriteBuffer);
+    /// ```
+    pub fn CH347Jtag_ByteWriteIR(iIndex: ULONG, iWriteLength: ULONG, iWriteBuffer: PVOID) -> BOOL;
+
+    /// JTAG IR读，以字节为单位，多字节连续读
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_ByteReadIR(ULONG iIndex, PULONG oReadLength, PVOID oReadBuffer);
+    /// ```
+    pub fn CH347Jtag_ByteReadIR(iIndex: ULONG, oReadLength: PULONG, oReadBuffer: PVOID) -> BOOL;
+
+    /// 位带方式JTAG DR数据写
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_BitWriteDR(ULONG iIndex, ULONG iWriteBitLength, PVOID iWriteBitBuffer);
+    /// ```
+    pub fn CH347Jtag_BitWriteDR(
+        iIndex: ULONG,
+        iWriteBitLength: ULONG,
+        iWriteBitBuffer: PVOID,
+    ) -> BOOL;
+
+    /// 位带方式JTAG IR数据写
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_BitWriteIR(ULONG iIndex, ULONG iWriteBitLength, PVOID iWriteBitBuffer);
+    /// ```
+    pub fn CH347Jtag_BitWriteIR(
+        iIndex: ULONG,
+        iWriteBitLength: ULONG,
+        iWriteBitBuffer: PVOID,
+    ) -> BOOL;
+
+    /// 位带方式JTAG IR数据读
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_BitReadIR(ULONG iIndex, PULONG oReadBitLength, PVOID oReadBitBuffer);
+    /// ```
+    pub fn CH347Jtag_BitReadIR(
+        iIndex: ULONG,
+        oReadBitLength: PULONG,
+        oReadBitBuffer: PVOID,
+    ) -> BOOL;
+
+    /// 位带方式JTAG DR数据读
+    ///
+    /// ```c
+    /// BOOL WINAPI CH347Jtag_BitReadDR(ULONG iIndex, PULONG oReadBitLength, PVOID oReadBitBuffer);
+    /// ```
+    pub fn CH347Jtag_BitReadDR(
+        iIndex: ULONG,
+        oReadBitLength: PULONG,
+        oReadBitBuffer: PVOID,
+    ) -> BOOL;
 }
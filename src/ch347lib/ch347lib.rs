@@ -1,4 +1,6 @@
 use std::error::Error;
+use std::io;
+use std::sync::Arc;
 use std::{fmt, string};
 
 use super::ch347dll::*;
@@ -29,6 +31,8 @@ pub fn enum_device() -> Vec<Ch347Device> {
     device_info_list
 }
 
+/// Opens every UART interface, on Linux the CH347 ttys in sysfs order.
+/// Opening a tty toggles DTR/RTS, use `enum_uart_info` to only list them
 #[cfg(target_os = "windows")]
 pub fn enum_uart_device() -> Vec<Ch347Device> {
     let mut device_info_list = Vec::new();
@@ -42,6 +46,37 @@ pub fn enum_uart_device() -> Vec<Ch347Device> {
     device_info_list
 }
 
+/// Opens every UART interface, on Linux the CH347 ttys in sysfs order.
+/// Opening a tty toggles DTR/RTS, use `enum_uart_info` to only list them
+#[cfg(target_os = "linux")]
+pub fn enum_uart_device() -> Vec<Ch347Device> {
+    crate::enum_uart_ports()
+        .iter()
+        .filter_map(|port| Ch347Device::open_tty(port).ok())
+        .collect()
+}
+
+/// Info of all UART interfaces, on Linux read from sysfs without opening
+/// the ttys
+#[cfg(target_os = "windows")]
+pub fn enum_uart_info() -> Vec<DeviceInfo> {
+    enum_uart_device()
+        .iter()
+        .filter_map(|dev| dev.get_raw_info())
+        .collect()
+}
+
+/// Info of all UART interfaces, on Linux read from sysfs without opening
+/// the ttys
+#[cfg(target_os = "linux")]
+pub fn enum_uart_info() -> Vec<DeviceInfo> {
+    crate::enum_uart_ports()
+        .iter()
+        .enumerate()
+        .map(|(i, port)| DeviceInfo::from_uart_port(i as u8, port))
+        .collect()
+}
+
 #[cfg(target_os = "windows")]
 pub fn open_device(index: u32) -> HANDLE {
     unsafe { CH347OpenDevice(index as ULONG) }
@@ -236,8 +271,11 @@ pub struct Ch347Device {
 
     ts_type: CH347TransType,
     spi_cfg: SpiConfig,
+    chip_select: ChipSelect,
 }
 
+/// Opens every vendor and UART interface, opening a UART tty on Linux
+/// toggles DTR/RTS, see `enum_uart_device`
 pub fn enum_ch347_device() -> Vec<Ch347Device> {
     let mut device_list: Vec<Ch347Device> = Vec::new();
 
@@ -245,11 +283,8 @@ pub fn enum_ch347_device() -> Vec<Ch347Device> {
         device_list.push(i);
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        for i in enum_uart_device() {
-            device_list.push(i);
-        }
+    for i in enum_uart_device() {
+        device_list.push(i);
     }
 
     device_list
@@ -268,6 +303,7 @@ impl Ch347Device {
             index: index as ULONG,
             ts_type: CH347TransType::Parallel,
             spi_cfg: SpiConfig::default(),
+            chip_select: ChipSelect::CS0,
         })
     }
 
@@ -282,24 +318,67 @@ impl Ch347Device {
             fd,
             ts_type: CH347TransType::Parallel,
             spi_cfg: SpiConfig::default(),
+            chip_select: ChipSelect::CS0,
         })
     }
 
-    #[cfg(target_os = "windows")]
     pub fn new_serial(index: u32) -> Option<Ch347Device> {
+        Ch347Device::open_serial(index).ok()
+    }
+
+    #[cfg(target_os = "windows")]
+    pub(crate) fn open_serial(index: u32) -> io::Result<Ch347Device> {
         unsafe {
             if CH347Uart_Open(index as ULONG) == INVALID_HANDLE_VALUE {
-                return None;
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "CH347Uart_Open Fail",
+                ));
             }
         }
 
-        Some(Ch347Device {
+        Ok(Ch347Device {
             index: index as ULONG,
             ts_type: CH347TransType::Serial,
             spi_cfg: SpiConfig::default(),
+            chip_select: ChipSelect::CS0,
         })
     }
 
+    /// The vendor library has no UART support on Linux, the tty is used
+    #[cfg(target_os = "linux")]
+    pub(crate) fn open_serial(index: u32) -> io::Result<Ch347Device> {
+        let port = crate::enum_uart_ports()
+            .into_iter()
+            .nth(index as usize)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No such CH347 UART"))?;
+
+        Ch347Device::open_tty(&port)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn open_tty(port: &crate::UartPort) -> io::Result<Ch347Device> {
+        Ok(Ch347Device {
+            fd: crate::uart::open_tty(&port.path)?,
+            ts_type: CH347TransType::Serial,
+            spi_cfg: SpiConfig::default(),
+            chip_select: ChipSelect::CS0,
+        })
+    }
+
+    pub(crate) fn ts_type(&self) -> &CH347TransType {
+        &self.ts_type
+    }
+
+    /// SPI, I2C, GPIO and JTAG only work on the vendor interface, a UART
+    /// device holds a tty on Linux
+    pub(crate) fn check_parallel(&self) -> Result<(), &'static str> {
+        match self.ts_type {
+            CH347TransType::Parallel => Ok(()),
+            CH347TransType::Serial => Err("Not a SPI/I2C/GPIO/JTAG interface of the CH347"),
+        }
+    }
+
     #[cfg(target_os = "windows")]
     pub fn get_dev_index(&self) -> ULONG {
         self.index
@@ -311,10 +390,36 @@ impl Ch347Device {
     }
 
     pub fn spi_flash(mut self) -> Result<SpiFlash<Ch347Device>, Box<dyn Error>> {
+        self.check_parallel()?;
         self.spi_cfg = self.get_raw_spi_config()?;
         Ok(SpiFlash::new(self))
     }
 
+    /// Open one SpiFlash on CS0 and one on CS1 of the same adapter, e.g. for dual-BIOS boards
+    pub fn spi_flash_dual(
+        mut self,
+    ) -> Result<(SpiFlash<Ch347CsDrive>, SpiFlash<Ch347CsDrive>), Box<dyn Error>> {
+        self.check_parallel()?;
+        self.spi_cfg = self.get_raw_spi_config()?;
+        let dev = Arc::new(self);
+
+        Ok((
+            SpiFlash::new(Ch347CsDrive::new(Arc::clone(&dev), ChipSelect::CS0)),
+            SpiFlash::new(Ch347CsDrive::new(dev, ChipSelect::CS1)),
+        ))
+    }
+
+    pub fn get_chip_select(&self) -> ChipSelect {
+        self.chip_select
+    }
+
+    /// Chip select used by the SpiDrive of this device, polarity takes effect
+    /// on the next `apply_spi_config`
+    pub fn set_chip_select(&mut self, cs: ChipSelect, active_high: bool) {
+        self.chip_select = cs;
+        self.spi_cfg.set_chip_select(cs, active_high);
+    }
+
     pub fn get_raw_info(&self) -> Option<DeviceInfo> {
         let device_info = DeviceInfo::default();
 
@@ -331,6 +436,7 @@ impl Ch347Device {
                 }
                 Some(device_info)
             }
+            #[cfg(target_os = "windows")]
             CH347TransType::Serial => {
                 unsafe {
                     if CH347Uart_GetDeviceInfor(self.get_dev_index(), &device_info as *const _) == 0
@@ -340,6 +446,11 @@ impl Ch347Device {
                 }
                 Some(device_info)
             }
+            #[cfg(target_os = "linux")]
+            CH347TransType::Serial => {
+                let (index, port) = crate::uart::uart_port_of_fd(self.fd)?;
+                Some(DeviceInfo::from_uart_port(index as u8, &port))
+            }
         }
     }
 
@@ -354,6 +465,12 @@ impl Ch347Device {
         Ok(spicfg)
     }
 
+    /// Replace the cached config by the one currently in the controller
+    pub(crate) fn reload_spi_config(&mut self) -> Result<(), &'static str> {
+        self.spi_cfg = self.get_raw_spi_config()?;
+        Ok(())
+    }
+
     pub fn apply_spi_config(&mut self) -> Result<(), &'static str> {
         unsafe {
             if CH347SPI_Init(self.get_dev_index(), &mut self.spi_cfg) == 0 {
@@ -375,12 +492,32 @@ impl Ch347Device {
     }
 
     pub fn i2c_set(&self, speed: I2cSpeed) {
+        if self.check_parallel().is_err() {
+            return;
+        }
+
         unsafe {
             CH347I2C_Set(self.get_dev_index(), speed as ULONG);
         }
     }
 
+    /// Hardware delay before the next I2C stream operation, returns at once
+    pub fn i2c_set_delay_ms(&self, ms: u32) -> Result<(), &'static str> {
+        self.check_parallel()?;
+
+        unsafe {
+            if CH347I2C_SetDelaymS(self.get_dev_index(), ms as ULONG) == 0 {
+                return Err("CH347I2C_SetDelaymS Fail");
+            }
+        }
+        Ok(())
+    }
+
     pub fn i2c_device_detect(&self, addr: u8) -> bool {
+        if self.check_parallel().is_err() {
+            return false;
+        }
+
         unsafe {
             let mut wbuf: [u8; 1] = [addr << 1];
             if CH347StreamI2C(
@@ -398,6 +535,8 @@ impl Ch347Device {
     }
 
     pub fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), ()> {
+        self.check_parallel().map_err(|_| ())?;
+
         i2c_stream(
             self.get_dev_index(),
             wbuf.len() as u32,
@@ -406,14 +545,16 @@ impl Ch347Device {
             rbuf.as_mut_ptr(),
         )
     }
-}
 
-impl SpiDrive for Ch347Device {
-    fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+    pub(crate) fn spi_transfer(
+        &self,
+        cs: ChipSelect,
+        iobuf: &mut [u8],
+    ) -> Result<(), &'static str> {
         unsafe {
             if CH347StreamSPI4(
                 self.get_dev_index(),
-                0x80,
+                cs.to_raw(),
                 iobuf.len() as ULONG,
                 iobuf.as_mut_ptr() as *mut libc::c_void,
             ) == 0
@@ -425,8 +566,9 @@ impl SpiDrive for Ch347Device {
         Ok(())
     }
 
-    fn write_after_read(
+    pub(crate) fn spi_write_after_read(
         &self,
+        cs: ChipSelect,
         write_len: u32,
         read_len: u32,
         iobuf: &mut [u8],
@@ -434,7 +576,7 @@ impl SpiDrive for Ch347Device {
         unsafe {
             if CH347SPI_Read(
                 self.get_dev_index(),
-                0x80,
+                cs.to_raw(),
                 write_len as ULONG,
                 &mut (read_len as ULONG),
                 iobuf.as_mut_ptr() as *mut libc::c_void,
@@ -448,10 +590,96 @@ impl SpiDrive for Ch347Device {
     }
 }
 
+impl SpiDrive for Ch347Device {
+    fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+        self.spi_transfer(self.chip_select, iobuf)
+    }
+
+    fn write_after_read(
+        &self,
+        write_len: u32,
+        read_len: u32,
+        iobuf: &mut [u8],
+    ) -> Result<(), &'static str> {
+        self.spi_write_after_read(self.chip_select, write_len, read_len, iobuf)
+    }
+}
+
+/// SpiDrive bound to one chip select of a CH347 shared with other drives
+pub struct Ch347CsDrive {
+    dev: Arc<Ch347Device>,
+    cs: ChipSelect,
+}
+
+impl Ch347CsDrive {
+    pub fn new(dev: Arc<Ch347Device>, cs: ChipSelect) -> Ch347CsDrive {
+        Ch347CsDrive { dev, cs }
+    }
+
+    pub fn get_chip_select(&self) -> ChipSelect {
+        self.cs
+    }
+
+    pub fn device(&self) -> &Arc<Ch347Device> {
+        &self.dev
+    }
+}
+
+impl SpiDrive for Ch347CsDrive {
+    fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+        self.dev.spi_transfer(self.cs, iobuf)
+    }
+
+    fn write_after_read(
+        &self,
+        write_len: u32,
+        read_len: u32,
+        iobuf: &mut [u8],
+    ) -> Result<(), &'static str> {
+        self.dev
+            .spi_write_after_read(self.cs, write_len, read_len, iobuf)
+    }
+}
+
+impl SpiFlash<Ch347Device> {
+    /// Try the SPI clocks from the fastest to the slowest and keep the first
+    /// one at which the chip reads back consistently.
+    pub fn auto_clock(&mut self, sample_len: usize) -> Result<SpiClockLevel, &'static str> {
+        for i in 0..8 {
+            let level = match SpiClockLevel::from_byte(i) {
+                None => continue,
+                Some(level) => level,
+            };
+
+            self.drive.change_spi_raw_config(|spi_cfg| {
+                spi_cfg.clock = level.to_byte();
+            })?;
+
+            if self.is_stable(sample_len) {
+                return Ok(level);
+            }
+        }
+
+        Err("No stable SPI clock found")
+    }
+}
+
 impl Drop for Ch347Device {
     fn drop(&mut self) {
         unsafe {
-            CH347CloseDevice(self.get_dev_index());
+            match self.ts_type {
+                CH347TransType::Parallel => {
+                    CH347CloseDevice(self.get_dev_index());
+                }
+                #[cfg(target_os = "windows")]
+                CH347TransType::Serial => {
+                    CH347Uart_Close(self.index);
+                }
+                #[cfg(target_os = "linux")]
+                CH347TransType::Serial => {
+                    libc::close(self.fd);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,87 @@
+use std::fmt;
+
+use clap::ValueEnum;
+
+/// I2C EEPROM model, same order as `EEPROM_TYPE` of the vendor library
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum EepromType {
+    #[clap(name = "24c01")]
+    E24C01,
+    #[clap(name = "24c02")]
+    E24C02,
+    #[clap(name = "24c04")]
+    E24C04,
+    #[clap(name = "24c08")]
+    E24C08,
+    #[clap(name = "24c16")]
+    E24C16,
+    #[clap(name = "24c32")]
+    E24C32,
+    #[clap(name = "24c64")]
+    E24C64,
+    #[clap(name = "24c128")]
+    E24C128,
+    #[clap(name = "24c256")]
+    E24C256,
+    #[clap(name = "24c512")]
+    E24C512,
+    #[clap(name = "24c1024")]
+    E24C1024,
+    #[clap(name = "24c2048")]
+    E24C2048,
+    #[clap(name = "24c4096")]
+    E24C4096,
+}
+
+impl EepromType {
+    /// unit: byte
+    pub fn capacity(&self) -> usize {
+        match self {
+            EepromType::E24C01 => 128,
+            EepromType::E24C02 => 256,
+            EepromType::E24C04 => 512,
+            EepromType::E24C08 => 1024,
+            EepromType::E24C16 => 1024 * 2,
+            EepromType::E24C32 => 1024 * 4,
+            EepromType::E24C64 => 1024 * 8,
+            EepromType::E24C128 => 1024 * 16,
+            EepromType::E24C256 => 1024 * 32,
+            EepromType::E24C512 => 1024 * 64,
+            EepromType::E24C1024 => 1024 * 128,
+            EepromType::E24C2048 => 1024 * 256,
+            EepromType::E24C4096 => 1024 * 512,
+        }
+    }
+
+    /// Largest write that may be issued at once, it must not cross a page boundary
+    pub fn page_size(&self) -> usize {
+        match self {
+            EepromType::E24C01 | EepromType::E24C02 => 8,
+            EepromType::E24C04 | EepromType::E24C08 | EepromType::E24C16 => 16,
+            EepromType::E24C32 | EepromType::E24C64 => 32,
+            EepromType::E24C128 | EepromType::E24C256 => 64,
+            EepromType::E24C512 => 128,
+            EepromType::E24C1024 | EepromType::E24C2048 | EepromType::E24C4096 => 256,
+        }
+    }
+
+    /// Length of the word address sent after the device address
+    pub fn addr_bytes(&self) -> usize {
+        if self.capacity() <= 1024 * 2 {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// `EEPROM_TYPE` value of the vendor library
+    pub fn to_raw(&self) -> libc::c_int {
+        *self as libc::c_int
+    }
+}
+
+impl fmt::Display for EepromType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &format!("{:?}", self)[1..])
+    }
+}
@@ -0,0 +1,7 @@
+mod eeprom_type;
+mod soft_eeprom;
+mod vendor_eeprom;
+
+pub use eeprom_type::*;
+pub use soft_eeprom::*;
+pub use vendor_eeprom::*;
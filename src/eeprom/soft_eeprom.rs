@@ -0,0 +1,295 @@
+use std::time::{Duration, Instant};
+
+use crate::i2c::I2cDrive;
+use crate::spi_flash::WriteEvent;
+
+use super::EepromType;
+
+/// Default 7-bit device address of a 24Cxx with A0..A2 tied low
+pub const EEPROM_DEFAULT_ADDR: u8 = 0x50;
+
+/// Longest sequential read issued at once
+const READ_CHUNK: usize = 1024;
+
+/// 24Cxx EEPROM driven over raw I2C transfers, without the vendor helpers
+pub struct SoftEeprom<T: I2cDrive> {
+    pub drive: T,
+    pub eeprom_type: EepromType,
+    /// 7-bit device address, the block select bits must be zero
+    pub addr: u8,
+    /// Longest time to wait for the internal write cycle
+    pub write_timeout: Duration,
+}
+
+impl<T: I2cDrive> SoftEeprom<T> {
+    pub fn new(drive: T, eeprom_type: EepromType) -> SoftEeprom<T> {
+        SoftEeprom {
+            drive,
+            eeprom_type,
+            addr: EEPROM_DEFAULT_ADDR,
+            write_timeout: Duration::from_millis(20),
+        }
+    }
+
+    /// Set the 7-bit device address, the bits used for block select must
+    /// be zero
+    pub fn set_addr(&mut self, addr: u8) -> Result<(), &'static str> {
+        if addr > 0x7F {
+            return Err("Invalid 7-bit address");
+        }
+        if addr & self.block_mask() != 0 {
+            return Err("Address overlaps the block select bits");
+        }
+
+        self.addr = addr;
+        Ok(())
+    }
+
+    fn check_range(&self, addr: u32, len: usize) -> Result<(), &'static str> {
+        if (addr as usize + len) > self.eeprom_type.capacity() {
+            return Err("Address out of eeprom range");
+        }
+
+        Ok(())
+    }
+
+    /// Size of the region addressed by the word address alone, the memory
+    /// above it is selected by the low bits of the device address
+    fn block_size(&self) -> usize {
+        1 << (8 * self.eeprom_type.addr_bytes())
+    }
+
+    /// Device address bits taken by the block number
+    fn block_mask(&self) -> u8 {
+        ((self.eeprom_type.capacity() - 1) / self.block_size()) as u8
+    }
+
+    /// Device address (7-bit) and word address bytes of a memory address
+    fn address(&self, mem_addr: usize) -> (u8, Vec<u8>) {
+        let addr_bytes = self.eeprom_type.addr_bytes();
+        let block = (mem_addr / self.block_size()) as u8;
+        let word = (0..addr_bytes)
+            .rev()
+            .map(|i| (mem_addr >> (8 * i)) as u8)
+            .collect();
+
+        (self.addr | block, word)
+    }
+
+    pub fn read(&self, addr: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        self.check_range(addr, buf.len())?;
+
+        let block_size = self.block_size();
+        let mut i = 0;
+
+        while i < buf.len() {
+            let mem_addr = addr as usize + i;
+            // a sequential read may wrap inside its block, never cross it
+            let len = (block_size - (mem_addr % block_size))
+                .min(buf.len() - i)
+                .min(READ_CHUNK);
+
+            let (dev_addr, word) = self.address(mem_addr);
+            let mut wbuf = vec![dev_addr << 1];
+            wbuf.extend_from_slice(&word);
+
+            self.drive.i2c_stream(&wbuf, &mut buf[i..(i + len)])?;
+
+            i += len;
+        }
+
+        Ok(())
+    }
+
+    /// Poll the device address until the write cycle is over,
+    /// returns the number of polls that were not acknowledged
+    fn wait_write_cycle(&self, dev_addr: u8) -> Result<usize, &'static str> {
+        let start = Instant::now();
+        let mut busy = 0;
+
+        loop {
+            if self.drive.i2c_probe(dev_addr) {
+                return Ok(busy);
+            }
+            busy += 1;
+
+            if start.elapsed() > self.write_timeout {
+                return Err("EEPROM write cycle timeout");
+            }
+        }
+    }
+
+    pub fn write(&self, addr: u32, buf: &[u8]) -> Result<(), &'static str> {
+        self.write_with_callback(|_| true, addr, buf)
+    }
+
+    /// Write page by page, `cbk` returns false to stop early
+    pub fn write_with_callback<F>(
+        &self,
+        mut cbk: F,
+        addr: u32,
+        buf: &[u8],
+    ) -> Result<(), &'static str>
+    where
+        F: FnMut(WriteEvent) -> bool,
+    {
+        self.check_range(addr, buf.len())?;
+
+        let page_size = self.eeprom_type.page_size();
+        let mut i = 0;
+
+        while i < buf.len() {
+            let mem_addr = addr as usize + i;
+            // a page write wraps around inside the page, never cross it
+            let len = (page_size - (mem_addr % page_size)).min(buf.len() - i);
+            let data = &buf[i..(i + len)];
+
+            let (dev_addr, word) = self.address(mem_addr);
+            let mut wbuf = vec![dev_addr << 1];
+            wbuf.extend_from_slice(&word);
+            wbuf.extend_from_slice(data);
+
+            if self.drive.i2c_stream(&wbuf, &mut []).is_err() {
+                // the address was acknowledged but the data was not
+                if self.drive.i2c_probe(dev_addr) {
+                    return Err("EEPROM is write protected");
+                }
+                return Err("EEPROM no acknowledge");
+            }
+
+            // a protected chip ignores the data and never starts a write cycle
+            if self.wait_write_cycle(dev_addr)? == 0 {
+                let mut rbuf = vec![0; len];
+                self.read(mem_addr as u32, &mut rbuf)?;
+                if rbuf != data {
+                    return Err("EEPROM is write protected");
+                }
+            }
+
+            if !cbk(WriteEvent::Block(i, len)) {
+                return Ok(());
+            }
+
+            i += len;
+        }
+
+        cbk(WriteEvent::Finish(buf.len()));
+        Ok(())
+    }
+
+    /// Fill the whole chip with 0xFF
+    pub fn erase_with_callback<F>(&self, cbk: F) -> Result<(), &'static str>
+    where
+        F: FnMut(WriteEvent) -> bool,
+    {
+        let buf = vec![0xFF; self.eeprom_type.capacity()];
+        self.write_with_callback(cbk, 0, &buf)
+    }
+}
+
+#[test]
+pub fn test_soft_eeprom() {
+    use std::cell::{Cell, RefCell};
+
+    /// 24Cxx model: page wrap, block select bits and a busy write cycle
+    struct MockEeprom {
+        eeprom_type: EepromType,
+        mem: RefCell<Vec<u8>>,
+        busy: Cell<usize>,
+        write_protect: bool,
+        writes: RefCell<Vec<(u8, usize)>>,
+    }
+
+    impl MockEeprom {
+        fn locate(&self, wbuf: &[u8]) -> (usize, usize) {
+            let addr_bytes = self.eeprom_type.addr_bytes();
+            let block = ((wbuf[0] >> 1) & 0x07) as usize;
+            let word = wbuf[1..(1 + addr_bytes)]
+                .iter()
+                .fold(0, |a, &b| (a << 8) | b as usize);
+            let addr = (block << (8 * addr_bytes)) | word;
+            (addr % self.eeprom_type.capacity(), 1 + addr_bytes)
+        }
+    }
+
+    impl I2cDrive for MockEeprom {
+        fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+            if self.busy.get() != 0 {
+                self.busy.set(self.busy.get() - 1);
+                return Err("nack");
+            }
+            if (wbuf[0] >> 4) != 0x0A {
+                return Err("nack");
+            }
+            if wbuf.len() == 1 {
+                return Ok(());
+            }
+
+            let (addr, data_offset) = self.locate(wbuf);
+            let data = &wbuf[data_offset..];
+            if !data.is_empty() {
+                self.writes.borrow_mut().push((wbuf[0] >> 1, data.len()));
+                if self.write_protect {
+                    return Ok(());
+                }
+                let page_size = self.eeprom_type.page_size();
+                let page = addr - (addr % page_size);
+                let mut mem = self.mem.borrow_mut();
+                for (i, b) in data.iter().enumerate() {
+                    mem[page + (addr + i) % page_size] = *b;
+                }
+                self.busy.set(3);
+            }
+
+            let mem = self.mem.borrow();
+            for (i, b) in rbuf.iter_mut().enumerate() {
+                *b = mem[(addr + i) % mem.len()];
+            }
+            Ok(())
+        }
+    }
+
+    let new_mock = |eeprom_type: EepromType, write_protect| MockEeprom {
+        eeprom_type,
+        mem: RefCell::new(vec![0xFF; eeprom_type.capacity()]),
+        busy: Cell::new(0),
+        write_protect,
+        writes: RefCell::new(Vec::new()),
+    };
+
+    // 24C16: 1 byte word address, block select bits, 16 byte pages
+    let eeprom = SoftEeprom::new(new_mock(EepromType::E24C16, false), EepromType::E24C16);
+    let data: Vec<u8> = (0..600).map(|i| i as u8).collect();
+    eeprom.write(0xF9, &data).unwrap();
+    let writes = eeprom.drive.writes.borrow().clone();
+    assert_eq!(writes[0], (0x50, 7));
+    assert_eq!(writes[1], (0x51, 16));
+    assert!(writes.iter().all(|&(_, len)| len <= 16));
+
+    let mut rbuf = vec![0; data.len()];
+    eeprom.read(0xF9, &mut rbuf).unwrap();
+    assert_eq!(rbuf, data);
+
+    // 24C256: 2 byte word address, 64 byte pages
+    let mut eeprom = SoftEeprom::new(new_mock(EepromType::E24C256, false), EepromType::E24C256);
+    eeprom.write(0x1234, &data).unwrap();
+    let mut rbuf = vec![0; data.len()];
+    eeprom.read(0x1234, &mut rbuf).unwrap();
+    assert_eq!(rbuf, data);
+    assert!(eeprom.read(0x7FFF, &mut [0; 2]).is_err());
+    assert!(eeprom.set_addr(0xA0).is_err());
+    eeprom.set_addr(0x57).unwrap();
+    assert_eq!(eeprom.addr, 0x57);
+
+    // 24C04 uses bit 0 of the device address for the upper 256 bytes
+    let mut eeprom = SoftEeprom::new(new_mock(EepromType::E24C04, false), EepromType::E24C04);
+    assert!(eeprom.set_addr(0x51).is_err());
+    eeprom.set_addr(0x52).unwrap();
+
+    // write protected chip never starts a write cycle
+    let eeprom = SoftEeprom::new(new_mock(EepromType::E24C02, true), EepromType::E24C02);
+    assert_eq!(
+        eeprom.write(0, &[0x55; 4]),
+        Err("EEPROM is write protected")
+    );
+}
@@ -0,0 +1,111 @@
+use crate::ch347lib::*;
+use crate::spi_flash::WriteEvent;
+use crate::windows::basetsd::*;
+
+use super::EepromType;
+
+/// I2C EEPROM accessed through `CH347ReadEEPROM`/`CH347WriteEEPROM`
+pub struct Eeprom {
+    dev: Ch347Device,
+    pub eeprom_type: EepromType,
+}
+
+impl Ch347Device {
+    pub fn eeprom(self, eeprom_type: EepromType) -> Eeprom {
+        Eeprom {
+            dev: self,
+            eeprom_type,
+        }
+    }
+}
+
+impl Eeprom {
+    pub fn device(&self) -> &Ch347Device {
+        &self.dev
+    }
+
+    fn check_range(&self, addr: u32, len: usize) -> Result<(), &'static str> {
+        if (addr as usize + len) > self.eeprom_type.capacity() {
+            return Err("Address out of eeprom range");
+        }
+
+        Ok(())
+    }
+
+    pub fn read(&self, addr: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        self.check_range(addr, buf.len())?;
+
+        unsafe {
+            if CH347ReadEEPROM(
+                self.dev.get_dev_index(),
+                self.eeprom_type.to_raw(),
+                addr as ULONG,
+                buf.len() as ULONG,
+                buf.as_mut_ptr(),
+            ) == 0
+            {
+                return Err("CH347ReadEEPROM Fail");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write(&self, addr: u32, buf: &[u8]) -> Result<(), &'static str> {
+        self.write_with_callback(|_| true, addr, buf)
+    }
+
+    /// Write page by page, `cbk` returns false to stop early
+    pub fn write_with_callback<F>(
+        &self,
+        mut cbk: F,
+        addr: u32,
+        buf: &[u8],
+    ) -> Result<(), &'static str>
+    where
+        F: FnMut(WriteEvent) -> bool,
+    {
+        self.check_range(addr, buf.len())?;
+
+        let page_size = self.eeprom_type.page_size();
+        let mut i = 0;
+
+        while i < buf.len() {
+            let addr_offset = addr as usize + i;
+            // never cross a page boundary
+            let len = (page_size - (addr_offset % page_size)).min(buf.len() - i);
+            let mut wbuf = buf[i..(i + len)].to_vec();
+
+            unsafe {
+                if CH347WriteEEPROM(
+                    self.dev.get_dev_index(),
+                    self.eeprom_type.to_raw(),
+                    addr_offset as ULONG,
+                    len as ULONG,
+                    wbuf.as_mut_ptr(),
+                ) == 0
+                {
+                    return Err("CH347WriteEEPROM Fail");
+                }
+            }
+
+            if !cbk(WriteEvent::Block(i, len)) {
+                return Ok(());
+            }
+
+            i += len;
+        }
+
+        cbk(WriteEvent::Finish(buf.len()));
+        Ok(())
+    }
+
+    /// Fill the whole chip with 0xFF
+    pub fn erase_with_callback<F>(&self, cbk: F) -> Result<(), &'static str>
+    where
+        F: FnMut(WriteEvent) -> bool,
+    {
+        let buf = vec![0xFF; self.eeprom_type.capacity()];
+        self.write_with_callback(cbk, 0, &buf)
+    }
+}
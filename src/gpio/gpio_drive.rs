@@ -0,0 +1,20 @@
+use crate::{gpio_get, gpio_set, Ch347Device};
+
+/// Raw access to the 8 GPIO lines, GPIO0-7 are bits 0-7
+pub trait GpioDrive {
+    /// (direction, data), a direction bit of 1 is output
+    fn gpio_read(&self) -> Result<(u8, u8), &'static str>;
+
+    /// Only the pins set in `enable` are changed
+    fn gpio_write(&self, enable: u8, dir: u8, data: u8) -> Result<(), &'static str>;
+}
+
+impl GpioDrive for Ch347Device {
+    fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+        gpio_get(self.get_dev_index()).map_err(|_| "CH347GPIO_Get Fail")
+    }
+
+    fn gpio_write(&self, enable: u8, dir: u8, data: u8) -> Result<(), &'static str> {
+        gpio_set(self.get_dev_index(), enable, dir, data).map_err(|_| "CH347GPIO_Set Fail")
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Mutex;
+
+use super::GpioDrive;
+use crate::Ch347Device;
+
+pub const GPIO_PIN_COUNT: u8 = 8;
+
+struct GpioState {
+    dir: u8,
+    data: u8,
+    /// pins handed out as `Pin`
+    taken: u8,
+}
+
+/// GPIO port handle, caches direction and output data so a pin change
+/// never disturbs the other pins
+pub struct Gpio<T: GpioDrive> {
+    pub drive: T,
+    state: Mutex<GpioState>,
+}
+
+/// One GPIO owned exclusively until dropped
+pub struct Pin<'a, T: GpioDrive> {
+    gpio: &'a Gpio<T>,
+    num: u8,
+}
+
+impl Ch347Device {
+    pub fn gpio(self) -> Result<Gpio<Ch347Device>, &'static str> {
+        self.check_parallel()?;
+        Gpio::new(self)
+    }
+}
+
+impl<T: GpioDrive> Gpio<T> {
+    /// Starts from the current hardware state
+    pub fn new(drive: T) -> Result<Gpio<T>, &'static str> {
+        let (dir, data) = drive.gpio_read()?;
+
+        Ok(Gpio {
+            drive,
+            state: Mutex::new(GpioState {
+                dir,
+                data,
+                taken: 0,
+            }),
+        })
+    }
+
+    /// Take pin `num` (0-7), fails if it is already taken
+    pub fn pin(&self, num: u8) -> Result<Pin<'_, T>, &'static str> {
+        if num >= GPIO_PIN_COUNT {
+            return Err("GPIO number out of range");
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.taken & (1 << num) != 0 {
+            return Err("GPIO is already taken");
+        }
+        state.taken |= 1 << num;
+
+        Ok(Pin { gpio: self, num })
+    }
+
+    /// Cached (direction, output data)
+    pub fn cached(&self) -> (u8, u8) {
+        let state = self.state.lock().unwrap();
+        (state.dir, state.data)
+    }
+
+    /// Input levels of all pins, outputs read back their driven level
+    pub fn read(&self) -> Result<u8, &'static str> {
+        Ok(self.drive.gpio_read()?.1)
+    }
+
+    /// Change direction and data of the pins in `mask` only
+    pub fn write_masked(&self, mask: u8, dir: u8, data: u8) -> Result<(), &'static str> {
+        let mut state = self.state.lock().unwrap();
+
+        let new_dir = (state.dir & !mask) | (dir & mask);
+        let new_data = (state.data & !mask) | (data & mask);
+        self.drive.gpio_write(mask, new_dir, new_data)?;
+
+        state.dir = new_dir;
+        state.data = new_data;
+        Ok(())
+    }
+
+    /// Open drain emulation, `release` makes the pins inputs and lets the
+    /// pull-up take the line high, otherwise they are driven low
+    pub fn open_drain(&self, mask: u8, release: bool) -> Result<(), &'static str> {
+        let dir = if release { 0 } else { mask };
+        self.write_masked(mask, dir, 0)
+    }
+}
+
+impl<T: GpioDrive> Pin<'_, T> {
+    pub fn num(&self) -> u8 {
+        self.num
+    }
+
+    fn mask(&self) -> u8 {
+        1 << self.num
+    }
+
+    pub fn is_output(&self) -> bool {
+        self.gpio.cached().0 & self.mask() != 0
+    }
+
+    /// Switch to output, the level is applied in the same transfer
+    pub fn set_output(&self, high: bool) -> Result<(), &'static str> {
+        let data = if high { self.mask() } else { 0 };
+        self.gpio.write_masked(self.mask(), self.mask(), data)
+    }
+
+    pub fn set_input(&self) -> Result<(), &'static str> {
+        let data = self.gpio.cached().1;
+        self.gpio.write_masked(self.mask(), 0, data)
+    }
+
+    /// Drive the level, the pin must be an output
+    pub fn set(&self, high: bool) -> Result<(), &'static str> {
+        if !self.is_output() {
+            return Err("GPIO is not an output");
+        }
+
+        self.set_output(high)
+    }
+
+    pub fn set_high(&self) -> Result<(), &'static str> {
+        self.set(true)
+    }
+
+    pub fn set_low(&self) -> Result<(), &'static str> {
+        self.set(false)
+    }
+
+    pub fn toggle(&self) -> Result<(), &'static str> {
+        self.set(!self.is_set_high())
+    }
+
+    /// Level read from the pin
+    pub fn is_high(&self) -> Result<bool, &'static str> {
+        Ok(self.gpio.read()? & self.mask() != 0)
+    }
+
+    pub fn is_low(&self) -> Result<bool, &'static str> {
+        Ok(!self.is_high()?)
+    }
+
+    /// Level last written to the output latch
+    pub fn is_set_high(&self) -> bool {
+        self.gpio.cached().1 & self.mask() != 0
+    }
+}
+
+impl<T: GpioDrive> Drop for Pin<'_, T> {
+    fn drop(&mut self) {
+        self.gpio.state.lock().unwrap().taken &= !self.mask();
+    }
+}
+
+#[test]
+pub fn test_gpio_pin() {
+    use std::cell::RefCell;
+
+    struct MockGpio {
+        writes: RefCell<Vec<(u8, u8, u8)>>,
+    }
+
+    impl GpioDrive for MockGpio {
+        fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+            Ok((0x81, 0x80))
+        }
+
+        fn gpio_write(&self, enable: u8, dir: u8, data: u8) -> Result<(), &'static str> {
+            self.writes.borrow_mut().push((enable, dir, data));
+            Ok(())
+        }
+    }
+
+    let gpio = Gpio::new(MockGpio {
+        writes: RefCell::new(Vec::new()),
+    })
+    .unwrap();
+
+    let reset = gpio.pin(2).unwrap();
+    assert!(gpio.pin(2).is_err());
+    assert!(gpio.pin(8).is_err());
+    assert!(reset.set_high().is_err());
+
+    reset.set_output(true).unwrap();
+    reset.toggle().unwrap();
+    assert!(!reset.is_set_high());
+    // GPIO0 and GPIO7 keep their direction and level
+    assert_eq!(
+        *gpio.drive.writes.borrow(),
+        vec![(0x04, 0x85, 0x84), (0x04, 0x85, 0x80)]
+    );
+
+    drop(reset);
+    assert!(gpio.pin(2).is_ok());
+}
@@ -0,0 +1,323 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{sleep_until, Gpio, GpioDrive, GPIO_PIN_COUNT};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PwmChannel {
+    pub pin: u8,
+    /// range 0~1
+    pub duty: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PwmConfig {
+    /// unit: Hz
+    pub freq: f64,
+    pub channels: Vec<PwmChannel>,
+    /// stop after this time
+    pub duration: Option<Duration>,
+    /// stop after this many periods
+    pub cycles: Option<u64>,
+}
+
+impl PwmConfig {
+    pub fn new(freq: f64, channels: Vec<PwmChannel>) -> PwmConfig {
+        PwmConfig {
+            freq,
+            channels,
+            duration: None,
+            cycles: None,
+        }
+    }
+
+    fn check(&self) -> Result<(), &'static str> {
+        if self.freq.is_nan() || self.freq <= 0.0 {
+            return Err("PWM frequency must be above 0");
+        }
+        if self.channels.is_empty() {
+            return Err("No PWM pin given");
+        }
+
+        let mut mask = 0u8;
+        for c in &self.channels {
+            if c.pin >= GPIO_PIN_COUNT {
+                return Err("GPIO number out of range");
+            }
+            if !(0.0..=1.0).contains(&c.duty) {
+                return Err("PWM duty must be in range 0~1");
+            }
+            if mask & (1 << c.pin) != 0 {
+                return Err("PWM pin given twice");
+            }
+            mask |= 1 << c.pin;
+        }
+
+        Ok(())
+    }
+
+    fn mask(&self) -> u8 {
+        self.channels.iter().fold(0, |m, c| m | (1 << c.pin))
+    }
+}
+
+/// Result of a PWM run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PwmReport {
+    /// complete periods output
+    pub cycles: u64,
+    pub elapsed: Duration,
+    /// periods that could not be output in time and were dropped
+    pub late: u64,
+}
+
+impl PwmReport {
+    /// Measured output frequency, unit: Hz
+    pub fn frequency(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+
+        self.cycles as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+struct PwmShared {
+    stop: AtomicBool,
+    duty: Mutex<Vec<PwmChannel>>,
+}
+
+/// Stops a running `PwmOutput` from another thread, eg. a Ctrl-C handler
+#[derive(Clone)]
+pub struct PwmStop(Arc<PwmShared>);
+
+impl PwmStop {
+    pub fn stop(&self) {
+        self.0.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+type PwmResult<T> = Result<(Gpio<T>, PwmReport), &'static str>;
+
+/// Software PWM on a background thread
+///
+/// Edges are scheduled on absolute deadlines, so the time taken by the
+/// USB transfers does not add up into a lower frequency. The pins get their
+/// direction and level back when the output stops or is dropped.
+pub struct PwmOutput<T: GpioDrive + Send + 'static> {
+    shared: Arc<PwmShared>,
+    /// taken by `wait`, joined on drop otherwise
+    handle: Option<JoinHandle<PwmResult<T>>>,
+}
+
+impl<T: GpioDrive + Send + 'static> PwmOutput<T> {
+    pub fn start(gpio: Gpio<T>, config: PwmConfig) -> Result<PwmOutput<T>, &'static str> {
+        config.check()?;
+
+        let shared = Arc::new(PwmShared {
+            stop: AtomicBool::new(false),
+            duty: Mutex::new(config.channels.clone()),
+        });
+
+        let thread_shared = shared.clone();
+        let handle = thread::spawn(move || run(gpio, config, thread_shared));
+
+        Ok(PwmOutput {
+            shared,
+            handle: Some(handle),
+        })
+    }
+
+    /// Takes effect from the next period
+    pub fn set_duty(&self, pin: u8, duty: f32) -> Result<(), &'static str> {
+        if !(0.0..=1.0).contains(&duty) {
+            return Err("PWM duty must be in range 0~1");
+        }
+
+        let mut channels = self.shared.duty.lock().unwrap();
+        match channels.iter_mut().find(|c| c.pin == pin) {
+            Some(c) => c.duty = duty,
+            None => return Err("GPIO is not a PWM output"),
+        }
+
+        Ok(())
+    }
+
+    pub fn stop_handle(&self) -> PwmStop {
+        PwmStop(self.shared.clone())
+    }
+
+    /// true once the duration or cycle limit is reached
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(|h| h.is_finished())
+    }
+
+    /// Wait for the duration or cycle limit, or for a `PwmStop`
+    pub fn wait(mut self) -> PwmResult<T> {
+        match self.handle.take() {
+            Some(handle) => handle.join().unwrap_or(Err("PWM thread panicked")),
+            None => Err("PWM thread already joined"),
+        }
+    }
+
+    pub fn stop(self) -> PwmResult<T> {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        self.wait()
+    }
+}
+
+impl<T: GpioDrive + Send + 'static> Drop for PwmOutput<T> {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run<T: GpioDrive>(gpio: Gpio<T>, config: PwmConfig, shared: Arc<PwmShared>) -> PwmResult<T> {
+    let mask = config.mask();
+    let (saved_dir, saved_data) = gpio.cached();
+
+    let ret = output(&gpio, &config, &shared);
+
+    // restore even if the output failed half way
+    let restore = gpio.write_masked(mask, saved_dir, saved_data);
+    let report = ret?;
+    restore?;
+
+    Ok((gpio, report))
+}
+
+fn output<T: GpioDrive>(
+    gpio: &Gpio<T>,
+    config: &PwmConfig,
+    shared: &PwmShared,
+) -> Result<PwmReport, &'static str> {
+    let mask = config.mask();
+    let period = Duration::from_secs_f64(1.0 / config.freq);
+
+    let start = Instant::now();
+    let mut period_start = start;
+    let mut cycles = 0;
+    let mut late = 0;
+
+    loop {
+        if shared.stop.load(Ordering::SeqCst)
+            || config.cycles.is_some_and(|n| cycles >= n)
+            || config.duration.is_some_and(|d| start.elapsed() >= d)
+        {
+            break;
+        }
+
+        let mut channels = shared.duty.lock().unwrap().clone();
+        channels.sort_by(|a, b| a.duty.total_cmp(&b.duty));
+
+        let high = channels
+            .iter()
+            .filter(|c| c.duty > 0.0)
+            .fold(0, |m, c| m | (1 << c.pin));
+        gpio.write_masked(mask, mask, high)?;
+
+        // falling edges in order of duty
+        let mut data = high;
+        for c in channels.iter().filter(|c| c.duty > 0.0 && c.duty < 1.0) {
+            sleep_until(period_start + period.mul_f32(c.duty));
+            data &= !(1 << c.pin);
+            gpio.write_masked(mask, mask, data)?;
+        }
+
+        cycles += 1;
+        period_start += period;
+
+        // more than a period behind, drop the lost time instead of
+        // bursting short periods to catch up
+        let now = Instant::now();
+        if now > period_start + period {
+            late += 1;
+            period_start = now;
+        }
+
+        sleep_until(period_start);
+    }
+
+    Ok(PwmReport {
+        cycles,
+        elapsed: start.elapsed(),
+        late,
+    })
+}
+
+#[test]
+pub fn test_pwm_output() {
+    /// Output latch shared with the test
+    struct MockLatch {
+        writes: Arc<Mutex<Vec<(u8, u8)>>>,
+    }
+
+    impl GpioDrive for MockLatch {
+        fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+            Ok((0x80, 0x80))
+        }
+
+        fn gpio_write(&self, _enable: u8, dir: u8, data: u8) -> Result<(), &'static str> {
+            self.writes.lock().unwrap().push((dir, data));
+            Ok(())
+        }
+    }
+
+    let writes = Arc::new(Mutex::new(Vec::new()));
+    let gpio = Gpio::new(MockLatch {
+        writes: writes.clone(),
+    })
+    .unwrap();
+
+    let mut config = PwmConfig::new(
+        1000.0,
+        vec![
+            PwmChannel { pin: 0, duty: 0.5 },
+            PwmChannel { pin: 1, duty: 0.25 },
+            PwmChannel { pin: 2, duty: 1.0 },
+        ],
+    );
+    config.cycles = Some(4);
+
+    let pwm = PwmOutput::start(gpio, config.clone()).unwrap();
+    let (gpio, report) = pwm.wait().unwrap();
+    assert_eq!(report.cycles, 4);
+    assert!(report.frequency() > 0.0);
+
+    let log = writes.lock().unwrap().clone();
+    assert_eq!(log.len(), 4 * 3 + 1);
+    assert_eq!(&log[0..3], &[(0x87, 0x87), (0x87, 0x85), (0x87, 0x84)]);
+    // GPIO0-2 are inputs again, GPIO7 untouched
+    assert_eq!(log.last(), Some(&(0x80, 0x80)));
+    assert_eq!(gpio.cached(), (0x80, 0x80));
+
+    // dropping an endless output stops it and restores the pins too
+    let pwm = PwmOutput::start(
+        gpio,
+        PwmConfig::new(1000.0, vec![PwmChannel { pin: 0, duty: 0.5 }]),
+    )
+    .unwrap();
+    thread::sleep(Duration::from_millis(5));
+    drop(pwm);
+    let cnt = writes.lock().unwrap().len();
+    assert_eq!(writes.lock().unwrap().last(), Some(&(0x80, 0x80)));
+    thread::sleep(Duration::from_millis(5));
+    assert_eq!(writes.lock().unwrap().len(), cnt);
+
+    let gpio = Gpio::new(MockLatch {
+        writes: writes.clone(),
+    })
+    .unwrap();
+
+    config.channels[1].duty = 1.5;
+    assert!(PwmOutput::start(gpio, config).is_err());
+}
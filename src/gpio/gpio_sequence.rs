@@ -0,0 +1,329 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{Gpio, GpioDrive, GPIO_PIN_COUNT};
+
+/// One step of a `GpioSequence`, pins are given as a mask
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GpioStep {
+    /// Drive the pins high
+    Set(u8),
+    /// Drive the pins low
+    Clear(u8),
+    /// Release the pins
+    Input(u8),
+    Wait(Duration),
+    /// Poll until the pin reads `high`, fails after `timeout`
+    WaitFor {
+        pin: u8,
+        high: bool,
+        timeout: Duration,
+    },
+    Repeat(u32, Vec<GpioStep>),
+}
+
+impl fmt::Display for GpioStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpioStep::Set(m) => write!(f, "set 0x{:02X}", m),
+            GpioStep::Clear(m) => write!(f, "clear 0x{:02X}", m),
+            GpioStep::Input(m) => write!(f, "input 0x{:02X}", m),
+            GpioStep::Wait(d) => write!(f, "wait {}", humantime::format_duration(*d)),
+            GpioStep::WaitFor { pin, high, timeout } => write!(
+                f,
+                "wait-for GPIO{} {} {}",
+                pin,
+                if *high { "high" } else { "low" },
+                humantime::format_duration(*timeout)
+            ),
+            GpioStep::Repeat(n, steps) => write!(f, "repeat {} ({} steps)", n, steps.len()),
+        }
+    }
+}
+
+/// Line based GPIO script, one step per line, `#` starts a comment
+///
+/// ```text
+/// alias RESET 3
+/// alias VCC GPIO5
+/// clear RESET
+/// set VCC
+/// wait 50ms
+/// wait-for 6 high 100ms
+/// repeat 3
+///   set 0,1
+///   wait 1ms
+///   clear 0,1
+/// end
+/// input RESET
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GpioSequence {
+    pub steps: Vec<GpioStep>,
+}
+
+/// Pin list like `0,3`, `GPIO4` or alias names
+fn parse_pins(input: &str, aliases: &BTreeMap<String, u8>) -> Result<u8, String> {
+    let mut mask = 0;
+
+    for item in input.split(',').filter(|s| !s.is_empty()) {
+        let lower = item.to_lowercase();
+
+        if let Some(m) = aliases.get(&lower) {
+            mask |= m;
+            continue;
+        }
+
+        match lower.strip_prefix("gpio").unwrap_or(&lower).parse::<u8>() {
+            Ok(n) if n < GPIO_PIN_COUNT => mask |= 1 << n,
+            _ => return Err(format!("invalid pin {:?}", item)),
+        }
+    }
+
+    if mask == 0 {
+        return Err("no pin given".to_string());
+    }
+
+    Ok(mask)
+}
+
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    humantime::parse_duration(input).map_err(|e| format!("invalid time {:?}: {}", input, e))
+}
+
+impl FromStr for GpioSequence {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut aliases = BTreeMap::new();
+        // the open repeat blocks, the bottom one is the sequence itself
+        let mut blocks: Vec<(u32, Vec<GpioStep>)> = vec![(1, Vec::new())];
+
+        for (n, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((cmd, args)) = words.split_first() else {
+                continue;
+            };
+            let pins = || parse_pins(&args.join(","), &aliases);
+
+            let step = match (cmd.to_lowercase().as_str(), args) {
+                ("alias", [name, pin]) => {
+                    let mask =
+                        parse_pins(pin, &aliases).map_err(|e| format!("line {}: {}", n + 1, e))?;
+                    aliases.insert(name.to_lowercase(), mask);
+                    continue;
+                }
+                ("set", [_, ..]) => pins().map(GpioStep::Set),
+                ("clear", [_, ..]) => pins().map(GpioStep::Clear),
+                ("input", [_, ..]) => pins().map(GpioStep::Input),
+                ("wait", [time]) => parse_duration(time).map(GpioStep::Wait),
+                ("wait-for", [pin, level, timeout]) => {
+                    let high = match level.to_lowercase().as_str() {
+                        "high" | "1" => Ok(true),
+                        "low" | "0" => Ok(false),
+                        _ => Err(format!("invalid level {:?}", level)),
+                    };
+
+                    match (parse_pins(pin, &aliases), high, parse_duration(timeout)) {
+                        (Ok(mask), _, _) if mask.count_ones() != 1 => {
+                            Err("wait-for takes a single pin".to_string())
+                        }
+                        (Ok(mask), Ok(high), Ok(timeout)) => Ok(GpioStep::WaitFor {
+                            pin: mask.trailing_zeros() as u8,
+                            high,
+                            timeout,
+                        }),
+                        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => Err(e),
+                    }
+                }
+                ("repeat", [count]) => {
+                    let count = count
+                        .parse()
+                        .map_err(|_| format!("line {}: invalid count {:?}", n + 1, count))?;
+                    blocks.push((count, Vec::new()));
+                    continue;
+                }
+                ("end", []) if blocks.len() > 1 => {
+                    let (count, steps) = blocks.pop().unwrap_or_default();
+                    Ok(GpioStep::Repeat(count, steps))
+                }
+                ("end", []) => Err("end without repeat".to_string()),
+                _ => Err(format!("unknown step {:?}", line)),
+            };
+
+            let step = step.map_err(|e| format!("line {}: {}", n + 1, e))?;
+            if let Some((_, steps)) = blocks.last_mut() {
+                steps.push(step);
+            }
+        }
+
+        if blocks.len() != 1 {
+            return Err("repeat without end".into());
+        }
+
+        Ok(GpioSequence {
+            steps: blocks.pop().unwrap_or_default().1,
+        })
+    }
+}
+
+impl GpioSequence {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<GpioSequence, Box<dyn Error>> {
+        fs::read_to_string(path)?.parse()
+    }
+}
+
+/// Runs `GpioSequence`s on a GPIO port
+pub struct GpioSequencer<'a, T: GpioDrive> {
+    gpio: &'a Gpio<T>,
+    /// time between two reads of a `wait-for`
+    pub poll_interval: Duration,
+}
+
+impl<T: GpioDrive> Gpio<T> {
+    pub fn sequencer(&self) -> GpioSequencer<'_, T> {
+        GpioSequencer {
+            gpio: self,
+            poll_interval: Duration::from_millis(1),
+        }
+    }
+}
+
+impl<T: GpioDrive> GpioSequencer<'_, T> {
+    pub fn run(&self, seq: &GpioSequence) -> Result<(), &'static str> {
+        self.run_with_callback(|_| {}, seq)
+    }
+
+    /// `cbk` is called before each step is executed
+    pub fn run_with_callback<F>(&self, mut cbk: F, seq: &GpioSequence) -> Result<(), &'static str>
+    where
+        F: FnMut(&GpioStep),
+    {
+        self.run_steps(&mut cbk, &seq.steps)
+    }
+
+    fn run_steps<F>(&self, cbk: &mut F, steps: &[GpioStep]) -> Result<(), &'static str>
+    where
+        F: FnMut(&GpioStep),
+    {
+        for step in steps {
+            cbk(step);
+
+            match step {
+                GpioStep::Set(m) => self.gpio.write_masked(*m, *m, *m)?,
+                GpioStep::Clear(m) => self.gpio.write_masked(*m, *m, 0)?,
+                GpioStep::Input(m) => {
+                    let data = self.gpio.cached().1;
+                    self.gpio.write_masked(*m, 0, data)?;
+                }
+                GpioStep::Wait(d) => thread::sleep(*d),
+                GpioStep::WaitFor { pin, high, timeout } => {
+                    let start = Instant::now();
+
+                    while ((self.gpio.read()? & (1 << pin)) != 0) != *high {
+                        if start.elapsed() > *timeout {
+                            return Err("GPIO wait-for timeout");
+                        }
+                        thread::sleep(self.poll_interval);
+                    }
+                }
+                GpioStep::Repeat(n, inner) => {
+                    for _ in 0..*n {
+                        self.run_steps(cbk, inner)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+pub fn test_gpio_sequence() {
+    use std::cell::{Cell, RefCell};
+
+    /// GPIO6 goes high after a few reads
+    struct MockBoard {
+        writes: RefCell<Vec<(u8, u8, u8)>>,
+        reads: Cell<u32>,
+    }
+
+    impl GpioDrive for MockBoard {
+        fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+            self.reads.set(self.reads.get() + 1);
+            Ok((0, if self.reads.get() > 3 { 0x40 } else { 0 }))
+        }
+
+        fn gpio_write(&self, enable: u8, dir: u8, data: u8) -> Result<(), &'static str> {
+            self.writes.borrow_mut().push((enable, dir, data));
+            Ok(())
+        }
+    }
+
+    let seq: GpioSequence = "
+        # power on with RESET held
+        alias RESET 3
+        alias VCC GPIO5
+        clear RESET
+        set VCC
+        wait 1ms
+        wait-for 6 high 100ms
+        repeat 2
+          set 0, 1
+          clear 0,1
+        end
+        input reset
+    "
+    .parse()
+    .unwrap();
+    assert_eq!(seq.steps.len(), 6);
+    assert_eq!(
+        seq.steps[4],
+        GpioStep::Repeat(2, vec![GpioStep::Set(0x03), GpioStep::Clear(0x03)])
+    );
+
+    let gpio = Gpio::new(MockBoard {
+        writes: RefCell::new(Vec::new()),
+        reads: Cell::new(0),
+    })
+    .unwrap();
+    let mut sequencer = gpio.sequencer();
+    sequencer.poll_interval = Duration::ZERO;
+    sequencer.run(&seq).unwrap();
+    assert_eq!(
+        *gpio.drive.writes.borrow(),
+        vec![
+            (0x08, 0x08, 0x00),
+            (0x20, 0x28, 0x20),
+            (0x03, 0x2B, 0x23),
+            (0x03, 0x2B, 0x20),
+            (0x03, 0x2B, 0x23),
+            (0x03, 0x2B, 0x20),
+            (0x08, 0x23, 0x20),
+        ]
+    );
+
+    // pins may also be separated by spaces
+    let seq: GpioSequence = "alias RESET 3\nalias VCC 5\nset 0 1\nclear RESET VCC"
+        .parse()
+        .unwrap();
+    assert_eq!(seq.steps, vec![GpioStep::Set(0x03), GpioStep::Clear(0x28)]);
+
+    let timeout: GpioSequence = "wait-for 7 high 1ms".parse().unwrap();
+    assert_eq!(sequencer.run(&timeout), Err("GPIO wait-for timeout"));
+
+    assert!("repeat 2\nset 1".parse::<GpioSequence>().is_err());
+    assert!("end".parse::<GpioSequence>().is_err());
+    assert!("set 8".parse::<GpioSequence>().is_err());
+    assert!("wait-for 0,1 high 1ms".parse::<GpioSequence>().is_err());
+    assert!("jump 1".parse::<GpioSequence>().is_err());
+}
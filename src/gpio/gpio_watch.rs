@@ -0,0 +1,229 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{Gpio, GpioDrive, GPIO_PIN_COUNT};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpioEdge {
+    Rising,
+    Falling,
+}
+
+impl fmt::Display for GpioEdge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpioEdge::Rising => write!(f, "rising"),
+            GpioEdge::Falling => write!(f, "falling"),
+        }
+    }
+}
+
+/// Level change of one pin, `time` counts from the start of the watch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpioEvent {
+    pub time: Duration,
+    pub pin: u8,
+    pub edge: GpioEdge,
+}
+
+impl GpioEvent {
+    pub fn is_high(&self) -> bool {
+        self.edge == GpioEdge::Rising
+    }
+}
+
+/// Polls the input levels and yields an event for every edge on the
+/// watched pins. Pulses shorter than the poll period can be missed.
+pub struct GpioWatch<'a, T: GpioDrive> {
+    gpio: &'a Gpio<T>,
+    mask: u8,
+    /// sleep between two polls, zero polls as fast as the adapter answers
+    pub interval: Duration,
+    /// stop after this time, None watches forever
+    pub duration: Option<Duration>,
+    start: Instant,
+    level: u8,
+    pending: VecDeque<GpioEvent>,
+}
+
+impl<T: GpioDrive> Gpio<T> {
+    /// Watch the pins in `mask`, starting from their current levels
+    pub fn watch(&self, mask: u8, interval: Duration) -> Result<GpioWatch<'_, T>, &'static str> {
+        let level = self.read()?;
+
+        Ok(GpioWatch {
+            gpio: self,
+            mask,
+            interval,
+            duration: None,
+            start: Instant::now(),
+            level,
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+impl<T: GpioDrive> GpioWatch<'_, T> {
+    /// Levels seen at the last poll
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn start_time(&self) -> Instant {
+        self.start
+    }
+}
+
+impl<T: GpioDrive> Iterator for GpioWatch<'_, T> {
+    type Item = Result<GpioEvent, &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(e) = self.pending.pop_front() {
+                return Some(Ok(e));
+            }
+
+            if let Some(d) = self.duration {
+                if self.start.elapsed() >= d {
+                    return None;
+                }
+            }
+
+            if !self.interval.is_zero() {
+                thread::sleep(self.interval);
+            }
+
+            let level = match self.gpio.read() {
+                Ok(v) => v,
+                Err(e) => return Some(Err(e)),
+            };
+            let time = self.start.elapsed();
+            let changed = (level ^ self.level) & self.mask;
+            self.level = level;
+
+            for pin in (0..GPIO_PIN_COUNT).filter(|p| changed & (1 << p) != 0) {
+                let edge = if level & (1 << pin) != 0 {
+                    GpioEdge::Rising
+                } else {
+                    GpioEdge::Falling
+                };
+                self.pending.push_back(GpioEvent { time, pin, edge });
+            }
+        }
+    }
+}
+
+/// Value Change Dump of watched pins, viewable in GTKWave
+pub struct VcdWriter<W: Write> {
+    out: W,
+    last_time: u128,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// VCD identifier of a pin
+    fn id(pin: u8) -> char {
+        (b'!' + pin) as char
+    }
+
+    /// Writes the header and the `initial` levels of the pins in `mask`
+    pub fn new(mut out: W, mask: u8, initial: u8) -> io::Result<VcdWriter<W>> {
+        let pins: Vec<u8> = (0..GPIO_PIN_COUNT)
+            .filter(|p| mask & (1 << p) != 0)
+            .collect();
+
+        writeln!(out, "$version ch347tool gpio watch $end")?;
+        writeln!(out, "$timescale 1us $end")?;
+        writeln!(out, "$scope module ch347 $end")?;
+        for &pin in &pins {
+            writeln!(out, "$var wire 1 {} GPIO{} $end", Self::id(pin), pin)?;
+        }
+        writeln!(out, "$upscope $end")?;
+        writeln!(out, "$enddefinitions $end")?;
+
+        writeln!(out, "#0")?;
+        writeln!(out, "$dumpvars")?;
+        for &pin in &pins {
+            writeln!(out, "{}{}", (initial >> pin) & 1, Self::id(pin))?;
+        }
+        writeln!(out, "$end")?;
+
+        Ok(VcdWriter { out, last_time: 0 })
+    }
+
+    pub fn event(&mut self, e: &GpioEvent) -> io::Result<()> {
+        let time = e.time.as_micros();
+        if self.last_time != time {
+            writeln!(self.out, "#{}", time)?;
+            self.last_time = time;
+        }
+
+        writeln!(self.out, "{}{}", e.is_high() as u8, Self::id(e.pin))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[test]
+pub fn test_gpio_watch() {
+    use std::cell::Cell;
+
+    /// Replays a list of input levels, one per poll
+    struct MockInput {
+        levels: Vec<u8>,
+        poll: Cell<usize>,
+    }
+
+    impl GpioDrive for MockInput {
+        fn gpio_read(&self) -> Result<(u8, u8), &'static str> {
+            let i = self.poll.get();
+            self.poll.set(i + 1);
+            self.levels.get(i).map(|&v| (0, v)).ok_or("end")
+        }
+
+        fn gpio_write(&self, _enable: u8, _dir: u8, _data: u8) -> Result<(), &'static str> {
+            Ok(())
+        }
+    }
+
+    let gpio = Gpio::new(MockInput {
+        // Gpio::new and watch read the first two levels
+        levels: vec![0x01, 0x01, 0x01, 0x03, 0x82, 0x80],
+        poll: Cell::new(0),
+    })
+    .unwrap();
+
+    let watch = gpio.watch(0x03, Duration::ZERO).unwrap();
+    let events: Vec<(u8, GpioEdge)> = watch
+        .map_while(|e| e.ok())
+        .map(|e| (e.pin, e.edge))
+        .collect();
+    // GPIO7 is not watched
+    assert_eq!(
+        events,
+        vec![
+            (1, GpioEdge::Rising),
+            (0, GpioEdge::Falling),
+            (1, GpioEdge::Falling)
+        ]
+    );
+
+    let mut vcd = VcdWriter::new(Vec::new(), 0x05, 0x01).unwrap();
+    let at = |us, pin, edge| GpioEvent {
+        time: Duration::from_micros(us),
+        pin,
+        edge,
+    };
+    vcd.event(&at(10, 0, GpioEdge::Falling)).unwrap();
+    vcd.event(&at(10, 2, GpioEdge::Rising)).unwrap();
+    let text = String::from_utf8(vcd.out).unwrap();
+    assert!(text.contains("$var wire 1 # GPIO2 $end"));
+    assert!(text.contains("$dumpvars\n1!\n0#\n$end\n"));
+    assert!(text.ends_with("#10\n0!\n1#\n"));
+}
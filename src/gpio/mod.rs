@@ -0,0 +1,37 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+mod gpio_drive;
+mod gpio_pin;
+mod gpio_pwm;
+mod gpio_sequence;
+mod gpio_watch;
+
+pub use gpio_drive::*;
+pub use gpio_pin::*;
+pub use gpio_pwm::*;
+pub use gpio_sequence::*;
+pub use gpio_watch::*;
+
+/// Remaining time below which `sleep_until` busy waits instead of sleeping
+const SPIN_TIME: Duration = Duration::from_micros(500);
+
+/// Sleep most of the way and spin the rest, `thread::sleep` alone
+/// oversleeps by up to a scheduler tick
+pub(crate) fn sleep_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+
+        let left = deadline - now;
+        if left > SPIN_TIME {
+            thread::sleep(left - SPIN_TIME);
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
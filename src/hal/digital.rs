@@ -0,0 +1,73 @@
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin, StatefulOutputPin};
+
+use super::HalError;
+use crate::{gpio_get, gpio_set, Ch347Device};
+
+/// One of GPIO0-7, only this pin is touched when it is driven
+pub struct Ch347Pin<'a> {
+    dev: &'a Ch347Device,
+    mask: u8,
+}
+
+impl Ch347Device {
+    pub fn hal_pin(&self, pin: u8) -> Result<Ch347Pin<'_>, &'static str> {
+        if pin > 7 {
+            return Err("GPIO index out of range");
+        }
+
+        Ok(Ch347Pin {
+            dev: self,
+            mask: 1 << pin,
+        })
+    }
+}
+
+impl Ch347Pin<'_> {
+    fn data(&self) -> Result<u8, HalError> {
+        match gpio_get(self.dev.get_dev_index()) {
+            Err(_) => Err(HalError("CH347GPIO_Get Fail")),
+            Ok((_, data)) => Ok(data),
+        }
+    }
+
+    fn drive(&self, high: bool) -> Result<(), HalError> {
+        let data = if high { self.mask } else { 0x00 };
+
+        gpio_set(self.dev.get_dev_index(), self.mask, self.mask, data)
+            .map_err(|_| HalError("CH347GPIO_Set Fail"))
+    }
+}
+
+impl ErrorType for Ch347Pin<'_> {
+    type Error = HalError;
+}
+
+impl InputPin for Ch347Pin<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.data()? & self.mask != 0)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.data()? & self.mask == 0)
+    }
+}
+
+impl OutputPin for Ch347Pin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.drive(false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.drive(true)
+    }
+}
+
+impl StatefulOutputPin for Ch347Pin<'_> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.data()? & self.mask != 0)
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.data()? & self.mask == 0)
+    }
+}
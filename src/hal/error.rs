@@ -0,0 +1,34 @@
+use std::{error::Error, fmt};
+
+use embedded_hal::{digital, i2c, spi};
+
+/// Error of the embedded-hal implementations, carries the failing vendor call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HalError(pub &'static str);
+
+impl fmt::Display for HalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for HalError {}
+
+impl spi::Error for HalError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+impl i2c::Error for HalError {
+    fn kind(&self) -> i2c::ErrorKind {
+        // CH347StreamI2C does not tell a missing ACK from a USB failure
+        i2c::ErrorKind::Other
+    }
+}
+
+impl digital::Error for HalError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
@@ -0,0 +1,58 @@
+use embedded_hal::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+
+use super::HalError;
+use crate::Ch347Device;
+
+impl ErrorType for Ch347Device {
+    type Error = HalError;
+}
+
+/// All writes followed by all reads become one `CH347StreamI2C` call, so
+/// `[Write, Read]` uses a repeated start as required. The CH347 can not
+/// restart from a read back to a write, such a transaction is rejected.
+impl I2c<SevenBitAddress> for Ch347Device {
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let read_start = operations
+            .iter()
+            .position(|op| matches!(op, Operation::Read(_)))
+            .unwrap_or(operations.len());
+        if operations[read_start..]
+            .iter()
+            .any(|op| matches!(op, Operation::Write(_)))
+        {
+            return Err(HalError("CH347 can not restart from a read to a write"));
+        }
+
+        let mut wbuf: Vec<u8> = vec![address << 1];
+        let mut read_len = 0;
+        for op in operations.iter() {
+            match op {
+                Operation::Write(buf) => wbuf.extend_from_slice(buf),
+                Operation::Read(buf) => read_len += buf.len(),
+            }
+        }
+
+        // read only, the address byte itself carries the read bit
+        if (wbuf.len() == 1) && (read_len != 0) {
+            wbuf[0] |= 0x01;
+        }
+
+        let mut rbuf = vec![0; read_len];
+        self.i2c_stream(&wbuf, &mut rbuf)
+            .map_err(|_| HalError("CH347StreamI2C Fail"))?;
+
+        let mut offset = 0;
+        for op in &mut operations[read_start..] {
+            if let Operation::Read(buf) = op {
+                buf.copy_from_slice(&rbuf[offset..(offset + buf.len())]);
+                offset += buf.len();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+mod digital;
+mod error;
+mod i2c;
+mod spi;
+
+pub use digital::*;
+pub use error::*;
+pub use spi::*;
@@ -0,0 +1,95 @@
+use std::{thread, time::Duration};
+
+use embedded_hal::spi::{ErrorType, Operation, SpiBus, SpiDevice};
+
+use super::HalError;
+use crate::Ch347Spi;
+
+/// `SpiBus` over a CH347, chip select is left to the caller
+///
+/// Use `Ch347Spi` directly as a `SpiDevice` when the peripheral sits on one
+/// of the CH347 chip select pins.
+pub struct Ch347SpiBus {
+    spi: Ch347Spi,
+}
+
+impl Ch347SpiBus {
+    pub fn new(spi: Ch347Spi) -> Ch347SpiBus {
+        spi.set_auto_cs(false);
+        Ch347SpiBus { spi }
+    }
+
+    pub fn release(self) -> Ch347Spi {
+        self.spi.set_auto_cs(true);
+        self.spi
+    }
+}
+
+impl ErrorType for Ch347SpiBus {
+    type Error = HalError;
+}
+
+impl SpiBus for Ch347SpiBus {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.read(words).map_err(HalError)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.spi.write(words).map_err(HalError)
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        transfer(&self.spi, read, write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.transfer(words).map_err(HalError)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl ErrorType for Ch347Spi {
+    type Error = HalError;
+}
+
+impl SpiDevice for Ch347Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.cs_assert().map_err(HalError)?;
+
+        let mut ret = Ok(());
+        for op in operations {
+            ret = match op {
+                Operation::Read(buf) => Ch347Spi::read(self, buf).map_err(HalError),
+                Operation::Write(buf) => Ch347Spi::write(self, buf).map_err(HalError),
+                Operation::Transfer(read, write) => transfer(self, read, write),
+                Operation::TransferInPlace(buf) => Ch347Spi::transfer(self, buf).map_err(HalError),
+                Operation::DelayNs(ns) => {
+                    thread::sleep(Duration::from_nanos(*ns as u64));
+                    Ok(())
+                }
+            };
+
+            if ret.is_err() {
+                break;
+            }
+        }
+
+        // always release chip select, but report the first error
+        let release = self.cs_release().map_err(HalError);
+        ret.and(release)
+    }
+}
+
+/// Clock out `max(read.len(), write.len())` bytes, `write` is padded with 0x00
+fn transfer(spi: &Ch347Spi, read: &mut [u8], write: &[u8]) -> Result<(), HalError> {
+    let mut iobuf = vec![0; read.len().max(write.len())];
+    iobuf[..write.len()].copy_from_slice(write);
+
+    spi.transfer(&mut iobuf).map_err(HalError)?;
+
+    read.copy_from_slice(&iobuf[..read.len()]);
+    Ok(())
+}
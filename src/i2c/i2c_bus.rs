@@ -0,0 +1,335 @@
+use std::{cell::Cell, error::Error, fmt, thread, time::Duration};
+
+use super::I2cDrive;
+use crate::Ch347Device;
+
+/// I2C slave address, `u8` converts into a 7-bit address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I2cAddress {
+    Seven(u8),
+    Ten(u16),
+}
+
+impl From<u8> for I2cAddress {
+    fn from(addr: u8) -> Self {
+        I2cAddress::Seven(addr)
+    }
+}
+
+impl fmt::Display for I2cAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            I2cAddress::Seven(a) => write!(f, "0x{:02X}", a),
+            I2cAddress::Ten(a) => write!(f, "0x{:03X}(10-bit)", a),
+        }
+    }
+}
+
+impl I2cAddress {
+    /// Bytes sent after START for a write, the R/W bit is cleared. The
+    /// CH347 repeats the first byte with R/W set for the read phase, which
+    /// is also the correct 10-bit read header.
+    pub(crate) fn header(&self) -> Result<Vec<u8>, I2cError> {
+        match *self {
+            I2cAddress::Seven(a) if a <= 0x7F => Ok(vec![a << 1]),
+            I2cAddress::Ten(a) if a <= 0x3FF => Ok(vec![0xF0 | ((a >> 7) as u8 & 0x06), a as u8]),
+            _ => Err(I2cError::InvalidAddress(*self)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I2cError {
+    /// Nobody acknowledged the slave address
+    AddressNack(I2cAddress),
+    /// The slave acknowledged its address but refused a data byte
+    DataNack(I2cAddress),
+    /// The adapter failed to run the transfer at all
+    Bus(&'static str),
+    InvalidAddress(I2cAddress),
+}
+
+impl fmt::Display for I2cError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            I2cError::AddressNack(a) => write!(f, "I2C address {} no acknowledge", a),
+            I2cError::DataNack(a) => write!(f, "I2C device {} data no acknowledge", a),
+            I2cError::Bus(e) => write!(f, "I2C bus error: {}", e),
+            I2cError::InvalidAddress(a) => write!(f, "Invalid I2C address {}", a),
+        }
+    }
+}
+
+impl Error for I2cError {}
+
+/// GPIO numbers wired to SCL and SDA, used to recover a stuck bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct I2cRecoveryPins {
+    pub scl: u8,
+    pub sda: u8,
+}
+
+/// Consecutive bus errors before an automatic recovery is attempted
+const AUTO_RECOVER_ERRORS: u32 = 2;
+
+/// I2C master working on slave addresses instead of raw frames
+pub struct I2cBus<T: I2cDrive> {
+    pub drive: T,
+    /// enables stuck bus detection and automatic recovery
+    pub recovery: Option<I2cRecoveryPins>,
+    bus_errors: Cell<u32>,
+}
+
+impl Ch347Device {
+    pub fn i2c(self) -> I2cBus<Ch347Device> {
+        I2cBus::new(self)
+    }
+}
+
+impl<T: I2cDrive> I2cBus<T> {
+    pub fn new(drive: T) -> I2cBus<T> {
+        I2cBus {
+            drive,
+            recovery: None,
+            bus_errors: Cell::new(0),
+        }
+    }
+
+    /// The adapter only reports that a transfer failed, find out why
+    fn classify(&self, addr: I2cAddress, header: &[u8]) -> I2cError {
+        if let Err(e) = self.drive.bus_check() {
+            return I2cError::Bus(e);
+        }
+
+        // an idle bus has both lines high
+        if let Some(pins) = self.recovery {
+            match self.drive.i2c_line_get(pins.sda) {
+                Err(e) => return I2cError::Bus(e),
+                Ok(false) => return I2cError::Bus("SDA held low"),
+                Ok(true) => {}
+            }
+            if let Ok(false) = self.drive.i2c_line_get(pins.scl) {
+                return I2cError::Bus("SCL held low");
+            }
+        }
+
+        if self.drive.i2c_stream(header, &mut []).is_ok() {
+            I2cError::DataNack(addr)
+        } else {
+            I2cError::AddressNack(addr)
+        }
+    }
+
+    fn stream(&self, addr: I2cAddress, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), I2cError> {
+        let header = addr.header()?;
+
+        let mut frame = header.clone();
+        frame.extend_from_slice(wbuf);
+
+        // read only, the address byte itself carries the read bit
+        if let (I2cAddress::Seven(_), true, false) = (addr, wbuf.is_empty(), rbuf.is_empty()) {
+            frame[0] |= 0x01;
+        }
+
+        let ret = self
+            .drive
+            .i2c_stream(&frame, rbuf)
+            .map_err(|_| self.classify(addr, &header));
+
+        match ret {
+            Err(I2cError::Bus(e)) => {
+                self.bus_errors.set(self.bus_errors.get() + 1);
+
+                if (self.recovery.is_some()) && (self.bus_errors.get() >= AUTO_RECOVER_ERRORS) {
+                    self.bus_errors.set(0);
+                    if let Ok(true) = self.recover() {
+                        return self
+                            .drive
+                            .i2c_stream(&frame, rbuf)
+                            .map_err(|_| self.classify(addr, &header));
+                    }
+                }
+
+                Err(I2cError::Bus(e))
+            }
+            _ => {
+                self.bus_errors.set(0);
+                ret
+            }
+        }
+    }
+
+    /// Clock SCL up to 9 times until the slave releases SDA, then issue a
+    /// STOP. Needs `recovery` pins, returns true if the bus is idle again.
+    pub fn recover(&self) -> Result<bool, I2cError> {
+        let pins = self
+            .recovery
+            .ok_or(I2cError::Bus("No recovery pins configured"))?;
+        let half_period = || thread::sleep(Duration::from_micros(5));
+        let set = |pin, release| self.drive.i2c_line_set(pin, release).map_err(I2cError::Bus);
+        let get = |pin| self.drive.i2c_line_get(pin).map_err(I2cError::Bus);
+
+        set(pins.sda, true)?;
+        set(pins.scl, true)?;
+        half_period();
+
+        for _ in 0..9 {
+            if get(pins.sda)? {
+                break;
+            }
+
+            set(pins.scl, false)?;
+            half_period();
+            set(pins.scl, true)?;
+            half_period();
+        }
+
+        // STOP: SDA rises while SCL is high
+        set(pins.scl, false)?;
+        set(pins.sda, false)?;
+        half_period();
+        set(pins.scl, true)?;
+        half_period();
+        set(pins.sda, true)?;
+        half_period();
+
+        Ok(get(pins.sda)? && get(pins.scl)?)
+    }
+
+    /// Delay the next transfer without blocking the host
+    pub fn delay_ms(&self, ms: u32) -> Result<(), I2cError> {
+        self.drive.i2c_delay_ms(ms).map_err(I2cError::Bus)
+    }
+
+    /// true if the address is acknowledged
+    pub fn probe<A: Into<I2cAddress>>(&self, addr: A) -> Result<bool, I2cError> {
+        match self.write(addr, &[]) {
+            Ok(_) => Ok(true),
+            Err(I2cError::AddressNack(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn write<A: Into<I2cAddress>>(&self, addr: A, data: &[u8]) -> Result<(), I2cError> {
+        self.stream(addr.into(), data, &mut [])
+    }
+
+    pub fn read<A: Into<I2cAddress>>(&self, addr: A, buf: &mut [u8]) -> Result<(), I2cError> {
+        self.stream(addr.into(), &[], buf)
+    }
+
+    /// Write then read with a repeated start in between
+    pub fn write_read<A: Into<I2cAddress>>(
+        &self,
+        addr: A,
+        wbuf: &[u8],
+        rbuf: &mut [u8],
+    ) -> Result<(), I2cError> {
+        self.stream(addr.into(), wbuf, rbuf)
+    }
+}
+
+#[test]
+pub fn test_i2c_bus() {
+    use std::cell::RefCell;
+
+    /// Device at 0x50 accepting 2 data bytes, 10-bit device at 0x2A5
+    struct MockBus {
+        frames: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl I2cDrive for MockBus {
+        fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+            self.frames.borrow_mut().push(wbuf.to_vec());
+            rbuf.fill(0x5A);
+            match wbuf[0] & 0xFE {
+                0xA0 if wbuf.len() <= 3 => Ok(()),
+                0xF4 if wbuf.get(1) == Some(&0xA5) => Ok(()),
+                _ => Err("nack"),
+            }
+        }
+    }
+
+    let bus = I2cBus::new(MockBus {
+        frames: RefCell::new(Vec::new()),
+    });
+
+    let mut rbuf = [0; 2];
+    bus.write_read(0x50, &[0x10], &mut rbuf).unwrap();
+    assert_eq!(bus.drive.frames.borrow().last().unwrap(), &vec![0xA0, 0x10]);
+    assert_eq!(rbuf, [0x5A, 0x5A]);
+
+    bus.read(0x50, &mut rbuf).unwrap();
+    assert_eq!(bus.drive.frames.borrow().last().unwrap(), &vec![0xA1]);
+
+    assert_eq!(
+        bus.write(0x51, &[0]),
+        Err(I2cError::AddressNack(I2cAddress::Seven(0x51)))
+    );
+    assert_eq!(
+        bus.write(0x50, &[0; 3]),
+        Err(I2cError::DataNack(I2cAddress::Seven(0x50)))
+    );
+    assert_eq!(bus.probe(0x51), Ok(false));
+    assert!(matches!(
+        bus.write(0x80, &[]),
+        Err(I2cError::InvalidAddress(_))
+    ));
+
+    bus.write_read(I2cAddress::Ten(0x2A5), &[0x01], &mut rbuf)
+        .unwrap();
+    assert_eq!(
+        bus.drive.frames.borrow().last().unwrap(),
+        &vec![0xF4, 0xA5, 0x01]
+    );
+}
+
+#[test]
+pub fn test_i2c_recover() {
+    /// A slave holding SDA low until it sees `stuck` more SCL clocks
+    struct StuckBus {
+        stuck: Cell<u32>,
+        scl: Cell<bool>,
+    }
+
+    impl I2cDrive for StuckBus {
+        fn i2c_stream(&self, _wbuf: &[u8], _rbuf: &mut [u8]) -> Result<(), &'static str> {
+            match self.stuck.get() {
+                0 => Ok(()),
+                _ => Err("fail"),
+            }
+        }
+
+        fn i2c_line_set(&self, pin: u8, release: bool) -> Result<(), &'static str> {
+            if pin == 0 {
+                if release && !self.scl.get() && self.stuck.get() > 0 {
+                    self.stuck.set(self.stuck.get() - 1);
+                }
+                self.scl.set(release);
+            }
+            Ok(())
+        }
+
+        fn i2c_line_get(&self, pin: u8) -> Result<bool, &'static str> {
+            Ok(match pin {
+                0 => self.scl.get(),
+                _ => self.stuck.get() == 0,
+            })
+        }
+    }
+
+    let mut bus = I2cBus::new(StuckBus {
+        stuck: Cell::new(5),
+        scl: Cell::new(true),
+    });
+    assert!(bus.recover().is_err());
+
+    bus.recovery = Some(I2cRecoveryPins { scl: 0, sda: 1 });
+    assert_eq!(bus.write(0x50, &[0]), Err(I2cError::Bus("SDA held low")));
+    // the second bus error in a row recovers the bus and retries
+    assert_eq!(bus.write(0x50, &[0]), Ok(()));
+    assert_eq!(bus.drive.stuck.get(), 0);
+
+    bus.drive.stuck.set(20);
+    assert_eq!(bus.recover(), Ok(false));
+}
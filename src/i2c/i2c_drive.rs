@@ -0,0 +1,81 @@
+use std::{thread, time::Duration};
+
+use crate::{gpio_get, gpio_set, Ch347Device, GPIO_PIN_COUNT};
+
+/// Raw I2C access used by the software drivers, the first byte of `wbuf` is
+/// the 8-bit device address (R/W bit included)
+pub trait I2cDrive {
+    /// START, write `wbuf`, then repeated START and read `rbuf` if not empty,
+    /// finally STOP. Fails when any byte is not acknowledged.
+    fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str>;
+
+    /// Only address the device, true if acknowledged
+    fn i2c_probe(&self, addr: u8) -> bool {
+        self.i2c_stream(&[addr << 1], &mut []).is_ok()
+    }
+
+    /// Err if the adapter itself is not working, used to tell a bus error
+    /// apart from a missing acknowledge
+    fn bus_check(&self) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    /// Delay the next `i2c_stream`, the default sleeps on the host
+    fn i2c_delay_ms(&self, ms: u32) -> Result<(), &'static str> {
+        thread::sleep(Duration::from_millis(ms as u64));
+        Ok(())
+    }
+
+    /// Open drain control of a GPIO wired to SCL or SDA, `release` lets the
+    /// pull-up take the line high, otherwise it is driven low
+    fn i2c_line_set(&self, _pin: u8, _release: bool) -> Result<(), &'static str> {
+        Err("I2C lines are not accessible")
+    }
+
+    /// Level of a GPIO wired to SCL or SDA
+    fn i2c_line_get(&self, _pin: u8) -> Result<bool, &'static str> {
+        Err("I2C lines are not accessible")
+    }
+}
+
+impl I2cDrive for Ch347Device {
+    fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+        Ch347Device::i2c_stream(self, wbuf, rbuf).map_err(|_| "CH347StreamI2C Fail")
+    }
+
+    fn i2c_probe(&self, addr: u8) -> bool {
+        self.i2c_device_detect(addr)
+    }
+
+    fn bus_check(&self) -> Result<(), &'static str> {
+        gpio_get(self.get_dev_index())
+            .map(|_| ())
+            .map_err(|_| "CH347 not responding")
+    }
+
+    fn i2c_delay_ms(&self, ms: u32) -> Result<(), &'static str> {
+        self.i2c_set_delay_ms(ms)
+    }
+
+    fn i2c_line_set(&self, pin: u8, release: bool) -> Result<(), &'static str> {
+        if pin >= GPIO_PIN_COUNT {
+            return Err("Invalid GPIO pin");
+        }
+
+        let mask = 1 << pin;
+        // released pins are inputs, the output latch stays low
+        let dir = if release { 0 } else { mask };
+
+        gpio_set(self.get_dev_index(), mask, dir, 0).map_err(|_| "CH347GPIO_Set Fail")
+    }
+
+    fn i2c_line_get(&self, pin: u8) -> Result<bool, &'static str> {
+        if pin >= GPIO_PIN_COUNT {
+            return Err("Invalid GPIO pin");
+        }
+
+        let (_, data) = gpio_get(self.get_dev_index()).map_err(|_| "CH347GPIO_Get Fail")?;
+
+        Ok(data & (1 << pin) != 0)
+    }
+}
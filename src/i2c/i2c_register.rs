@@ -0,0 +1,132 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{I2cAddress, I2cBus, I2cDrive, I2cError};
+
+/// Width of a register address or value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataWidth {
+    /// 8-bit
+    #[clap(name = "b")]
+    #[serde(alias = "b")]
+    Byte,
+    /// 16-bit
+    #[clap(name = "w")]
+    #[serde(alias = "w")]
+    Word,
+}
+
+impl DataWidth {
+    pub fn bytes(&self) -> usize {
+        match self {
+            DataWidth::Byte => 1,
+            DataWidth::Word => 2,
+        }
+    }
+
+    pub fn max_value(&self) -> u16 {
+        match self {
+            DataWidth::Byte => 0xFF,
+            DataWidth::Word => 0xFFFF,
+        }
+    }
+}
+
+/// Byte order of multi-byte values on the bus
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    pub fn to_bytes(&self, value: u16, width: DataWidth) -> Vec<u8> {
+        match (width, self) {
+            (DataWidth::Byte, _) => vec![value as u8],
+            (DataWidth::Word, Endian::Big) => value.to_be_bytes().to_vec(),
+            (DataWidth::Word, Endian::Little) => value.to_le_bytes().to_vec(),
+        }
+    }
+
+    pub fn from_bytes(&self, buf: &[u8]) -> u16 {
+        match (buf.len(), self) {
+            (1, _) => buf[0] as u16,
+            (_, Endian::Big) => u16::from_be_bytes([buf[0], buf[1]]),
+            (_, Endian::Little) => u16::from_le_bytes([buf[0], buf[1]]),
+        }
+    }
+}
+
+/// Register layout of an I2C slave, the register address is always sent
+/// MSB first
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegFormat {
+    pub addr_width: DataWidth,
+    pub value_width: DataWidth,
+    pub endian: Endian,
+}
+
+impl Default for RegFormat {
+    /// 8-bit register, 8-bit value, words little endian as SMBus
+    fn default() -> Self {
+        RegFormat {
+            addr_width: DataWidth::Byte,
+            value_width: DataWidth::Byte,
+            endian: Endian::Little,
+        }
+    }
+}
+
+impl RegFormat {
+    fn reg_bytes(&self, reg: u16) -> Vec<u8> {
+        Endian::Big.to_bytes(reg, self.addr_width)
+    }
+}
+
+impl<T: I2cDrive> I2cBus<T> {
+    /// Write the register address, repeated start, then read the value
+    pub fn read_reg<A: Into<I2cAddress>>(
+        &self,
+        addr: A,
+        reg: u16,
+        format: RegFormat,
+    ) -> Result<u16, I2cError> {
+        let mut rbuf = vec![0; format.value_width.bytes()];
+        self.write_read(addr, &format.reg_bytes(reg), &mut rbuf)?;
+
+        Ok(format.endian.from_bytes(&rbuf))
+    }
+
+    pub fn write_reg<A: Into<I2cAddress>>(
+        &self,
+        addr: A,
+        reg: u16,
+        value: u16,
+        format: RegFormat,
+    ) -> Result<(), I2cError> {
+        let mut wbuf = format.reg_bytes(reg);
+        wbuf.extend(format.endian.to_bytes(value, format.value_width));
+
+        self.write(addr, &wbuf)
+    }
+}
+
+#[test]
+pub fn test_reg_format() {
+    let format = RegFormat {
+        addr_width: DataWidth::Word,
+        value_width: DataWidth::Word,
+        endian: Endian::Big,
+    };
+    assert_eq!(format.reg_bytes(0x1234), vec![0x12, 0x34]);
+    assert_eq!(RegFormat::default().reg_bytes(0x1234), vec![0x34]);
+    assert_eq!(
+        Endian::Little.to_bytes(0x1234, DataWidth::Word),
+        vec![0x34, 0x12]
+    );
+    assert_eq!(Endian::Little.from_bytes(&[0x34, 0x12]), 0x1234);
+    assert_eq!(Endian::Big.from_bytes(&[0x34, 0x12]), 0x3412);
+    assert_eq!(Endian::Big.from_bytes(&[0x34]), 0x34);
+}
@@ -0,0 +1,129 @@
+use std::ops::RangeInclusive;
+
+use clap::ValueEnum;
+
+use super::{I2cAddress, I2cBus, I2cDrive, I2cError};
+
+/// Addresses reserved by the I2C specification are skipped by default
+pub const I2C_SCAN_RANGE: RangeInclusive<u8> = 0x03..=0x77;
+
+/// How an address is probed, same as the i2cdetect modes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ScanMode {
+    /// read byte on 0x30-0x37 and 0x50-0x5F, quick write elsewhere
+    Auto,
+    /// zero length write, may corrupt write-only devices like AT24RF08
+    Quick,
+    /// one byte read, may lock up devices which expect a register address
+    Read,
+}
+
+impl ScanMode {
+    /// Mode really used for `addr`
+    pub fn resolve(&self, addr: u8) -> ScanMode {
+        match (self, addr) {
+            (ScanMode::Auto, 0x30..=0x37 | 0x50..=0x5F) => ScanMode::Read,
+            (ScanMode::Auto, _) => ScanMode::Quick,
+            (mode, _) => *mode,
+        }
+    }
+}
+
+impl<T: I2cDrive> I2cBus<T> {
+    /// true if the 7-bit address responds to `mode`
+    pub fn probe_with(&self, addr: u8, mode: ScanMode) -> Result<bool, I2cError> {
+        match mode.resolve(addr) {
+            // a raw read, `read` would classify a NACK with a quick write
+            ScanMode::Read => {
+                let mut header = I2cAddress::Seven(addr).header()?;
+                header[0] |= 0x01;
+
+                match self.drive.i2c_stream(&header, &mut [0]) {
+                    Ok(_) => Ok(true),
+                    Err(_) => self.drive.bus_check().map(|_| false).map_err(I2cError::Bus),
+                }
+            }
+            _ => self.probe(addr),
+        }
+    }
+
+    /// Responding 7-bit addresses in `range`
+    pub fn scan_range(
+        &self,
+        range: RangeInclusive<u8>,
+        mode: ScanMode,
+    ) -> Result<Vec<u8>, I2cError> {
+        let mut ret = Vec::new();
+
+        for addr in range {
+            if self.probe_with(addr, mode)? {
+                ret.push(addr);
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Responding 7-bit addresses, auto mode without reserved addresses
+    pub fn scan(&self) -> Result<Vec<u8>, I2cError> {
+        self.scan_range(I2C_SCAN_RANGE, ScanMode::Auto)
+    }
+}
+
+#[test]
+pub fn test_i2c_scan() {
+    use std::cell::{Cell, RefCell};
+
+    /// Devices at 0x1C and 0x50, `broken` fails every transfer
+    struct MockBus {
+        frames: RefCell<Vec<Vec<u8>>>,
+        broken: Cell<bool>,
+    }
+
+    impl I2cDrive for MockBus {
+        fn i2c_stream(&self, wbuf: &[u8], _rbuf: &mut [u8]) -> Result<(), &'static str> {
+            self.frames.borrow_mut().push(wbuf.to_vec());
+            match wbuf[0] >> 1 {
+                0x1C | 0x50 if !self.broken.get() => Ok(()),
+                _ => Err("nack"),
+            }
+        }
+
+        fn bus_check(&self) -> Result<(), &'static str> {
+            match self.broken.get() {
+                true => Err("USB fail"),
+                false => Ok(()),
+            }
+        }
+    }
+
+    assert_eq!(ScanMode::Auto.resolve(0x1C), ScanMode::Quick);
+    assert_eq!(ScanMode::Auto.resolve(0x37), ScanMode::Read);
+    assert_eq!(ScanMode::Auto.resolve(0x50), ScanMode::Read);
+    assert_eq!(ScanMode::Quick.resolve(0x50), ScanMode::Quick);
+    assert_eq!(ScanMode::Read.resolve(0x1C), ScanMode::Read);
+
+    let bus = I2cBus::new(MockBus {
+        frames: RefCell::new(Vec::new()),
+        broken: Cell::new(false),
+    });
+    assert_eq!(bus.scan().unwrap(), vec![0x1C, 0x50]);
+
+    // read probes send one read frame per address and never a quick write
+    bus.drive.frames.borrow_mut().clear();
+    assert_eq!(
+        bus.scan_range(0x4E..=0x51, ScanMode::Read).unwrap(),
+        vec![0x50]
+    );
+    assert_eq!(
+        *bus.drive.frames.borrow(),
+        vec![vec![0x9D], vec![0x9F], vec![0xA1], vec![0xA3]]
+    );
+
+    bus.drive.broken.set(true);
+    assert_eq!(
+        bus.scan_range(0x50..=0x51, ScanMode::Read),
+        Err(I2cError::Bus("USB fail"))
+    );
+    assert!(bus.scan().is_err());
+}
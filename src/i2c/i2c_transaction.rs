@@ -0,0 +1,116 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use super::{I2cAddress, I2cBus, I2cDrive, I2cError};
+
+/// One step of a scripted I2C transaction
+///
+/// Text form: `w:<hex>` write, `r:<len>` read, `delay:<ms>` hardware delay.
+/// A write directly followed by a read is sent with a repeated start.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum I2cOp {
+    Write(Vec<u8>),
+    Read(usize),
+    Delay(u32),
+}
+
+impl FromStr for I2cOp {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid i2c step \"{}\"", s))?;
+
+        let op = match kind.to_lowercase().as_str() {
+            "w" => I2cOp::Write(hex::decode(arg.trim_start_matches("0x"))?),
+            "r" => I2cOp::Read(arg.parse()?),
+            "delay" => I2cOp::Delay(arg.trim_end_matches("ms").parse()?),
+            _ => return Err(format!("Unknown i2c step \"{}\"", kind).into()),
+        };
+
+        Ok(op)
+    }
+}
+
+impl fmt::Display for I2cOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            I2cOp::Write(data) => write!(f, "w:{}", hex::encode_upper(data)),
+            I2cOp::Read(len) => write!(f, "r:{}", len),
+            I2cOp::Delay(ms) => write!(f, "delay:{}", ms),
+        }
+    }
+}
+
+impl<T: I2cDrive> I2cBus<T> {
+    /// Run the steps in order, returns the data of every read step
+    pub fn transaction<A: Into<I2cAddress>>(
+        &self,
+        addr: A,
+        ops: &[I2cOp],
+    ) -> Result<Vec<Vec<u8>>, I2cError> {
+        let addr = addr.into();
+        let mut ret = Vec::new();
+        let mut i = 0;
+
+        while i < ops.len() {
+            match (&ops[i], ops.get(i + 1)) {
+                (I2cOp::Write(data), Some(I2cOp::Read(len))) => {
+                    let mut rbuf = vec![0; *len];
+                    self.write_read(addr, data, &mut rbuf)?;
+                    ret.push(rbuf);
+                    i += 1;
+                }
+                (I2cOp::Write(data), _) => self.write(addr, data)?,
+                (I2cOp::Read(len), _) => {
+                    let mut rbuf = vec![0; *len];
+                    self.read(addr, &mut rbuf)?;
+                    ret.push(rbuf);
+                }
+                (I2cOp::Delay(ms), _) => self.delay_ms(*ms)?,
+            }
+            i += 1;
+        }
+
+        Ok(ret)
+    }
+}
+
+#[test]
+pub fn test_i2c_transaction() {
+    use std::cell::RefCell;
+
+    struct MockBus {
+        log: RefCell<Vec<String>>,
+    }
+
+    impl I2cDrive for MockBus {
+        fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+            self.log
+                .borrow_mut()
+                .push(format!("{:02X?} {}", wbuf, rbuf.len()));
+            Ok(())
+        }
+
+        fn i2c_delay_ms(&self, ms: u32) -> Result<(), &'static str> {
+            self.log.borrow_mut().push(format!("delay {}", ms));
+            Ok(())
+        }
+    }
+
+    let ops: Vec<I2cOp> = ["w:F3", "delay:20", "r:3", "w:E5", "r:2"]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    assert!("x:00".parse::<I2cOp>().is_err());
+
+    let bus = I2cBus::new(MockBus {
+        log: RefCell::new(Vec::new()),
+    });
+    let ret = bus.transaction(0x40, &ops).unwrap();
+    assert_eq!(ret, vec![vec![0; 3], vec![0; 2]]);
+    assert_eq!(
+        *bus.drive.log.borrow(),
+        vec!["[80, F3] 0", "delay 20", "[81] 3", "[80, E5] 2"]
+    );
+}
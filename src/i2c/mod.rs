@@ -0,0 +1,11 @@
+mod i2c_bus;
+mod i2c_drive;
+mod i2c_register;
+mod i2c_scan;
+mod i2c_transaction;
+
+pub use i2c_bus::*;
+pub use i2c_drive::*;
+pub use i2c_register::*;
+pub use i2c_scan::*;
+pub use i2c_transaction::*;
@@ -0,0 +1,406 @@
+use super::{parse_idcode, Idcode, Jtag, JtagChip, JtagDrive};
+
+/// Longest chain `scan_idcodes` looks at
+pub const JTAG_MAX_DEVICES: usize = 32;
+
+/// Longest total IR and bypass length the chain detection handles
+const JTAG_MAX_CHAIN_BITS: usize = 1024;
+
+/// One device of a detected chain
+pub struct JtagChainDevice {
+    /// `None` for devices that select BYPASS after reset
+    pub idcode: Option<Idcode>,
+    /// Found in the device database
+    pub chip: Option<JtagChip>,
+    /// `None` if the chain could not be split and the device is not in the
+    /// database
+    pub ir_len: Option<usize>,
+    /// IR length split off the capture pattern instead of taken from the
+    /// database, wrong if the device captures more ones than the 01 marker
+    pub ir_len_guessed: bool,
+}
+
+/// Position of the first bit equal to `level` at or after `from`
+fn find_bit(buf: &[u8], from: usize, bits: usize, level: bool) -> Option<usize> {
+    (from..bits).find(|&n| (buf[n / 8] & (1 << (n % 8)) != 0) == level)
+}
+
+/// Split the IR capture bits into one length per device, every IR captures
+/// 01 in its lowest bits
+fn split_ir(capture: &[bool], known: &[Option<usize>]) -> Result<Vec<(usize, bool)>, &'static str> {
+    let total = capture.len();
+    let mut lens = Vec::new();
+    let mut pos = 0;
+
+    for (i, ir_len) in known.iter().enumerate() {
+        if pos >= total || !capture[pos] {
+            return Err("Bad IR capture pattern");
+        }
+
+        let left = known.len() - i - 1;
+        let len = match ir_len {
+            Some(len) => (*len, false),
+            None if left == 0 => (total - pos, true),
+            // the next device starts at the next 01, leave 2 bits to each after it
+            None => (pos + 2..total.saturating_sub(left * 2 - 2))
+                .find(|&n| capture[n] && n + 1 < total && !capture[n + 1])
+                .map(|n| (n - pos, true))
+                .ok_or("Cannot split the IR chain")?,
+        };
+
+        lens.push(len);
+        pos += len.0;
+    }
+
+    if pos != total {
+        return Err("IR lengths do not add up");
+    }
+
+    Ok(lens)
+}
+
+impl<T: JtagDrive> Jtag<T> {
+    /// IDCODE of every device after a reset, the device nearest TDO first.
+    /// Devices without IDCODE select BYPASS and show up as `None`
+    pub fn scan_idcodes(&self) -> Result<Vec<Option<u32>>, &'static str> {
+        let bits = JTAG_MAX_DEVICES * 32;
+
+        self.reset()?;
+        let tdo = self.shift_dr(&vec![0xFF; bits / 8], bits)?;
+        let bit = |n: usize| tdo[n / 8] & (1 << (n % 8)) != 0;
+
+        if tdo.iter().all(|&b| b == 0) {
+            return Err("TDO stuck low");
+        }
+
+        let mut devices = Vec::new();
+        let mut pos = 0;
+        while pos < bits && devices.len() < JTAG_MAX_DEVICES {
+            // BYPASS captures 0, an IDCODE always has bit 0 set
+            if !bit(pos) {
+                devices.push(None);
+                pos += 1;
+                continue;
+            }
+            if pos + 32 > bits {
+                break;
+            }
+
+            let idcode = (0..32).fold(0u32, |id, i| id | (bit(pos + i) as u32) << i);
+            // the ones shifted in have come out, end of chain
+            if idcode == 0xFFFF_FFFF {
+                break;
+            }
+            devices.push(Some(idcode));
+            pos += 32;
+        }
+
+        Ok(devices)
+    }
+
+    /// Captured IR bits of the whole chain, leaves all devices in BYPASS.
+    /// Ones, zeros and ones again are shifted: the zeros come out delayed
+    /// by the total IR length, and the last ones load BYPASS
+    pub fn ir_capture(&self) -> Result<Vec<bool>, &'static str> {
+        let n = JTAG_MAX_CHAIN_BITS;
+        let mut tdi = vec![0xFF; n * 3 / 8];
+        tdi[n / 8..n * 2 / 8].fill(0);
+
+        let tdo = self.shift_ir(&tdi, n * 3)?;
+        let total =
+            find_bit(&tdo, n, n * 2, false).ok_or("IR chain too long or TDO stuck high")? - n;
+        if total == 0 {
+            return Err("No IR in the chain");
+        }
+
+        Ok((0..total)
+            .map(|i| tdo[i / 8] & (1 << (i % 8)) != 0)
+            .collect())
+    }
+
+    /// Number of devices, each in BYPASS delays TDO by one bit
+    pub fn bypass_count(&self) -> Result<usize, &'static str> {
+        let n = JTAG_MAX_CHAIN_BITS;
+        let mut tdi = vec![0xFF; n * 2 / 8];
+        tdi[..n / 8].fill(0);
+
+        let tdo = self.shift_dr(&tdi, n * 2)?;
+        find_bit(&tdo, n, n * 2, true)
+            .map(|i| i - n)
+            .ok_or("Chain too long or TDO stuck low")
+    }
+
+    /// IDCODEs, known chips and IR lengths of the chain, the device
+    /// nearest TDO first. The TAPs are reset afterwards. IR length detection
+    /// is a heuristic, when it fails only the database lengths are given
+    pub fn detect_chain(&self) -> Result<Vec<JtagChainDevice>, &'static str> {
+        let idcodes = self.scan_idcodes()?;
+        if idcodes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let chips: Vec<Option<JtagChip>> =
+            idcodes.iter().map(|id| id.and_then(parse_idcode)).collect();
+        let known: Vec<Option<usize>> = chips
+            .iter()
+            .map(|c| c.as_ref().map(|c| c.ir_len as usize))
+            .collect();
+
+        let lens = self.detect_ir_lens(&known);
+        self.reset()?;
+        let lens = lens.unwrap_or_else(|_| known.iter().map(|&l| (l, false)).collect());
+
+        Ok(idcodes
+            .into_iter()
+            .zip(chips)
+            .zip(lens)
+            .map(
+                |((idcode, chip), (ir_len, ir_len_guessed))| JtagChainDevice {
+                    idcode: idcode.map(Idcode),
+                    chip,
+                    ir_len,
+                    ir_len_guessed,
+                },
+            )
+            .collect())
+    }
+
+    /// IR length of every device, `known` are the database lengths
+    fn detect_ir_lens(
+        &self,
+        known: &[Option<usize>],
+    ) -> Result<Vec<(Option<usize>, bool)>, &'static str> {
+        let capture = self.ir_capture()?;
+        if self.bypass_count()? != known.len() {
+            return Err("Chain length differs between IDCODE and BYPASS scans");
+        }
+
+        Ok(split_ir(&capture, known)?
+            .into_iter()
+            .map(|(len, guessed)| (Some(len), guessed))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+
+    use crate::{JtagDrive, TapState};
+
+    /// One TAP of `MockChain`
+    pub struct MockTap {
+        pub idcode: Option<u32>,
+        pub ir_len: usize,
+    }
+
+    /// Chain of TAPs behind one TDI/TDO, the first TAP is nearest TDO.
+    /// IRs capture 0b..01, DRs are IDCODE after a reset and BYPASS after
+    /// an all ones instruction
+    pub struct MockChain {
+        pub taps: Vec<MockTap>,
+        pub state: Cell<TapState>,
+        bypass: Cell<bool>,
+        reg: RefCell<VecDeque<bool>>,
+    }
+
+    impl MockChain {
+        pub fn new(taps: Vec<MockTap>) -> MockChain {
+            MockChain {
+                taps,
+                state: Cell::new(TapState::TestLogicReset),
+                bypass: Cell::new(false),
+                reg: RefCell::new(VecDeque::new()),
+            }
+        }
+
+        fn clock(&self, tms: bool, tdi: bool) -> bool {
+            let state = self.state.get();
+            let mut tdo = false;
+
+            match state {
+                TapState::ShiftDr | TapState::ShiftIr => {
+                    let mut reg = self.reg.borrow_mut();
+                    tdo = reg.pop_front().unwrap_or(tdi);
+                    reg.push_back(tdi);
+                }
+                _ => (),
+            }
+
+            let next = state.next(tms);
+            match next {
+                TapState::TestLogicReset => self.bypass.set(false),
+                TapState::CaptureDr => {
+                    let mut reg = self.reg.borrow_mut();
+                    reg.clear();
+                    for tap in &self.taps {
+                        match tap.idcode {
+                            Some(id) if !self.bypass.get() => {
+                                reg.extend((0..32).map(|i| id & (1 << i) != 0))
+                            }
+                            _ => reg.push_back(false),
+                        }
+                    }
+                }
+                TapState::CaptureIr => {
+                    let mut reg = self.reg.borrow_mut();
+                    reg.clear();
+                    for tap in &self.taps {
+                        reg.extend((0..tap.ir_len).map(|i| i == 0));
+                    }
+                }
+                TapState::UpdateIr => {
+                    let ir_len: usize = self.taps.iter().map(|t| t.ir_len).sum();
+                    let reg = self.reg.borrow();
+                    self.bypass.set(reg.iter().take(ir_len).all(|&b| b));
+                }
+                _ => (),
+            }
+            self.state.set(next);
+
+            tdo
+        }
+    }
+
+    impl JtagDrive for MockChain {
+        fn jtag_tms(&self, tms: &[bool]) -> Result<(), &'static str> {
+            for &t in tms {
+                self.clock(t, false);
+            }
+            Ok(())
+        }
+
+        fn jtag_shift(
+            &self,
+            tdi: &[u8],
+            bits: usize,
+            exit: bool,
+            mut tdo: Option<&mut [u8]>,
+        ) -> Result<(), &'static str> {
+            for n in 0..bits {
+                let out = self.clock(exit && n == bits - 1, tdi[n / 8] & (1 << (n % 8)) != 0);
+                if let Some(tdo) = &mut tdo {
+                    if out {
+                        tdo[n / 8] |= 1 << (n % 8);
+                    } else {
+                        tdo[n / 8] &= !(1 << (n % 8));
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[test]
+pub fn test_scan_idcodes() {
+    use mock::{MockChain, MockTap};
+
+    let jtag = Jtag::new(MockChain::new(vec![
+        MockTap {
+            idcode: Some(0x4BA00477),
+            ir_len: 4,
+        },
+        MockTap {
+            idcode: None,
+            ir_len: 5,
+        },
+        MockTap {
+            idcode: Some(0x0362D093),
+            ir_len: 6,
+        },
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        jtag.scan_idcodes().unwrap(),
+        vec![Some(0x4BA00477), None, Some(0x0362D093)]
+    );
+    assert_eq!(jtag.state(), crate::TapState::RunTestIdle);
+    assert_eq!(jtag.drive.state.get(), jtag.state());
+
+    // IR capture patterns come out LSB first, 4 + 5 + 6 bits
+    let ir = jtag.shift_ir(&[0xFF, 0xFF], 15).unwrap();
+    assert_eq!(ir, vec![0b0001_0001, 0b0000_0010]);
+
+    jtag.run_test_idle(10).unwrap();
+    assert_eq!(jtag.drive.state.get(), crate::TapState::RunTestIdle);
+
+    let empty = Jtag::new(MockChain::new(Vec::new())).unwrap();
+    assert!(empty.scan_idcodes().unwrap().is_empty());
+}
+
+#[test]
+pub fn test_detect_chain() {
+    use mock::{MockChain, MockTap};
+
+    let jtag = Jtag::new(MockChain::new(vec![
+        MockTap {
+            idcode: Some(0x4BA00477),
+            ir_len: 4,
+        },
+        MockTap {
+            idcode: None,
+            ir_len: 5,
+        },
+        MockTap {
+            idcode: Some(0x10000001),
+            ir_len: 3,
+        },
+        MockTap {
+            idcode: Some(0x0362D093),
+            ir_len: 6,
+        },
+    ]))
+    .unwrap();
+
+    assert_eq!(jtag.ir_capture().unwrap().len(), 18);
+    assert_eq!(jtag.bypass_count().unwrap(), 4);
+
+    let chain = jtag.detect_chain().unwrap();
+    let summary: Vec<(Option<&str>, Option<usize>, bool)> = chain
+        .iter()
+        .map(|d| {
+            (
+                d.chip.as_ref().map(|c| c.name.as_str()),
+                d.ir_len,
+                d.ir_len_guessed,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (Some("CoreSight JTAG-DP"), Some(4), false),
+            (None, Some(5), true),
+            (None, Some(3), true),
+            (Some("XC7A35T"), Some(6), false),
+        ]
+    );
+    assert_eq!(chain[2].idcode, Some(Idcode(0x10000001)));
+    assert_eq!(jtag.state(), crate::TapState::TestLogicReset);
+
+    // the database length does not match, the IDCODEs are still reported
+    let jtag = Jtag::new(MockChain::new(vec![
+        MockTap {
+            idcode: Some(0x0362D093),
+            ir_len: 5,
+        },
+        MockTap {
+            idcode: Some(0x10000001),
+            ir_len: 3,
+        },
+    ]))
+    .unwrap();
+    let chain = jtag.detect_chain().unwrap();
+    assert_eq!(chain.len(), 2);
+    assert_eq!((chain[0].ir_len, chain[0].ir_len_guessed), (Some(6), false));
+    assert_eq!(chain[1].idcode, Some(Idcode(0x10000001)));
+    assert_eq!(chain[1].ir_len, None);
+    assert_eq!(jtag.state(), crate::TapState::TestLogicReset);
+
+    // a capture without the 01 marker can not be split
+    assert!(split_ir(&[true, true, true, true], &[None, None]).is_err());
+    assert!(split_ir(&[true, false, true, false], &[Some(3), None]).is_err());
+}
@@ -0,0 +1,313 @@
+use super::Jtag;
+use crate::windows::basetsd::*;
+use crate::{CH347Jtag_INIT, CH347ReadData, CH347WriteData, Ch347Device};
+
+// Raw JTAG commands of the CH347 in mode 3, the same packets the ch347
+// driver of OpenOCD (src/jtag/drivers/ch347.c) sends. The CH347Jtag_*
+// calls of the vendor library only move between fixed TAP states, so they
+// can not clock arbitrary TMS sequences.
+
+/// Bit-bang command, one byte per pin state, no reply
+const CMD_JTAG_BIT_OP: u8 = 0xD1;
+/// Bit-bang command, replies TDO of every TCK rising edge in bit 0 of a byte
+const CMD_JTAG_BIT_OP_RD: u8 = 0xD2;
+/// Bulk TDI shift with TMS low, 8 bits per byte LSB first, no reply
+const CMD_JTAG_DATA_SHIFT: u8 = 0xD3;
+/// Bulk TDI shift with TMS low, replies the TDO bytes
+const CMD_JTAG_DATA_SHIFT_RD: u8 = 0xD4;
+
+const PIN_TCK: u8 = 0x01;
+const PIN_TMS: u8 = 0x02;
+const PIN_TDI: u8 = 0x10;
+
+/// Payload bytes of one command packet
+const PACKET_DATA_MAX: usize = 500;
+
+/// Fastest JTAG clock level, levels 0~5 are 1.875MHz to 60MHz
+pub const JTAG_CLOCK_MAX: u8 = 5;
+
+pub trait JtagDrive {
+    /// Clock the TMS levels in order with TDI low
+    fn jtag_tms(&self, tms: &[bool]) -> Result<(), &'static str>;
+
+    /// Shift `bits` of `tdi` LSB first in a Shift-IR/DR state, TMS goes high
+    /// on the last bit if `exit` is set. TDO is captured into `tdo` if given
+    fn jtag_shift(
+        &self,
+        tdi: &[u8],
+        bits: usize,
+        exit: bool,
+        tdo: Option<&mut [u8]>,
+    ) -> Result<(), &'static str>;
+}
+
+impl Ch347Device {
+    /// Switch to JTAG at clock level 0~5, needs the chip in mode 3
+    pub fn jtag(self, clock: u8) -> Result<Jtag<Ch347Device>, &'static str> {
+        if clock > JTAG_CLOCK_MAX {
+            return Err("JTAG clock must be 0~5");
+        }
+        self.check_parallel()?;
+
+        unsafe {
+            if CH347Jtag_INIT(self.get_dev_index(), clock) == 0 {
+                return Err("CH347Jtag_INIT Fail");
+            }
+        }
+
+        Jtag::new(self)
+    }
+}
+
+/// Packet transport of the raw JTAG commands
+trait JtagPacket {
+    fn jtag_command(&self, cmd: u8, data: &[u8]) -> Result<(), &'static str>;
+
+    /// Read the reply of `cmd` into `buf`
+    fn jtag_reply(&self, cmd: u8, buf: &mut [u8]) -> Result<(), &'static str>;
+}
+
+impl JtagPacket for Ch347Device {
+    fn jtag_command(&self, cmd: u8, data: &[u8]) -> Result<(), &'static str> {
+        let mut pkt = vec![cmd, data.len() as u8, (data.len() >> 8) as u8];
+        pkt.extend_from_slice(data);
+
+        let mut len = pkt.len() as ULONG;
+        unsafe {
+            if CH347WriteData(self.get_dev_index(), pkt.as_mut_ptr() as PVOID, &mut len) == 0
+                || len as usize != pkt.len()
+            {
+                return Err("CH347WriteData Fail");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jtag_reply(&self, cmd: u8, buf: &mut [u8]) -> Result<(), &'static str> {
+        let mut pkt = vec![0; buf.len() + 3];
+
+        let mut len = pkt.len() as ULONG;
+        unsafe {
+            if CH347ReadData(self.get_dev_index(), pkt.as_mut_ptr() as PVOID, &mut len) == 0 {
+                return Err("CH347ReadData Fail");
+            }
+        }
+
+        let data_len = pkt[1] as usize | (pkt[2] as usize) << 8;
+        if (len as usize) < pkt.len() || pkt[0] != cmd || data_len != buf.len() {
+            return Err("Bad JTAG reply");
+        }
+        buf.copy_from_slice(&pkt[3..]);
+
+        Ok(())
+    }
+}
+
+/// Clock bits one TCK at a time, returns TDO if `read`
+fn jtag_bits<P: JtagPacket>(
+    p: &P,
+    bits: &[(bool, bool)],
+    read: bool,
+) -> Result<Vec<bool>, &'static str> {
+    let mut tdo = Vec::new();
+
+    // two bytes per bit plus the final TCK low
+    for chunk in bits.chunks(PACKET_DATA_MAX / 2 - 1) {
+        let mut data = Vec::with_capacity(chunk.len() * 2 + 1);
+        let mut level = 0;
+        for &(tms, tdi) in chunk {
+            level = if tms { PIN_TMS } else { 0 } | if tdi { PIN_TDI } else { 0 };
+            data.push(level);
+            data.push(level | PIN_TCK);
+        }
+        data.push(level);
+
+        if read {
+            p.jtag_command(CMD_JTAG_BIT_OP_RD, &data)?;
+            let mut reply = vec![0; chunk.len()];
+            p.jtag_reply(CMD_JTAG_BIT_OP_RD, &mut reply)?;
+            tdo.extend(reply.iter().map(|b| b & 0x01 != 0));
+        } else {
+            p.jtag_command(CMD_JTAG_BIT_OP, &data)?;
+        }
+    }
+
+    Ok(tdo)
+}
+
+fn get_bit(buf: &[u8], n: usize) -> bool {
+    buf[n / 8] & (1 << (n % 8)) != 0
+}
+
+/// Shift in a Shift-IR/DR state, see `JtagDrive::jtag_shift`
+fn jtag_shift_bits<P: JtagPacket>(
+    p: &P,
+    tdi: &[u8],
+    bits: usize,
+    exit: bool,
+    mut tdo: Option<&mut [u8]>,
+) -> Result<(), &'static str> {
+    let bytes = bits.div_ceil(8);
+    if tdi.len() < bytes || tdo.as_ref().is_some_and(|t| t.len() < bytes) {
+        return Err("JTAG buffer too small");
+    }
+    if bits == 0 {
+        return Ok(());
+    }
+
+    // whole bytes go in bulk, the rest and the bit raising TMS bit by bit
+    let bulk = if exit { (bits - 1) / 8 } else { bits / 8 };
+
+    for (i, chunk) in tdi[..bulk].chunks(PACKET_DATA_MAX).enumerate() {
+        match &mut tdo {
+            Some(tdo) => {
+                p.jtag_command(CMD_JTAG_DATA_SHIFT_RD, chunk)?;
+                let start = i * PACKET_DATA_MAX;
+                p.jtag_reply(CMD_JTAG_DATA_SHIFT_RD, &mut tdo[start..start + chunk.len()])?;
+            }
+            None => p.jtag_command(CMD_JTAG_DATA_SHIFT, chunk)?,
+        }
+    }
+
+    let rest: Vec<(bool, bool)> = (bulk * 8..bits)
+        .map(|n| (exit && n == bits - 1, get_bit(tdi, n)))
+        .collect();
+    if rest.is_empty() {
+        return Ok(());
+    }
+
+    let rest_tdo = jtag_bits(p, &rest, tdo.is_some())?;
+    if let Some(tdo) = tdo {
+        for (n, bit) in (bulk * 8..bits).zip(rest_tdo) {
+            if bit {
+                tdo[n / 8] |= 1 << (n % 8);
+            } else {
+                tdo[n / 8] &= !(1 << (n % 8));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl JtagDrive for Ch347Device {
+    fn jtag_tms(&self, tms: &[bool]) -> Result<(), &'static str> {
+        let bits: Vec<(bool, bool)> = tms.iter().map(|&t| (t, false)).collect();
+        jtag_bits(self, &bits, false).map(|_| ())
+    }
+
+    fn jtag_shift(
+        &self,
+        tdi: &[u8],
+        bits: usize,
+        exit: bool,
+        tdo: Option<&mut [u8]>,
+    ) -> Result<(), &'static str> {
+        jtag_shift_bits(self, tdi, bits, exit, tdo)
+    }
+}
+
+#[test]
+pub fn test_jtag_packets() {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    /// Records the packets, TDO echoes TDI
+    struct MockPackets {
+        sent: RefCell<Vec<(u8, Vec<u8>)>>,
+        replies: RefCell<VecDeque<(u8, Vec<u8>)>>,
+    }
+
+    impl JtagPacket for MockPackets {
+        fn jtag_command(&self, cmd: u8, data: &[u8]) -> Result<(), &'static str> {
+            self.sent.borrow_mut().push((cmd, data.to_vec()));
+            let reply = match cmd {
+                CMD_JTAG_BIT_OP_RD => data
+                    .iter()
+                    .filter(|&&b| b & PIN_TCK != 0)
+                    .map(|&b| (b & PIN_TDI != 0) as u8)
+                    .collect(),
+                CMD_JTAG_DATA_SHIFT_RD => data.to_vec(),
+                _ => return Ok(()),
+            };
+            self.replies.borrow_mut().push_back((cmd, reply));
+            Ok(())
+        }
+
+        fn jtag_reply(&self, cmd: u8, buf: &mut [u8]) -> Result<(), &'static str> {
+            let (reply_cmd, reply) = self.replies.borrow_mut().pop_front().ok_or("no reply")?;
+            if reply_cmd != cmd || reply.len() != buf.len() {
+                return Err("Bad JTAG reply");
+            }
+            buf.copy_from_slice(&reply);
+            Ok(())
+        }
+    }
+
+    let p = MockPackets {
+        sent: RefCell::new(Vec::new()),
+        replies: RefCell::new(VecDeque::new()),
+    };
+    let cmds = |p: &MockPackets| -> Vec<(u8, usize)> {
+        p.sent
+            .borrow_mut()
+            .drain(..)
+            .map(|(c, d)| (c, d.len()))
+            .collect()
+    };
+
+    // 21 bits with exit: 2 bytes in bulk, 5 bits one by one, TMS on the last
+    let tdi = [0x5A, 0xC3, 0x15];
+    let mut tdo = [0; 3];
+    jtag_shift_bits(&p, &tdi, 21, true, Some(&mut tdo)).unwrap();
+    assert_eq!(tdo, tdi);
+    let sent = p.sent.borrow().clone();
+    assert_eq!(sent[0], (CMD_JTAG_DATA_SHIFT_RD, vec![0x5A, 0xC3]));
+    assert_eq!(sent[1].0, CMD_JTAG_BIT_OP_RD);
+    // 0x15: TDI 1 0 1 0 1
+    assert_eq!(
+        sent[1].1,
+        vec![0x10, 0x11, 0x00, 0x01, 0x10, 0x11, 0x00, 0x01, 0x12, 0x13, 0x12]
+    );
+    assert_eq!(sent.len(), 2);
+    cmds(&p);
+
+    // a whole last byte with exit still goes bit by bit
+    jtag_shift_bits(&p, &[0xFF, 0x81], 16, true, None).unwrap();
+    assert_eq!(
+        cmds(&p),
+        vec![(CMD_JTAG_DATA_SHIFT, 1), (CMD_JTAG_BIT_OP, 8 * 2 + 1)]
+    );
+
+    // without exit whole bytes only go in bulk
+    jtag_shift_bits(&p, &[0xFF, 0x81], 16, false, None).unwrap();
+    assert_eq!(cmds(&p), vec![(CMD_JTAG_DATA_SHIFT, 2)]);
+
+    // long shifts are split into packets
+    let tdi: Vec<u8> = (0..1100).map(|i| i as u8).collect();
+    let mut tdo = vec![0; 1100];
+    jtag_shift_bits(&p, &tdi, 1100 * 8 - 3, true, Some(&mut tdo)).unwrap();
+    assert_eq!(&tdo[..1099], &tdi[..1099]);
+    assert_eq!(tdo[1099], tdi[1099] & 0x1F);
+    assert_eq!(
+        cmds(&p),
+        vec![
+            (CMD_JTAG_DATA_SHIFT_RD, 500),
+            (CMD_JTAG_DATA_SHIFT_RD, 500),
+            (CMD_JTAG_DATA_SHIFT_RD, 99),
+            (CMD_JTAG_BIT_OP_RD, 5 * 2 + 1),
+        ]
+    );
+
+    jtag_bits(&p, &[(true, false); 300], false).unwrap();
+    assert_eq!(
+        cmds(&p),
+        vec![
+            (CMD_JTAG_BIT_OP, 249 * 2 + 1),
+            (CMD_JTAG_BIT_OP, 51 * 2 + 1)
+        ]
+    );
+
+    assert!(jtag_shift_bits(&p, &[0], 9, true, None).is_err());
+}
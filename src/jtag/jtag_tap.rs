@@ -0,0 +1,231 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::JtagDrive;
+
+/// States of the IEEE 1149.1 TAP controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TapState {
+    TestLogicReset,
+    RunTestIdle,
+    SelectDrScan,
+    CaptureDr,
+    ShiftDr,
+    Exit1Dr,
+    PauseDr,
+    Exit2Dr,
+    UpdateDr,
+    SelectIrScan,
+    CaptureIr,
+    ShiftIr,
+    Exit1Ir,
+    PauseIr,
+    Exit2Ir,
+    UpdateIr,
+}
+
+impl TapState {
+    /// State after one TCK with the given TMS level
+    pub fn next(self, tms: bool) -> TapState {
+        use TapState::*;
+
+        match (self, tms) {
+            (TestLogicReset, true) => TestLogicReset,
+            (TestLogicReset, false) => RunTestIdle,
+            (RunTestIdle, true) => SelectDrScan,
+            (RunTestIdle, false) => RunTestIdle,
+            (SelectDrScan, true) => SelectIrScan,
+            (SelectDrScan, false) => CaptureDr,
+            (CaptureDr, true) => Exit1Dr,
+            (CaptureDr, false) => ShiftDr,
+            (ShiftDr, true) => Exit1Dr,
+            (ShiftDr, false) => ShiftDr,
+            (Exit1Dr, true) => UpdateDr,
+            (Exit1Dr, false) => PauseDr,
+            (PauseDr, true) => Exit2Dr,
+            (PauseDr, false) => PauseDr,
+            (Exit2Dr, true) => UpdateDr,
+            (Exit2Dr, false) => ShiftDr,
+            (UpdateDr, true) => SelectDrScan,
+            (UpdateDr, false) => RunTestIdle,
+            (SelectIrScan, true) => TestLogicReset,
+            (SelectIrScan, false) => CaptureIr,
+            (CaptureIr, true) => Exit1Ir,
+            (CaptureIr, false) => ShiftIr,
+            (ShiftIr, true) => Exit1Ir,
+            (ShiftIr, false) => ShiftIr,
+            (Exit1Ir, true) => UpdateIr,
+            (Exit1Ir, false) => PauseIr,
+            (PauseIr, true) => Exit2Ir,
+            (PauseIr, false) => PauseIr,
+            (Exit2Ir, true) => UpdateIr,
+            (Exit2Ir, false) => ShiftIr,
+            (UpdateIr, true) => SelectDrScan,
+            (UpdateIr, false) => RunTestIdle,
+        }
+    }
+
+    /// Shortest TMS sequence from `self` to `to`
+    pub fn path_to(self, to: TapState) -> Vec<bool> {
+        let mut queue = VecDeque::from([(self, Vec::new())]);
+        let mut seen = vec![self];
+
+        while let Some((state, path)) = queue.pop_front() {
+            if state == to {
+                return path;
+            }
+
+            for tms in [false, true] {
+                let next = state.next(tms);
+                if !seen.contains(&next) {
+                    seen.push(next);
+                    let mut path = path.clone();
+                    path.push(tms);
+                    queue.push_back((next, path));
+                }
+            }
+        }
+
+        unreachable!("every TAP state is reachable")
+    }
+}
+
+impl fmt::Display for TapState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// JTAG master that tracks the TAP state, shifts end in Run-Test/Idle
+pub struct Jtag<T: JtagDrive> {
+    pub drive: T,
+    state: Cell<TapState>,
+}
+
+impl<T: JtagDrive> Jtag<T> {
+    /// Resets the TAP to start from a known state
+    pub fn new(drive: T) -> Result<Jtag<T>, &'static str> {
+        let jtag = Jtag {
+            drive,
+            state: Cell::new(TapState::TestLogicReset),
+        };
+        jtag.reset()?;
+
+        Ok(jtag)
+    }
+
+    pub fn state(&self) -> TapState {
+        self.state.get()
+    }
+
+    /// Five TCKs with TMS high reach Test-Logic-Reset from any state
+    pub fn reset(&self) -> Result<(), &'static str> {
+        self.drive.jtag_tms(&[true; 5])?;
+        self.state.set(TapState::TestLogicReset);
+
+        Ok(())
+    }
+
+    pub fn goto_state(&self, state: TapState) -> Result<(), &'static str> {
+        let path = self.state.get().path_to(state);
+        if !path.is_empty() {
+            self.drive.jtag_tms(&path)?;
+        }
+        self.state.set(state);
+
+        Ok(())
+    }
+
+    /// Stay in Run-Test/Idle for `cycles` TCKs
+    pub fn run_test_idle(&self, cycles: usize) -> Result<(), &'static str> {
+        self.goto_state(TapState::RunTestIdle)?;
+        if cycles > 0 {
+            self.drive.jtag_tms(&vec![false; cycles])?;
+        }
+
+        Ok(())
+    }
+
+    fn shift(
+        &self,
+        shift_state: TapState,
+        tdi: &[u8],
+        bits: usize,
+    ) -> Result<Vec<u8>, &'static str> {
+        if bits == 0 {
+            return Err("Nothing to shift");
+        }
+        if tdi.len() * 8 < bits {
+            return Err("JTAG buffer too small");
+        }
+
+        let mut tdo = vec![0; bits.div_ceil(8)];
+
+        self.goto_state(shift_state)?;
+        self.drive.jtag_shift(tdi, bits, true, Some(&mut tdo))?;
+        self.state.set(shift_state.next(true));
+        self.goto_state(TapState::RunTestIdle)?;
+
+        Ok(tdo)
+    }
+
+    /// Shift `bits` of `tdi` LSB first through the instruction registers,
+    /// returns the captured TDO bits
+    pub fn shift_ir(&self, tdi: &[u8], bits: usize) -> Result<Vec<u8>, &'static str> {
+        self.shift(TapState::ShiftIr, tdi, bits)
+    }
+
+    /// Shift `bits` of `tdi` LSB first through the selected data registers,
+    /// returns the captured TDO bits
+    pub fn shift_dr(&self, tdi: &[u8], bits: usize) -> Result<Vec<u8>, &'static str> {
+        self.shift(TapState::ShiftDr, tdi, bits)
+    }
+}
+
+#[test]
+pub fn test_tap_state() {
+    use TapState::*;
+
+    assert_eq!(
+        TestLogicReset.path_to(ShiftDr),
+        vec![false, true, false, false]
+    );
+    assert_eq!(
+        TestLogicReset.path_to(ShiftIr),
+        vec![false, true, true, false, false]
+    );
+    assert_eq!(Exit1Ir.path_to(RunTestIdle), vec![true, false]);
+    assert_eq!(PauseDr.path_to(ShiftDr), vec![true, false]);
+    assert!(ShiftDr.path_to(ShiftDr).is_empty());
+
+    let all = [
+        TestLogicReset,
+        RunTestIdle,
+        SelectDrScan,
+        CaptureDr,
+        ShiftDr,
+        Exit1Dr,
+        PauseDr,
+        Exit2Dr,
+        UpdateDr,
+        SelectIrScan,
+        CaptureIr,
+        ShiftIr,
+        Exit1Ir,
+        PauseIr,
+        Exit2Ir,
+        UpdateIr,
+    ];
+    for from in all {
+        for to in all {
+            let end = from.path_to(to).into_iter().fold(from, TapState::next);
+            assert_eq!(end, to);
+        }
+        // five TMS high reset from anywhere
+        assert_eq!(
+            [true; 5].into_iter().fold(from, TapState::next),
+            TestLogicReset
+        );
+    }
+}
@@ -0,0 +1,9 @@
+mod jtag_chain;
+mod jtag_drive;
+mod jtag_tap;
+mod model;
+
+pub use jtag_chain::*;
+pub use jtag_drive::*;
+pub use jtag_tap::*;
+pub use model::*;
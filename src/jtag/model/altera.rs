@@ -0,0 +1,38 @@
+use super::{find_part, Idcode, JtagChip, JtagVendor, PartTable};
+
+#[test]
+pub fn test_parse_idcode() {
+    for (idcode, name) in [
+        (0x020A10DD, "EPM240"),
+        (0x020F30DD, "EP4CE22"),
+        (0x031810DD, "10M08"),
+    ] {
+        let chip = super::parse_idcode(idcode).unwrap();
+
+        assert_eq!(name, chip.name);
+        assert_eq!(10, chip.ir_len);
+    }
+}
+
+const PARTS: [(u16, &str, u8); 12] = [
+    // MAX II
+    (0x20A1, "EPM240", 10),
+    (0x20A2, "EPM570", 10),
+    (0x20A3, "EPM1270", 10),
+    (0x20A4, "EPM2210", 10),
+    // Cyclone IV E
+    (0x20F1, "EP4CE6/EP4CE10", 10),
+    (0x20F2, "EP4CE15", 10),
+    (0x20F3, "EP4CE22", 10),
+    (0x20F4, "EP4CE30/EP4CE40", 10),
+    (0x20F5, "EP4CE55", 10),
+    // Cyclone V
+    (0x2B05, "5CEBA4", 10),
+    // MAX 10
+    (0x3181, "10M08", 10),
+    (0x3105, "10M50", 10),
+];
+
+pub fn parse_idcode(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    find_part(vendor, &PARTS as &PartTable, idcode)
+}
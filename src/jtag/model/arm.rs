@@ -0,0 +1,18 @@
+use super::{find_part, Idcode, JtagChip, JtagVendor, PartTable};
+
+#[test]
+pub fn test_parse_idcode() {
+    for idcode in [0x3BA00477, 0x4BA00477, 0x5BA00477, 0x6BA00477] {
+        let chip = super::parse_idcode(idcode).unwrap();
+
+        assert_eq!("CoreSight JTAG-DP", chip.name);
+        assert_eq!(4, chip.ir_len);
+    }
+}
+
+/// The debug port of Cortex cores, the version differs per core
+const PARTS: [(u16, &str, u8); 1] = [(0xBA00, "CoreSight JTAG-DP", 4)];
+
+pub fn parse_idcode(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    find_part(vendor, &PARTS as &PartTable, idcode)
+}
@@ -0,0 +1,23 @@
+use super::{find_part, Idcode, JtagChip, JtagVendor, PartTable};
+
+#[test]
+pub fn test_parse_idcode() {
+    let chip = super::parse_idcode(0x4970203F).unwrap();
+
+    assert_eq!("ATmega128", chip.name);
+    assert_eq!(4, chip.ir_len);
+}
+
+/// AVR with JTAG
+const PARTS: [(u16, &str, u8); 6] = [
+    (0x9403, "ATmega16", 4),
+    (0x9502, "ATmega32", 4),
+    (0x9602, "ATmega64", 4),
+    (0x9702, "ATmega128", 4),
+    (0x9704, "ATmega1281", 4),
+    (0x9801, "ATmega2560", 4),
+];
+
+pub fn parse_idcode(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    find_part(vendor, &PARTS as &PartTable, idcode)
+}
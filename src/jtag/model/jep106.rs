@@ -0,0 +1,39 @@
+/// JEP106 manufacturers seen on JTAG chains: (continuation codes, id without
+/// parity, name). Only a subset of the standard
+const JEP106_LIST: [(u8, u8, &str); 26] = [
+    (0, 0x01, "AMD"),
+    (0, 0x04, "Fujitsu"),
+    (0, 0x09, "Intel"),
+    (0, 0x0E, "Freescale (Motorola)"),
+    (0, 0x10, "NEC"),
+    (0, 0x15, "NXP (Philips)"),
+    (0, 0x17, "Texas Instruments"),
+    (0, 0x18, "Toshiba"),
+    (0, 0x1F, "Atmel (Microchip)"),
+    (0, 0x20, "STMicroelectronics"),
+    (0, 0x21, "Lattice"),
+    (0, 0x29, "Microchip Technology"),
+    (0, 0x2C, "Micron Technology"),
+    (0, 0x34, "Cypress (Infineon)"),
+    (0, 0x41, "Infineon (Siemens)"),
+    (0, 0x49, "Xilinx (AMD)"),
+    (0, 0x4E, "Samsung"),
+    (0, 0x65, "Analog Devices"),
+    (0, 0x6E, "Altera (Intel)"),
+    (0, 0x70, "Qualcomm"),
+    (1, 0x3F, "Broadcom"),
+    (1, 0x67, "Actel (Microsemi)"),
+    (4, 0x3B, "ARM"),
+    (4, 0x72, "Tensilica (Cadence)"),
+    (6, 0x1E, "GigaDevice"),
+    (8, 0x0D, "Gowin Semiconductor"),
+];
+
+/// Manufacturer name of a JEP106 code, `bank` is the number of continuation
+/// codes
+pub fn jep106_manufacturer(bank: u8, id: u8) -> Option<&'static str> {
+    JEP106_LIST
+        .iter()
+        .find(|&&(b, i, _)| b == bank && i == id)
+        .map(|&(_, _, name)| name)
+}
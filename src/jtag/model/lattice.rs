@@ -0,0 +1,52 @@
+use super::{find_part, Idcode, JtagChip, JtagVendor, PartTable};
+
+#[test]
+pub fn test_parse_idcode() {
+    for (idcode, name) in [
+        (0x012BA043, "LCMXO2-1200"),
+        (0x41111043, "LFE5U-25F"),
+        (0x01112043, "LFE5UM-45F"),
+        (0x81113043, "LFE5UM5G-85F"),
+    ] {
+        let chip = super::parse_idcode(idcode).unwrap();
+
+        assert_eq!(name, chip.name);
+        assert_eq!(8, chip.ir_len);
+    }
+
+    assert!(super::parse_idcode(0x21111043).is_none());
+}
+
+const PARTS: [(u16, &str, u8); 5] = [
+    (0x12B0, "LCMXO2-256", 8),
+    (0x12BA, "LCMXO2-1200", 8),
+    (0x12BB, "LCMXO2-2000", 8),
+    (0x12BC, "LCMXO2-4000", 8),
+    (0x12B5, "LCMXO2-7000", 8),
+];
+
+/// ECP5 share the part number between variants, the version tells them apart
+fn parse_ecp5(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    let size = match idcode.part() {
+        0x1111 => "25F",
+        0x1112 => "45F",
+        0x1113 => "85F",
+        _ => return None,
+    };
+    let family = match idcode.version() {
+        0x0 => "LFE5UM",
+        0x4 => "LFE5U",
+        0x8 => "LFE5UM5G",
+        _ => return None,
+    };
+
+    Some(JtagChip {
+        name: format!("{}-{}", family, size),
+        vendor,
+        ir_len: 8,
+    })
+}
+
+pub fn parse_idcode(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    parse_ecp5(vendor, idcode).or_else(|| find_part(vendor, &PARTS as &PartTable, idcode))
+}
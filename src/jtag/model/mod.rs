@@ -0,0 +1,138 @@
+mod altera;
+mod arm;
+mod atmel;
+mod jep106;
+mod lattice;
+mod stmicro;
+mod xilinx;
+
+use std::fmt;
+
+pub use jep106::jep106_manufacturer;
+
+type IdcodeParser = fn(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip>;
+
+/// Part number, name and IR length
+type PartTable = [(u16, &'static str, u8)];
+
+pub struct JtagVendor {
+    pub name: &'static str,
+    /// JEP106 (continuation codes, id without parity)
+    pub jep106: (u8, u8),
+    pub parser: IdcodeParser,
+}
+
+pub struct JtagChip {
+    pub name: String,
+    pub vendor: &'static JtagVendor,
+    pub ir_len: u8,
+}
+
+/// IEEE 1149.1 device identification register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Idcode(pub u32);
+
+impl Idcode {
+    pub fn version(&self) -> u8 {
+        (self.0 >> 28) as u8
+    }
+
+    pub fn part(&self) -> u16 {
+        (self.0 >> 12) as u16
+    }
+
+    /// JEP106 (continuation codes, id without parity), the bank field only
+    /// keeps 4 bits of the continuation count
+    pub fn manufacturer(&self) -> (u8, u8) {
+        (((self.0 >> 8) & 0x0F) as u8, ((self.0 >> 1) & 0x7F) as u8)
+    }
+
+    /// Bit 0 is always set, 0x7F is reserved for continuation codes
+    pub fn is_valid(&self) -> bool {
+        self.0 & 0x01 == 1 && self.manufacturer().1 != 0x7F && self.0 != 0xFFFF_FFFF
+    }
+
+    pub fn manufacturer_name(&self) -> Option<&'static str> {
+        let (bank, id) = self.manufacturer();
+        jep106_manufacturer(bank, id)
+    }
+}
+
+impl fmt::Display for Idcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:08X}", self.0)
+    }
+}
+
+/// Look `idcode` up in a vendor part table, the version is ignored
+fn find_part(vendor: &'static JtagVendor, table: &PartTable, idcode: Idcode) -> Option<JtagChip> {
+    let (_, name, ir_len) = table.iter().find(|(part, _, _)| *part == idcode.part())?;
+
+    Some(JtagChip {
+        name: name.to_string(),
+        vendor,
+        ir_len: *ir_len,
+    })
+}
+
+const JTAG_VENDOR_LIST: [JtagVendor; 6] = [
+    JtagVendor {
+        name: "Altera (Intel)",
+        jep106: (0, 0x6E),
+        parser: altera::parse_idcode,
+    },
+    JtagVendor {
+        name: "ARM",
+        jep106: (4, 0x3B),
+        parser: arm::parse_idcode,
+    },
+    JtagVendor {
+        name: "Atmel (Microchip)",
+        jep106: (0, 0x1F),
+        parser: atmel::parse_idcode,
+    },
+    JtagVendor {
+        name: "Lattice",
+        jep106: (0, 0x21),
+        parser: lattice::parse_idcode,
+    },
+    JtagVendor {
+        name: "STMicroelectronics",
+        jep106: (0, 0x20),
+        parser: stmicro::parse_idcode,
+    },
+    JtagVendor {
+        name: "Xilinx (AMD)",
+        jep106: (0, 0x49),
+        parser: xilinx::parse_idcode,
+    },
+];
+
+#[test]
+pub fn test_parse_idcode() {
+    let id = Idcode(0x4BA00477);
+    assert!(id.is_valid());
+    assert_eq!(id.version(), 0x4);
+    assert_eq!(id.part(), 0xBA00);
+    assert_eq!(id.manufacturer(), (4, 0x3B));
+    assert_eq!(id.manufacturer_name(), Some("ARM"));
+
+    assert!(!Idcode(0xFFFF_FFFF).is_valid());
+    assert!(!Idcode(0x0362D092).is_valid());
+    assert!(parse_idcode(0xFFFF_FFFF).is_none());
+    assert!(parse_idcode(0x0000_0001).is_none());
+    assert_eq!(Idcode(0x1234_5001).manufacturer_name(), None);
+}
+
+pub fn parse_idcode(idcode: u32) -> Option<JtagChip> {
+    let idcode = Idcode(idcode);
+    if !idcode.is_valid() {
+        return None;
+    }
+
+    let vendor = JTAG_VENDOR_LIST
+        .iter()
+        .find(|&v| v.jep106 == idcode.manufacturer())?;
+
+    (vendor.parser)(vendor, idcode)
+}
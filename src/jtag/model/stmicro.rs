@@ -0,0 +1,30 @@
+use super::{find_part, Idcode, JtagChip, JtagVendor, PartTable};
+
+#[test]
+pub fn test_parse_idcode() {
+    for (idcode, name) in [
+        (0x16410041, "STM32F1 medium density"),
+        (0x06413041, "STM32F405/407"),
+    ] {
+        let chip = super::parse_idcode(idcode).unwrap();
+
+        assert_eq!(name, chip.name);
+        assert_eq!(5, chip.ir_len);
+    }
+}
+
+/// Boundary scan TAPs of STM32, next to the ARM debug port on the chain
+const PARTS: [(u16, &str, u8); 8] = [
+    (0x6410, "STM32F1 medium density", 5),
+    (0x6412, "STM32F1 low density", 5),
+    (0x6414, "STM32F1 high density", 5),
+    (0x6418, "STM32F1 connectivity line", 5),
+    (0x6413, "STM32F405/407", 5),
+    (0x6419, "STM32F42x/43x", 5),
+    (0x6431, "STM32F411", 5),
+    (0x6433, "STM32F401xD/E", 5),
+];
+
+pub fn parse_idcode(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    find_part(vendor, &PARTS as &PartTable, idcode)
+}
@@ -0,0 +1,45 @@
+use super::{find_part, Idcode, JtagChip, JtagVendor, PartTable};
+
+#[test]
+pub fn test_parse_idcode() {
+    for (idcode, name, ir_len) in [
+        (0x0362D093, "XC7A35T", 6),
+        (0x13631093, "XC7A100T", 6),
+        (0x03727093, "XC7Z020", 6),
+        (0x24001093, "XC6SLX9", 6),
+        (0x59604093, "XC9572XL", 8),
+    ] {
+        let chip = super::parse_idcode(idcode).unwrap();
+
+        assert_eq!(name, chip.name);
+        assert_eq!(ir_len, chip.ir_len);
+    }
+}
+
+const PARTS: [(u16, &str, u8); 16] = [
+    // Spartan-6
+    (0x4000, "XC6SLX4", 6),
+    (0x4001, "XC6SLX9", 6),
+    (0x4002, "XC6SLX16", 6),
+    (0x4004, "XC6SLX25", 6),
+    (0x4008, "XC6SLX45", 6),
+    // Artix-7
+    (0x362C, "XC7A50T", 6),
+    (0x362D, "XC7A35T", 6),
+    (0x3631, "XC7A100T", 6),
+    (0x3632, "XC7A75T", 6),
+    (0x3636, "XC7A200T", 6),
+    // Kintex-7
+    (0x3651, "XC7K325T", 6),
+    // Zynq-7000, the PL TAP
+    (0x3722, "XC7Z010", 6),
+    (0x3727, "XC7Z020", 6),
+    // XC9500XL
+    (0x9602, "XC9536XL", 8),
+    (0x9604, "XC9572XL", 8),
+    (0x9608, "XC95144XL", 8),
+];
+
+pub fn parse_idcode(vendor: &'static JtagVendor, idcode: Idcode) -> Option<JtagChip> {
+    find_part(vendor, &PARTS as &PartTable, idcode)
+}
@@ -1,6 +1,28 @@
+mod bitbang;
 mod ch347lib;
+mod eeprom;
+mod gpio;
+#[cfg(feature = "embedded-hal")]
+mod hal;
+mod i2c;
+mod jtag;
+mod register;
+mod smbus;
+mod spi;
 mod spi_flash;
+mod uart;
 mod windows;
 
+pub use bitbang::*;
 pub use ch347lib::*;
+pub use eeprom::*;
+pub use gpio::*;
+#[cfg(feature = "embedded-hal")]
+pub use hal::*;
+pub use i2c::*;
+pub use jtag::*;
+pub use register::*;
+pub use smbus::*;
+pub use spi::*;
 pub use spi_flash::*;
+pub use uart::*;
@@ -0,0 +1,3 @@
+mod register_map;
+
+pub use register_map::*;
@@ -0,0 +1,325 @@
+use std::{error::Error, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Ch347Spi, DataWidth, Endian, I2cAddress, I2cBus, I2cDrive, RegFormat, RegisterAccess,
+    RegisterItem,
+};
+
+fn default_access() -> RegisterAccess {
+    RegisterAccess::ReadWrite
+}
+
+/// Register layout of a peripheral, loaded from a JSON or YAML file
+///
+/// ```json
+/// {
+///   "name": "pmic",
+///   "addr_width": "byte",
+///   "value_width": "byte",
+///   "registers": [
+///     { "name": "status", "addr": 1, "access": "read_only",
+///       "items": [{ "name": "PGOOD", "offset": 0, "width": 1 }] }
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterMap {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "RegisterMap::default_width")]
+    pub addr_width: DataWidth,
+    #[serde(default = "RegisterMap::default_width")]
+    pub value_width: DataWidth,
+    #[serde(default = "RegisterMap::default_endian")]
+    pub endian: Endian,
+    /// OR-ed into the register address of a SPI read, eg. 0x80
+    #[serde(default)]
+    pub spi_read_mask: u16,
+    /// OR-ed into the register address of a SPI write
+    #[serde(default)]
+    pub spi_write_mask: u16,
+    pub registers: Vec<RegisterDef>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterDef {
+    pub name: String,
+    pub addr: u16,
+    #[serde(default)]
+    pub describe: String,
+    #[serde(default = "default_access")]
+    pub access: RegisterAccess,
+    #[serde(default)]
+    pub items: Vec<RegisterField>,
+}
+
+/// Named bit field of a register, the owned form of `RegisterItem`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterField {
+    pub name: String,
+    #[serde(default)]
+    pub alias: Vec<String>,
+    #[serde(default)]
+    pub describe: String,
+    pub offset: u8,
+    #[serde(default = "RegisterField::default_width")]
+    pub width: u8,
+    #[serde(default = "default_access")]
+    pub access: RegisterAccess,
+}
+
+pub enum FindRegister<'a> {
+    Reg(&'a RegisterDef),
+    Field(&'a RegisterDef, &'a RegisterField),
+}
+
+impl From<&RegisterItem> for RegisterField {
+    fn from(item: &RegisterItem) -> Self {
+        RegisterField {
+            name: item.name.to_string(),
+            alias: item.alias.iter().map(|a| a.to_string()).collect(),
+            describe: item.describe.to_string(),
+            offset: item.offset,
+            width: item.width,
+            access: item.access,
+        }
+    }
+}
+
+impl RegisterField {
+    fn default_width() -> u8 {
+        1
+    }
+
+    /// Bits of the field in the register value
+    pub fn mask(&self) -> u16 {
+        (((1u32 << self.width) - 1) << self.offset) as u16
+    }
+
+    pub fn extract(&self, reg_value: u16) -> u16 {
+        (reg_value & self.mask()) >> self.offset
+    }
+
+    /// Register value with the field replaced by `value`
+    pub fn insert(&self, reg_value: u16, value: u16) -> Result<u16, &'static str> {
+        if (value as u32) >= (1u32 << self.width) {
+            return Err("Value does not fit in the field");
+        }
+
+        Ok((reg_value & !self.mask()) | (value << self.offset))
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.alias.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+impl RegisterMap {
+    fn default_width() -> DataWidth {
+        DataWidth::Byte
+    }
+
+    fn default_endian() -> Endian {
+        Endian::Little
+    }
+
+    pub fn from_json(s: &str) -> Result<RegisterMap, Box<dyn Error>> {
+        let map: RegisterMap = serde_json::from_str(s)?;
+        map.check()?;
+        Ok(map)
+    }
+
+    pub fn from_yaml(s: &str) -> Result<RegisterMap, Box<dyn Error>> {
+        let map: RegisterMap = serde_yaml::from_str(s)?;
+        map.check()?;
+        Ok(map)
+    }
+
+    /// Every address must fit in `addr_width`, every field must be at least
+    /// one bit wide and fit in `value_width`
+    pub fn check(&self) -> Result<(), String> {
+        let value_bits = self.value_width.bytes() * 8;
+
+        for r in &self.registers {
+            if r.addr > self.addr_width.max_value() {
+                return Err(format!(
+                    "Register {} address 0x{:X} does not fit in {} bits",
+                    r.name,
+                    r.addr,
+                    self.addr_width.bytes() * 8
+                ));
+            }
+
+            for f in &r.items {
+                if f.width == 0 || (f.offset as usize + f.width as usize) > value_bits {
+                    return Err(format!(
+                        "Field {}.{} (offset {}, width {}) does not fit in {} bits",
+                        r.name, f.name, f.offset, f.width, value_bits
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `.yaml`/`.yml` files are parsed as YAML, everything else as JSON
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RegisterMap, Box<dyn Error>> {
+        let s = fs::read_to_string(&path)?;
+
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => RegisterMap::from_yaml(&s),
+            _ => RegisterMap::from_json(&s),
+        }
+    }
+
+    pub fn format(&self) -> RegFormat {
+        RegFormat {
+            addr_width: self.addr_width,
+            value_width: self.value_width,
+            endian: self.endian,
+        }
+    }
+
+    /// Find a register by name or address, or a field by name or alias
+    pub fn find(&self, name: &str) -> Option<FindRegister<'_>> {
+        for r in &self.registers {
+            if r.name.eq_ignore_ascii_case(name) {
+                return Some(FindRegister::Reg(r));
+            }
+        }
+
+        for r in &self.registers {
+            if let Some(f) = r.items.iter().find(|f| f.is_match(name)) {
+                return Some(FindRegister::Field(r, f));
+            }
+        }
+
+        let addr = match name.strip_prefix("0x") {
+            Some(s) => u16::from_str_radix(s, 16).ok()?,
+            None => name.parse().ok()?,
+        };
+
+        self.registers
+            .iter()
+            .find(|r| r.addr == addr)
+            .map(FindRegister::Reg)
+    }
+}
+
+impl<T: I2cDrive> I2cBus<T> {
+    pub fn read_register<A: Into<I2cAddress>>(
+        &self,
+        addr: A,
+        map: &RegisterMap,
+        reg: &RegisterDef,
+    ) -> Result<u16, Box<dyn Error>> {
+        Ok(self.read_reg(addr, reg.addr, map.format())?)
+    }
+
+    pub fn write_register<A: Into<I2cAddress>>(
+        &self,
+        addr: A,
+        map: &RegisterMap,
+        reg: &RegisterDef,
+        value: u16,
+    ) -> Result<(), Box<dyn Error>> {
+        if let RegisterAccess::ReadOnly = reg.access {
+            return Err(format!("Register {} is read-only", reg.name).into());
+        }
+
+        Ok(self.write_reg(addr, reg.addr, value, map.format())?)
+    }
+}
+
+impl Ch347Spi {
+    /// Register address (with `spi_read_mask`) out, then the value in
+    pub fn read_register(
+        &self,
+        map: &RegisterMap,
+        reg: &RegisterDef,
+    ) -> Result<u16, Box<dyn Error>> {
+        let wbuf = Endian::Big.to_bytes(reg.addr | map.spi_read_mask, map.addr_width);
+        let mut rbuf = vec![0; map.value_width.bytes()];
+        self.write_then_read(&wbuf, &mut rbuf)?;
+
+        Ok(map.endian.from_bytes(&rbuf))
+    }
+
+    pub fn write_register(
+        &self,
+        map: &RegisterMap,
+        reg: &RegisterDef,
+        value: u16,
+    ) -> Result<(), Box<dyn Error>> {
+        if let RegisterAccess::ReadOnly = reg.access {
+            return Err(format!("Register {} is read-only", reg.name).into());
+        }
+
+        let mut wbuf = Endian::Big.to_bytes(reg.addr | map.spi_write_mask, map.addr_width);
+        wbuf.extend(map.endian.to_bytes(value, map.value_width));
+
+        Ok(self.write(&wbuf)?)
+    }
+}
+
+#[test]
+pub fn test_register_map() {
+    let json = r#"{
+        "name": "pmic",
+        "value_width": "word",
+        "endian": "big",
+        "registers": [
+            { "name": "status", "addr": 1, "access": "ro",
+              "items": [
+                { "name": "PGOOD", "offset": 0 },
+                { "name": "VSEL", "alias": ["vout_sel"], "offset": 4, "width": 3 }
+              ] },
+            { "name": "ctrl", "addr": 2 }
+        ]
+    }"#;
+
+    let map = RegisterMap::from_json(json).unwrap();
+    assert_eq!(map.addr_width, DataWidth::Byte);
+    assert_eq!(map.value_width, DataWidth::Word);
+
+    let yaml = "value_width: word\nregisters:\n  - name: ctrl\n    addr: 0x10\n    items:\n      - name: EN\n        offset: 15\n";
+    let yaml_map = RegisterMap::from_yaml(yaml).unwrap();
+    assert_eq!(yaml_map.registers[0].addr, 0x10);
+    assert_eq!(yaml_map.registers[0].items[0].mask(), 0x8000);
+
+    assert!(matches!(map.find("CTRL"), Some(FindRegister::Reg(r)) if r.addr == 2));
+    assert!(matches!(map.find("0x01"), Some(FindRegister::Reg(r)) if r.name == "status"));
+    let field = match map.find("VOUT_SEL") {
+        Some(FindRegister::Field(r, f)) if r.name == "status" => f,
+        _ => panic!(),
+    };
+    assert_eq!(field.mask(), 0x0070);
+    assert_eq!(field.extract(0x00F5), 0x7);
+    assert_eq!(field.insert(0x00F5, 0x2), Ok(0x00A5));
+    assert!(field.insert(0, 0x8).is_err());
+    assert!(map.find("nothing").is_none());
+
+    // fields outside the register value are rejected on load
+    let yaml = "registers:\n  - name: ctrl\n    addr: 0\n    items:\n      - name: EN\n        offset: 15\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+    let yaml = "registers:\n  - name: ctrl\n    addr: 0\n    items:\n      - name: EN\n        offset: 7\n        width: 2\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+    let yaml = "registers:\n  - name: ctrl\n    addr: 0\n    items:\n      - name: EN\n        offset: 14\n        width: 3\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+    let yaml = "registers:\n  - name: ctrl\n    addr: 0\n    items:\n      - name: EN\n        width: 0\n        offset: 0\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+    let yaml = "value_width: word\nregisters:\n  - name: ctrl\n    addr: 0\n    items:\n      - name: EN\n        offset: 20\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+    let yaml = "value_width: word\nregisters:\n  - name: ctrl\n    addr: 0\n    items:\n      - name: EN\n        offset: 0\n        width: 32\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+
+    // so are addresses wider than the address bytes
+    let yaml = "registers:\n  - name: ctrl\n    addr: 0x100\n";
+    assert!(RegisterMap::from_yaml(yaml).is_err());
+    let yaml = "addr_width: word\nregisters:\n  - name: ctrl\n    addr: 0x100\n";
+    assert!(RegisterMap::from_yaml(yaml).is_ok());
+}
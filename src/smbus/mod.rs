@@ -0,0 +1,5 @@
+mod pmbus;
+mod protocol;
+
+pub use pmbus::*;
+pub use protocol::*;
@@ -0,0 +1,226 @@
+use std::{collections::BTreeMap, fmt};
+
+use super::{Smbus, SmbusError};
+use crate::I2cDrive;
+
+pub const PMBUS_VOUT_MODE: u8 = 0x20;
+pub const PMBUS_STATUS_BYTE: u8 = 0x78;
+pub const PMBUS_STATUS_WORD: u8 = 0x79;
+pub const PMBUS_READ_VIN: u8 = 0x88;
+pub const PMBUS_READ_IIN: u8 = 0x89;
+pub const PMBUS_READ_VOUT: u8 = 0x8B;
+pub const PMBUS_READ_IOUT: u8 = 0x8C;
+pub const PMBUS_READ_TEMPERATURE_1: u8 = 0x8D;
+pub const PMBUS_READ_POUT: u8 = 0x96;
+pub const PMBUS_READ_PIN: u8 = 0x97;
+
+/// STATUS_WORD bits, most significant first
+pub const PMBUS_STATUS_WORD_BITS: [(u8, &str); 16] = [
+    (15, "VOUT"),
+    (14, "IOUT/POUT"),
+    (13, "INPUT"),
+    (12, "MFR_SPECIFIC"),
+    (11, "POWER_GOOD#"),
+    (10, "FANS"),
+    (9, "OTHER"),
+    (8, "UNKNOWN"),
+    (7, "BUSY"),
+    (6, "OFF"),
+    (5, "VOUT_OV_FAULT"),
+    (4, "IOUT_OC_FAULT"),
+    (3, "VIN_UV_FAULT"),
+    (2, "TEMPERATURE"),
+    (1, "CML"),
+    (0, "NONE_OF_THE_ABOVE"),
+];
+
+/// Names of the bits set in a STATUS_WORD value
+pub fn pmbus_status_word_flags(status: u16) -> Vec<&'static str> {
+    PMBUS_STATUS_WORD_BITS
+        .iter()
+        .filter(|(bit, _)| status & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Sign extend the low `bits` bits of `v`
+fn sign_extend(v: u16, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    ((v as i32) << shift) >> shift
+}
+
+/// LINEAR11: 5-bit signed exponent, 11-bit signed mantissa
+pub fn pmbus_linear11(raw: u16) -> f64 {
+    let exponent = sign_extend(raw >> 11, 5);
+    let mantissa = sign_extend(raw & 0x07FF, 11);
+
+    mantissa as f64 * 2f64.powi(exponent)
+}
+
+/// LINEAR16: unsigned mantissa, exponent from VOUT_MODE
+pub fn pmbus_linear16(raw: u16, exponent: i8) -> f64 {
+    raw as f64 * 2f64.powi(exponent as i32)
+}
+
+/// DIRECT format coefficients, X = (Y * 10^-R - b) / m
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectCoefficients {
+    pub m: i16,
+    pub b: i16,
+    pub r: i8,
+}
+
+impl DirectCoefficients {
+    pub fn decode(&self, raw: u16) -> f64 {
+        let y = raw as i16 as f64;
+        (y * 10f64.powi(-(self.r as i32)) - self.b as f64) / self.m as f64
+    }
+}
+
+/// Output voltage data format from VOUT_MODE
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoutMode {
+    Linear(i8),
+    Vid(u8),
+    Direct,
+    Ieee754Half,
+}
+
+impl VoutMode {
+    pub fn from_byte(b: u8) -> Option<VoutMode> {
+        let param = b & 0x1F;
+
+        match b >> 5 {
+            0b000 => Some(VoutMode::Linear(sign_extend(param as u16, 5) as i8)),
+            0b001 => Some(VoutMode::Vid(param)),
+            0b010 => Some(VoutMode::Direct),
+            0b011 => Some(VoutMode::Ieee754Half),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for VoutMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoutMode::Linear(e) => write!(f, "LINEAR16, exponent {}", e),
+            VoutMode::Vid(code) => write!(f, "VID, code 0x{:02X}", code),
+            VoutMode::Direct => write!(f, "DIRECT"),
+            VoutMode::Ieee754Half => write!(f, "IEEE754 half"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PmbusError {
+    Smbus(SmbusError),
+    /// The format of this reading can not be decoded
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for PmbusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PmbusError::Smbus(e) => write!(f, "{}", e),
+            PmbusError::Unsupported(e) => write!(f, "PMBus unsupported: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PmbusError {}
+
+impl From<SmbusError> for PmbusError {
+    fn from(e: SmbusError) -> Self {
+        PmbusError::Smbus(e)
+    }
+}
+
+/// PMBus device at `addr`
+///
+/// Readings are LINEAR11 unless DIRECT coefficients are set for the command,
+/// READ_VOUT follows VOUT_MODE.
+pub struct Pmbus<T: I2cDrive> {
+    pub smbus: Smbus<T>,
+    pub addr: u8,
+    pub direct: BTreeMap<u8, DirectCoefficients>,
+}
+
+impl<T: I2cDrive> Pmbus<T> {
+    pub fn new(smbus: Smbus<T>, addr: u8) -> Pmbus<T> {
+        Pmbus {
+            smbus,
+            addr,
+            direct: BTreeMap::new(),
+        }
+    }
+
+    pub fn read_word(&self, cmd: u8) -> Result<u16, PmbusError> {
+        Ok(self.smbus.read_word_data(self.addr, cmd)?)
+    }
+
+    pub fn vout_mode(&self) -> Result<VoutMode, PmbusError> {
+        let b = self.smbus.read_byte_data(self.addr, PMBUS_VOUT_MODE)?;
+        VoutMode::from_byte(b).ok_or(PmbusError::Unsupported("VOUT_MODE"))
+    }
+
+    pub fn status_word(&self) -> Result<u16, PmbusError> {
+        self.read_word(PMBUS_STATUS_WORD)
+    }
+
+    /// Decode a reading that is not VOUT related
+    pub fn read_value(&self, cmd: u8) -> Result<f64, PmbusError> {
+        let raw = self.read_word(cmd)?;
+
+        Ok(match self.direct.get(&cmd) {
+            Some(c) => c.decode(raw),
+            None => pmbus_linear11(raw),
+        })
+    }
+
+    /// unit: V
+    pub fn read_vin(&self) -> Result<f64, PmbusError> {
+        self.read_value(PMBUS_READ_VIN)
+    }
+
+    /// unit: V
+    pub fn read_vout(&self) -> Result<f64, PmbusError> {
+        let raw = self.read_word(PMBUS_READ_VOUT)?;
+
+        match (self.vout_mode()?, self.direct.get(&PMBUS_READ_VOUT)) {
+            (VoutMode::Linear(e), _) => Ok(pmbus_linear16(raw, e)),
+            (VoutMode::Direct, Some(c)) => Ok(c.decode(raw)),
+            (VoutMode::Direct, None) => Err(PmbusError::Unsupported("DIRECT without coefficients")),
+            _ => Err(PmbusError::Unsupported("VOUT_MODE")),
+        }
+    }
+
+    /// unit: A
+    pub fn read_iout(&self) -> Result<f64, PmbusError> {
+        self.read_value(PMBUS_READ_IOUT)
+    }
+
+    /// unit: °C
+    pub fn read_temperature_1(&self) -> Result<f64, PmbusError> {
+        self.read_value(PMBUS_READ_TEMPERATURE_1)
+    }
+}
+
+#[test]
+pub fn test_pmbus_decode() {
+    assert_eq!(pmbus_linear11(0xD3FE), 15.96875);
+    assert_eq!(pmbus_linear11(0x0001), 1.0);
+    assert_eq!(pmbus_linear11(0xF801), 0.5);
+    assert_eq!(pmbus_linear11(0x07FF), -1.0);
+
+    assert_eq!(VoutMode::from_byte(0x17), Some(VoutMode::Linear(-9)));
+    assert_eq!(VoutMode::from_byte(0x40), Some(VoutMode::Direct));
+    assert_eq!(pmbus_linear16(0x0A00, -9), 5.0);
+
+    let c = DirectCoefficients { m: 2, b: 0, r: -1 };
+    assert_eq!(c.decode(30), 150.0);
+
+    assert_eq!(
+        pmbus_status_word_flags(0x0841),
+        vec!["POWER_GOOD#", "OFF", "NONE_OF_THE_ABOVE"]
+    );
+}
@@ -0,0 +1,240 @@
+use std::{error::Error, fmt};
+
+use crate::{Ch347Device, I2cBus, I2cDrive, I2cError};
+
+/// Longest SMBus block
+pub const SMBUS_BLOCK_MAX: usize = 32;
+
+/// SMBus Packet Error Code, CRC-8 with polynomial x^8 + x^2 + x + 1
+pub fn smbus_pec(crc: u8, data: &[u8]) -> u8 {
+    let mut crc = crc;
+
+    for b in data {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SmbusError {
+    I2c(I2cError),
+    /// PEC byte received, PEC calculated
+    Pec(u8, u8),
+    /// Block count out of 1..=32
+    BlockLength(usize),
+}
+
+impl fmt::Display for SmbusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SmbusError::I2c(e) => write!(f, "{}", e),
+            SmbusError::Pec(got, expect) => {
+                write!(
+                    f,
+                    "SMBus PEC mismatch, got 0x{:02X} expect 0x{:02X}",
+                    got, expect
+                )
+            }
+            SmbusError::BlockLength(l) => write!(f, "Invalid SMBus block length {}", l),
+        }
+    }
+}
+
+impl Error for SmbusError {}
+
+impl From<I2cError> for SmbusError {
+    fn from(e: I2cError) -> Self {
+        SmbusError::I2c(e)
+    }
+}
+
+/// SMBus transactions on a 7-bit address, optionally with PEC
+pub struct Smbus<T: I2cDrive> {
+    pub bus: I2cBus<T>,
+    /// append PEC to writes and verify it on reads
+    pub pec: bool,
+}
+
+impl Ch347Device {
+    pub fn smbus(self) -> Smbus<Ch347Device> {
+        Smbus::new(self.i2c())
+    }
+}
+
+impl<T: I2cDrive> Smbus<T> {
+    pub fn new(bus: I2cBus<T>) -> Smbus<T> {
+        Smbus { bus, pec: false }
+    }
+
+    fn write(&self, addr: u8, data: &[u8]) -> Result<(), SmbusError> {
+        let mut wbuf = data.to_vec();
+        if self.pec {
+            wbuf.push(smbus_pec(0, &[&[addr << 1], data].concat()));
+        }
+
+        Ok(self.bus.write(addr, &wbuf)?)
+    }
+
+    /// Write `wbuf` (may be empty), then read `len` bytes and check the PEC
+    fn read(&self, addr: u8, wbuf: &[u8], len: usize) -> Result<Vec<u8>, SmbusError> {
+        let mut rbuf = vec![0; len + self.pec as usize];
+
+        if wbuf.is_empty() {
+            self.bus.read(addr, &mut rbuf)?;
+        } else {
+            self.bus.write_read(addr, wbuf, &mut rbuf)?;
+        }
+
+        if self.pec {
+            let got = rbuf.pop().unwrap();
+            let mut crc = 0;
+            if !wbuf.is_empty() {
+                crc = smbus_pec(crc, &[&[addr << 1], wbuf].concat());
+            }
+            let expect = smbus_pec(crc, &[&[(addr << 1) | 1], rbuf.as_slice()].concat());
+
+            if got != expect {
+                return Err(SmbusError::Pec(got, expect));
+            }
+        }
+
+        Ok(rbuf)
+    }
+
+    pub fn quick(&self, addr: u8) -> Result<(), SmbusError> {
+        Ok(self.bus.write(addr, &[])?)
+    }
+
+    pub fn send_byte(&self, addr: u8, value: u8) -> Result<(), SmbusError> {
+        self.write(addr, &[value])
+    }
+
+    pub fn receive_byte(&self, addr: u8) -> Result<u8, SmbusError> {
+        Ok(self.read(addr, &[], 1)?[0])
+    }
+
+    pub fn write_byte_data(&self, addr: u8, cmd: u8, value: u8) -> Result<(), SmbusError> {
+        self.write(addr, &[cmd, value])
+    }
+
+    pub fn read_byte_data(&self, addr: u8, cmd: u8) -> Result<u8, SmbusError> {
+        Ok(self.read(addr, &[cmd], 1)?[0])
+    }
+
+    /// SMBus words are little endian
+    pub fn write_word_data(&self, addr: u8, cmd: u8, value: u16) -> Result<(), SmbusError> {
+        let v = value.to_le_bytes();
+        self.write(addr, &[cmd, v[0], v[1]])
+    }
+
+    pub fn read_word_data(&self, addr: u8, cmd: u8) -> Result<u16, SmbusError> {
+        let r = self.read(addr, &[cmd], 2)?;
+        Ok(u16::from_le_bytes([r[0], r[1]]))
+    }
+
+    /// Write `value`, read back the reply word with a repeated start
+    pub fn process_call(&self, addr: u8, cmd: u8, value: u16) -> Result<u16, SmbusError> {
+        let v = value.to_le_bytes();
+        let r = self.read(addr, &[cmd, v[0], v[1]], 2)?;
+        Ok(u16::from_le_bytes([r[0], r[1]]))
+    }
+
+    pub fn block_write(&self, addr: u8, cmd: u8, data: &[u8]) -> Result<(), SmbusError> {
+        if data.is_empty() || (data.len() > SMBUS_BLOCK_MAX) {
+            return Err(SmbusError::BlockLength(data.len()));
+        }
+
+        let mut wbuf = vec![cmd, data.len() as u8];
+        wbuf.extend_from_slice(data);
+        self.write(addr, &wbuf)
+    }
+
+    /// The CH347 must know the read length in advance, so the byte count is
+    /// fetched first and the command is issued again with the exact length
+    pub fn block_read(&self, addr: u8, cmd: u8) -> Result<Vec<u8>, SmbusError> {
+        let mut count = [0];
+        self.bus.write_read(addr, &[cmd], &mut count)?;
+
+        let count = count[0] as usize;
+        if (count == 0) || (count > SMBUS_BLOCK_MAX) {
+            return Err(SmbusError::BlockLength(count));
+        }
+
+        let r = self.read(addr, &[cmd], count + 1)?;
+        if r[0] as usize != count {
+            return Err(SmbusError::BlockLength(r[0] as usize));
+        }
+
+        Ok(r[1..].to_vec())
+    }
+}
+
+#[test]
+pub fn test_smbus() {
+    use std::cell::{Cell, RefCell};
+
+    // CRC-8/SMBUS check value
+    assert_eq!(smbus_pec(0, b"123456789"), 0xF4);
+
+    /// Device at 0x0B, every register reads as a 3 byte block, the last
+    /// byte of a longer read is the PEC
+    struct MockGauge {
+        last_write: RefCell<Vec<u8>>,
+        corrupt: Cell<bool>,
+    }
+
+    impl I2cDrive for MockGauge {
+        fn i2c_stream(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+            if (wbuf[0] >> 1) != 0x0B {
+                return Err("nack");
+            }
+            *self.last_write.borrow_mut() = wbuf.to_vec();
+
+            let reply = [3, 0x10, 0x20, 0x30];
+            if rbuf.is_empty() {
+                return Ok(());
+            }
+            if rbuf.len() == 1 {
+                rbuf[0] = reply[0];
+                return Ok(());
+            }
+
+            let n = rbuf.len() - 1;
+            rbuf[..n].copy_from_slice(&reply[..n]);
+            rbuf[n] = smbus_pec(0, &[wbuf, &[0x17], &reply[..n]].concat());
+            if self.corrupt.get() {
+                rbuf[0] ^= 0x01;
+            }
+            Ok(())
+        }
+    }
+
+    let mut smbus = Smbus::new(I2cBus::new(MockGauge {
+        last_write: RefCell::new(Vec::new()),
+        corrupt: Cell::new(false),
+    }));
+    smbus.pec = true;
+
+    assert_eq!(smbus.block_read(0x0B, 0x20), Ok(vec![0x10, 0x20, 0x30]));
+    assert_eq!(smbus.read_word_data(0x0B, 0x09), Ok(0x1003));
+
+    smbus.write_word_data(0x0B, 0x01, 0x1234).unwrap();
+    let w = smbus.bus.drive.last_write.borrow().clone();
+    assert_eq!(&w[..4], &[0x16, 0x01, 0x34, 0x12]);
+    assert_eq!(w[4], smbus_pec(0, &w[..4]));
+
+    // a corrupted PEC is reported
+    smbus.bus.drive.corrupt.set(true);
+    assert!(matches!(
+        smbus.read_word_data(0x0B, 0x09),
+        Err(SmbusError::Pec(_, _))
+    ));
+}
@@ -0,0 +1,247 @@
+use std::cell::Cell;
+use std::error::Error;
+
+use clap::ValueEnum;
+
+use crate::ch347lib::*;
+use crate::spi_flash::SpiDrive;
+use crate::windows::basetsd::*;
+
+/// Clock polarity / phase, same numbering as the SPI mode of most datasheets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SpiMode {
+    Mode0 = 0x00,
+    Mode1 = 0x01,
+    Mode2 = 0x02,
+    Mode3 = 0x03,
+}
+
+impl SpiMode {
+    pub fn from_byte(data: u8) -> Option<SpiMode> {
+        match data {
+            0 => Some(SpiMode::Mode0),
+            1 => Some(SpiMode::Mode1),
+            2 => Some(SpiMode::Mode2),
+            3 => Some(SpiMode::Mode3),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BitOrder {
+    LsbFirst = 0x00,
+    MsbFirst = 0x01,
+}
+
+/// General SPI master on a CH347, for peripherals other than flash chips
+pub struct Ch347Spi {
+    dev: Ch347Device,
+
+    /// chip select was asserted by `cs_assert`, transfers must not touch it
+    cs_held: Cell<bool>,
+
+    /// transfers assert and release chip select by themselves
+    auto_cs: Cell<bool>,
+}
+
+impl Ch347Device {
+    pub fn spi(mut self) -> Result<Ch347Spi, Box<dyn Error>> {
+        self.check_parallel()?;
+        self.reload_spi_config()?;
+
+        // CH347SPI_ChangeCS works on the chip select given to CH347SPI_Init
+        let cs = self.get_chip_select();
+        self.change_spi_raw_config(|spi_cfg| spi_cfg.chip_select = cs.to_raw())?;
+
+        Ok(Ch347Spi {
+            dev: self,
+            cs_held: Cell::new(false),
+            auto_cs: Cell::new(true),
+        })
+    }
+}
+
+impl Ch347Spi {
+    pub fn device(&self) -> &Ch347Device {
+        &self.dev
+    }
+
+    pub fn set_mode(&mut self, mode: SpiMode) -> Result<(), &'static str> {
+        self.dev
+            .change_spi_raw_config(|spi_cfg| spi_cfg.mode = mode as UCHAR)
+    }
+
+    pub fn set_bit_order(&mut self, order: BitOrder) -> Result<(), &'static str> {
+        self.dev
+            .change_spi_raw_config(|spi_cfg| spi_cfg.byte_order = order as UCHAR)
+    }
+
+    pub fn set_clock(&mut self, clock: SpiClockLevel) -> Result<(), &'static str> {
+        self.dev
+            .change_spi_raw_config(|spi_cfg| spi_cfg.clock = clock.to_byte())
+    }
+
+    pub fn set_chip_select(
+        &mut self,
+        cs: ChipSelect,
+        active_high: bool,
+    ) -> Result<(), &'static str> {
+        self.dev.set_chip_select(cs, active_high);
+        self.dev.apply_spi_config()
+    }
+
+    /// Chip select timing of the current chip select
+    ///
+    /// * `auto_deactive` - release chip select after every transfer
+    /// * `active_delay` - delay after asserting chip select, unit: us
+    /// * `deactive_delay` - delay after releasing chip select, unit: us
+    pub fn set_cs_timing(
+        &self,
+        auto_deactive: bool,
+        active_delay: u16,
+        deactive_delay: u16,
+    ) -> Result<(), &'static str> {
+        // low byte/half-word is CS0, high byte/half-word is CS1
+        let shift = match self.dev.get_chip_select() {
+            ChipSelect::CS0 => 0,
+            ChipSelect::CS1 => 1,
+        };
+
+        unsafe {
+            if CH347SPI_SetChipSelect(
+                self.dev.get_dev_index(),
+                0x01 << (shift * 8),
+                0x00,
+                (auto_deactive as ULONG) << (shift * 16),
+                (active_delay as ULONG) << (shift * 16),
+                (deactive_delay as ULONG) << (shift * 16),
+            ) == 0
+            {
+                return Err("CH347SPI_SetChipSelect Fail");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Assert chip select and keep it until `cs_release`, for transactions
+    /// made of several transfers
+    pub fn cs_assert(&self) -> Result<(), &'static str> {
+        unsafe {
+            if CH347SPI_ChangeCS(self.dev.get_dev_index(), 1) == 0 {
+                return Err("CH347SPI_ChangeCS Fail");
+            }
+        }
+
+        self.cs_held.set(true);
+        Ok(())
+    }
+
+    pub fn cs_release(&self) -> Result<(), &'static str> {
+        unsafe {
+            if CH347SPI_ChangeCS(self.dev.get_dev_index(), 0) == 0 {
+                return Err("CH347SPI_ChangeCS Fail");
+            }
+        }
+
+        self.cs_held.set(false);
+        Ok(())
+    }
+
+    /// When disabled, transfers leave the chip select lines alone, e.g. when
+    /// the peripheral's chip select is driven from a GPIO
+    pub fn set_auto_cs(&self, enable: bool) {
+        self.auto_cs.set(enable);
+    }
+
+    fn raw_cs(&self) -> ULONG {
+        if self.cs_held.get() || !self.auto_cs.get() {
+            return 0x00;
+        }
+
+        self.dev.get_chip_select().to_raw()
+    }
+
+    /// Write only, MISO is ignored
+    pub fn write(&self, wbuf: &[u8]) -> Result<(), &'static str> {
+        let mut iobuf = wbuf.to_vec();
+
+        unsafe {
+            if CH347SPI_Write(
+                self.dev.get_dev_index(),
+                self.raw_cs(),
+                iobuf.len() as ULONG,
+                iobuf.len() as ULONG,
+                iobuf.as_mut_ptr() as *mut libc::c_void,
+            ) == 0
+            {
+                return Err("CH347SPI_Write Fail");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read only, MOSI outputs `SpiConfig.out_default_data`
+    pub fn read(&self, rbuf: &mut [u8]) -> Result<(), &'static str> {
+        self.write_then_read(&[], rbuf)
+    }
+
+    /// Full duplex, `iobuf` is sent on MOSI and replaced by MISO
+    pub fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+        unsafe {
+            if CH347StreamSPI4(
+                self.dev.get_dev_index(),
+                self.raw_cs(),
+                iobuf.len() as ULONG,
+                iobuf.as_mut_ptr() as *mut libc::c_void,
+            ) == 0
+            {
+                return Err("CH347StreamSPI4 Fail");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write `wbuf`, then read `rbuf.len()` bytes within the same chip select
+    pub fn write_then_read(&self, wbuf: &[u8], rbuf: &mut [u8]) -> Result<(), &'static str> {
+        let mut iobuf = vec![0; wbuf.len().max(rbuf.len())];
+        iobuf[..wbuf.len()].copy_from_slice(wbuf);
+
+        let mut read_len = rbuf.len() as ULONG;
+
+        unsafe {
+            if CH347SPI_Read(
+                self.dev.get_dev_index(),
+                self.raw_cs(),
+                wbuf.len() as ULONG,
+                &mut read_len,
+                iobuf.as_mut_ptr() as *mut libc::c_void,
+            ) == 0
+            {
+                return Err("CH347SPI_Read Fail");
+            }
+        }
+
+        rbuf.copy_from_slice(&iobuf[..rbuf.len()]);
+        Ok(())
+    }
+}
+
+impl SpiDrive for Ch347Spi {
+    fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+        Ch347Spi::transfer(self, iobuf)
+    }
+
+    fn write_after_read(
+        &self,
+        write_len: u32,
+        read_len: u32,
+        iobuf: &mut [u8],
+    ) -> Result<(), &'static str> {
+        let wbuf = iobuf[..(write_len as usize)].to_vec();
+        self.write_then_read(&wbuf, &mut iobuf[..(read_len as usize)])
+    }
+}
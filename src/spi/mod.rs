@@ -0,0 +1,3 @@
+mod ch347_spi;
+
+pub use ch347_spi::*;
@@ -1,4 +1,5 @@
 use super::*;
+use serde::{Deserialize, Serialize};
 use std::{error::Error, fmt, ops::Range};
 
 pub enum SpiFlashCmd {
@@ -46,6 +47,35 @@ pub enum WriteEvent {
 
 pub type WriteEventFn = fn(e: WriteEvent);
 
+pub enum ReadEvent {
+    /// sector address, sector size
+    Block(usize, usize),
+    /// sector address, retry count
+    Retry(usize, usize),
+    /// sector address, unstable byte count
+    Unstable(usize, usize),
+}
+
+/// A byte that never read back the same value across passes
+#[derive(Debug)]
+pub struct UnstableByte {
+    pub addr: u32,
+    /// every value seen at this address, in read order
+    pub values: Vec<u8>,
+}
+
+impl fmt::Display for UnstableByte {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:04X}_{:04X} {:02X?}",
+            self.addr >> 16,
+            self.addr & 0xFFFF,
+            self.values
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct StatusRes {
     pub busy: bool,
@@ -120,6 +150,41 @@ impl<T: SpiDrive + 'static> SpiFlash<T> {
         return Ok(chip_info);
     }
 
+    /// Check that the JEDEC ID and the first `sample_len` bytes read back
+    /// identically several times in a row at the current clock. The ID
+    /// does not have to be known, only all 00 or FF is rejected.
+    pub fn is_stable(&self, sample_len: usize) -> bool {
+        const TIMES: usize = 3;
+
+        let mut jedec_ids = Vec::new();
+        for _ in 0..TIMES {
+            let mut wbuf: [u8; 4] = [SpiFlashCmd::JedecId.into(), 0x00, 0x00, 0x00];
+            if self.drive.transfer(&mut wbuf).is_err() {
+                return false;
+            }
+            jedec_ids.push(wbuf);
+        }
+
+        if jedec_ids.iter().any(|i| i.ne(&jedec_ids[0])) {
+            return false;
+        }
+        let jedec_id = &jedec_ids[0][1..4];
+        if jedec_id.iter().all(|&b| b == 0x00) || jedec_id.iter().all(|&b| b == 0xFF) {
+            return false;
+        }
+
+        let mut samples = Vec::new();
+        for _ in 0..TIMES {
+            let mut rbuf = vec![0; sample_len];
+            if self.try_read(0, &mut rbuf).is_err() {
+                return false;
+            }
+            samples.push(rbuf);
+        }
+
+        samples.iter().all(|i| i.eq(&samples[0]))
+    }
+
     pub fn read_uuid(&self, vendor: &Vendor) -> Result<Vec<u8>, &'static str> {
         return vendor.read_uid(self);
     }
@@ -142,15 +207,103 @@ impl<T: SpiDrive + 'static> SpiFlash<T> {
         return Ok(chip_info);
     }
 
+    /// Same as `try_read` but transfer errors are ignored
     pub fn read(&self, addr: u32, buf: &mut [u8]) {
+        let _ = self.try_read(addr, buf);
+    }
+
+    /// Read `buf.len()` bytes from `addr`. Buffers shorter than the 4 byte
+    /// command header go through a scratch buffer
+    pub fn try_read(&self, addr: u32, buf: &mut [u8]) -> Result<(), &'static str> {
+        if buf.len() < 4 {
+            let mut rbuf = [0; 4];
+            self.try_read(addr, &mut rbuf)?;
+            buf.copy_from_slice(&rbuf[..buf.len()]);
+            return Ok(());
+        }
+
         buf[0] = SpiFlashCmd::ReadData.into();
         buf[1] = (addr >> 16) as u8;
         buf[2] = (addr >> 8) as u8;
         buf[3] = (addr) as u8;
 
-        if let Err(_) = self.drive.write_after_read(4, buf.len() as u32, buf) {
-            return;
+        self.drive.write_after_read(4, buf.len() as u32, buf)
+    }
+
+    /// Read `buf.len()` bytes from `addr`, reading every sector `passes` times.
+    ///
+    /// A sector is accepted once its last `passes` reads are identical. A
+    /// sector that disagrees is re-read up to `retries` more times; if it
+    /// still cannot settle, the majority value of each byte is kept and the
+    /// bytes that changed between reads are returned. A failed transfer
+    /// aborts the read.
+    pub fn read_consistent<F>(
+        &self,
+        mut cbk: F,
+        addr: u32,
+        buf: &mut [u8],
+        passes: usize,
+        retries: usize,
+    ) -> Result<Vec<UnstableByte>, &'static str>
+    where
+        F: FnMut(ReadEvent),
+    {
+        const BLOCK_SIZE: usize = 4096;
+
+        let passes = passes.max(1);
+        let mut unstable = Vec::new();
+
+        for i in (0..buf.len()).step_by(BLOCK_SIZE) {
+            let block_len = BLOCK_SIZE.min(buf.len() - i);
+            let block_addr = addr + i as u32;
+
+            let mut reads: Vec<Vec<u8>> = Vec::new();
+            for _ in 0..passes {
+                let mut rbuf = vec![0; block_len];
+                self.try_read(block_addr, &mut rbuf)?;
+                reads.push(rbuf);
+            }
+
+            let mut retry_cnt = 0;
+            loop {
+                let last = &reads[(reads.len() - passes)..];
+                if last.iter().all(|r| r.eq(&last[0])) {
+                    buf[i..(i + block_len)].copy_from_slice(&last[0]);
+                    break;
+                }
+
+                if retry_cnt >= retries {
+                    let mut unstable_cnt = 0;
+
+                    for x in 0..block_len {
+                        let values: Vec<u8> = reads.iter().map(|r| r[x]).collect();
+                        buf[i + x] = majority_byte(&values);
+
+                        if values.iter().any(|&v| v != values[0]) {
+                            unstable_cnt += 1;
+                            unstable.push(UnstableByte {
+                                addr: block_addr + x as u32,
+                                values,
+                            });
+                        }
+                    }
+
+                    cbk(ReadEvent::Unstable(block_addr as usize, unstable_cnt));
+                    break;
+                }
+
+                retry_cnt += 1;
+                cbk(ReadEvent::Retry(block_addr as usize, retry_cnt));
+
+                let mut rbuf = vec![0; block_len];
+                self.try_read(block_addr, &mut rbuf)?;
+                reads.push(rbuf);
+            }
+
+            cbk(ReadEvent::Block(block_addr as usize, block_len));
         }
+
+        Ok(unstable)
     }
 
     pub fn read_status(&self) -> Result<StatusRes, &'static str> {
@@ -256,6 +409,123 @@ impl<T: SpiDrive + 'static> SpiFlash<T> {
     }
 }
 
+fn majority_byte(values: &[u8]) -> u8 {
+    let mut counts = [0usize; 256];
+    for &v in values {
+        counts[v as usize] += 1;
+    }
+
+    // ties keep the value that was read first
+    let mut ret = values[0];
+    for &v in values {
+        if counts[v as usize] > counts[ret as usize] {
+            ret = v;
+        }
+    }
+
+    ret
+}
+
+#[test]
+pub fn test_read_consistent() {
+    use std::cell::Cell;
+
+    // byte 0x1234 toggles on every read, everything else reads back 0xA5
+    struct FlakyDrive {
+        read_cnt: Cell<u8>,
+        fail_at: u8,
+    }
+
+    impl SpiDrive for FlakyDrive {
+        fn write_after_read(&self, _: u32, _: u32, iobuf: &mut [u8]) -> Result<(), &'static str> {
+            let addr = ((iobuf[1] as usize) << 16) | ((iobuf[2] as usize) << 8) | iobuf[3] as usize;
+            let cnt = self.read_cnt.get();
+            self.read_cnt.set(cnt.wrapping_add(1));
+            if cnt == self.fail_at {
+                return Err("USB fail");
+            }
+
+            iobuf.fill(0xA5);
+            if (addr..(addr + iobuf.len())).contains(&0x1234) {
+                iobuf[0x1234 - addr] = cnt & 0x01;
+            }
+            Ok(())
+        }
+
+        fn transfer(&self, _: &mut [u8]) -> Result<(), &'static str> {
+            Ok(())
+        }
+    }
+
+    let mut flash = SpiFlash::new(FlakyDrive {
+        read_cnt: Cell::new(0),
+        fail_at: 0xFF,
+    });
+
+    let mut buf = vec![0; 0x3000];
+    let unstable = flash.read_consistent(|_| {}, 0, &mut buf, 2, 3).unwrap();
+
+    assert_eq!(unstable.len(), 1);
+    assert_eq!(unstable[0].addr, 0x1234);
+    assert_eq!(unstable[0].values.len(), 5);
+    assert!(buf
+        .iter()
+        .enumerate()
+        .all(|(k, &v)| k == 0x1234 || v == 0xA5));
+
+    // a 2 byte tail block
+    let mut buf = vec![0; 0x1002];
+    flash
+        .read_consistent(|_| {}, 0x2000, &mut buf, 2, 0)
+        .unwrap();
+    assert!(buf.iter().all(|&v| v == 0xA5));
+
+    // a failed transfer is not taken as data
+    flash.drive.fail_at = flash.drive.read_cnt.get() + 1;
+    let mut buf = vec![0; 0x100];
+    assert!(flash.read_consistent(|_| {}, 0, &mut buf, 3, 0).is_err());
+}
+
+#[test]
+pub fn test_is_stable() {
+    use std::cell::Cell;
+
+    // answers a JEDEC ID that is not in the model table
+    struct IdDrive {
+        id: [u8; 3],
+        fail: Cell<bool>,
+    }
+
+    impl SpiDrive for IdDrive {
+        fn write_after_read(&self, _: u32, _: u32, iobuf: &mut [u8]) -> Result<(), &'static str> {
+            if self.fail.get() {
+                return Err("USB fail");
+            }
+            iobuf.fill(0x5A);
+            Ok(())
+        }
+
+        fn transfer(&self, iobuf: &mut [u8]) -> Result<(), &'static str> {
+            iobuf[1..4].copy_from_slice(&self.id);
+            Ok(())
+        }
+    }
+
+    let mut flash = SpiFlash::new(IdDrive {
+        id: [0x12, 0x34, 0x56],
+        fail: Cell::new(false),
+    });
+    assert!(flash.is_stable(2));
+    assert!(flash.is_stable(256));
+
+    flash.drive.fail.set(true);
+    assert!(!flash.is_stable(256));
+
+    flash.drive.fail.set(false);
+    flash.drive.id = [0xFF; 3];
+    assert!(!flash.is_stable(256));
+}
+
 pub type RegReader = fn(spi_flash: &SpiFlash<dyn SpiDrive>) -> Result<RegReadRet, &'static str>;
 pub type RegWriter =
     fn(spi_flash: &SpiFlash<dyn SpiDrive>, buf: &[u8]) -> Result<(), Box<dyn Error>>;
@@ -298,10 +568,13 @@ pub struct RegisterItem {
     pub access: RegisterAccess,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RegisterAccess {
+    #[serde(rename = "read_only", alias = "ro")]
     ReadOnly,
+    #[serde(rename = "read_write", alias = "rw")]
     ReadWrite,
+    #[serde(rename = "read_write_otp", alias = "otp")]
     ReadWriteOTP,
 }
 
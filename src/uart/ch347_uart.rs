@@ -0,0 +1,373 @@
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use super::UartConfig;
+use crate::{CH347TransType, Ch347Device};
+
+#[cfg(target_os = "windows")]
+use crate::{
+    windows::basetsd::*, CH347Uart_Init, CH347Uart_Read, CH347Uart_SetTimeout, CH347Uart_Write,
+};
+
+/// UART interface of a CH347, opened with `Ch347Uart::open` or
+/// `Ch347Device::new_serial(..)?.uart(..)`
+pub struct Ch347Uart {
+    dev: Ch347Device,
+    config: UartConfig,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl Ch347Device {
+    pub fn uart(self, config: &UartConfig) -> io::Result<Ch347Uart> {
+        if !matches!(self.ts_type(), CH347TransType::Serial) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Not a UART interface",
+            ));
+        }
+
+        let uart = Ch347Uart {
+            dev: self,
+            config: *config,
+            read_timeout: None,
+            write_timeout: None,
+        };
+        uart.configure()?;
+        uart.apply_timeouts()?;
+
+        Ok(uart)
+    }
+}
+
+fn invalid_input(e: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+impl Ch347Uart {
+    /// Open UART `index` as listed by `enum_uart_device`
+    pub fn open(index: u32, config: &UartConfig) -> io::Result<Ch347Uart> {
+        Ch347Device::open_serial(index)?.uart(config)
+    }
+
+    pub fn config(&self) -> &UartConfig {
+        &self.config
+    }
+
+    /// Change the line settings, the old ones stay if the new are rejected
+    pub fn set_config(&mut self, config: &UartConfig) -> io::Result<()> {
+        let old = self.config;
+
+        self.config = *config;
+        if let Err(e) = self.configure() {
+            self.config = old;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub fn set_baud(&mut self, baud: u32) -> io::Result<()> {
+        self.set_config(&UartConfig {
+            baud,
+            ..self.config
+        })
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// `None` blocks until data arrives, a read that times out fails with
+    /// `ErrorKind::TimedOut`
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.read_timeout = timeout;
+        self.apply_timeouts()
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.write_timeout = timeout;
+        self.apply_timeouts()
+    }
+
+    pub fn device(&self) -> &Ch347Device {
+        &self.dev
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn timeout_ms(timeout: Option<Duration>) -> libc::c_int {
+    match timeout {
+        Some(t) => t.as_millis().min(libc::c_int::MAX as u128) as libc::c_int,
+        None => -1,
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Ch347Uart {
+    fn fd(&self) -> i32 {
+        self.dev.get_dev_index() as i32
+    }
+
+    fn configure(&self) -> io::Result<()> {
+        let mut tio: libc::termios2 = unsafe { std::mem::zeroed() };
+
+        if unsafe { libc::ioctl(self.fd(), libc::TCGETS2, &mut tio) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        super::apply_termios(&mut tio, &self.config).map_err(invalid_input)?;
+        if unsafe { libc::ioctl(self.fd(), libc::TCSETS2, &tio) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    fn modem_line(&self, line: libc::c_int, active: bool) -> io::Result<()> {
+        let req = if active {
+            libc::TIOCMBIS
+        } else {
+            libc::TIOCMBIC
+        };
+
+        if unsafe { libc::ioctl(self.fd(), req, &line) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub fn set_dtr(&mut self, active: bool) -> io::Result<()> {
+        self.modem_line(libc::TIOCM_DTR, active)
+    }
+
+    /// Ignored by the hardware while RTS/CTS flow control is on
+    pub fn set_rts(&mut self, active: bool) -> io::Result<()> {
+        self.modem_line(libc::TIOCM_RTS, active)
+    }
+
+    /// Hold TX low for `duration`
+    pub fn send_break(&mut self, duration: Duration) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.fd(), libc::TIOCSBRK) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        std::thread::sleep(duration);
+        if unsafe { libc::ioctl(self.fd(), libc::TIOCCBRK) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Timeouts are passed to poll() on every read and write
+    fn apply_timeouts(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Wait until the tty is ready for `events`
+    fn poll(&self, events: libc::c_short, timeout: Option<Duration>) -> io::Result<()> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd(),
+            events,
+            revents: 0,
+        };
+
+        match unsafe { libc::poll(&mut pfd, 1, timeout_ms(timeout)) } {
+            0 => Err(io::Error::new(io::ErrorKind::TimedOut, "UART timeout")),
+            n if n < 0 => Err(io::Error::last_os_error()),
+            _ if pfd.revents & (libc::POLLERR | libc::POLLHUP | libc::POLLNVAL) != 0 => Err(
+                io::Error::new(io::ErrorKind::BrokenPipe, "UART disconnected"),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Read for Ch347Uart {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.poll(libc::POLLIN, self.read_timeout)?;
+        let n = unsafe { libc::read(self.fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(n as usize)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Write for Ch347Uart {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.poll(libc::POLLOUT, self.write_timeout)?;
+        let n = unsafe { libc::write(self.fd(), buf.as_ptr() as *const libc::c_void, buf.len()) };
+        if n < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(n as usize)
+    }
+
+    /// Wait until everything has been sent
+    fn flush(&mut self) -> io::Result<()> {
+        if unsafe { libc::tcdrain(self.fd()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn timeout_ms(timeout: Option<Duration>) -> ULONG {
+    match timeout {
+        Some(t) => t.as_millis().min(ULONG::MAX as u128 - 1) as ULONG,
+        None => ULONG::MAX,
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Ch347Uart {
+    fn configure(&self) -> io::Result<()> {
+        use super::{FlowControl, Parity, StopBits};
+
+        self.config.check().map_err(invalid_input)?;
+        if self.config.flow_control != FlowControl::None {
+            return Err(invalid_input("Flow control is not supported"));
+        }
+
+        let parity = match self.config.parity {
+            Parity::None => 0,
+            Parity::Odd => 1,
+            Parity::Even => 2,
+            Parity::Mark => 3,
+            Parity::Space => 4,
+        };
+        let stop_bits = match self.config.stop_bits {
+            StopBits::One => 0,
+            StopBits::OnePointFive => 1,
+            StopBits::Two => 2,
+        };
+
+        // byte timeout in 100us, one character time is enough above 9600 baud
+        if unsafe {
+            CH347Uart_Init(
+                self.dev.get_dev_index(),
+                self.config.baud as ULONG,
+                self.config.data_bits,
+                parity,
+                stop_bits,
+                10,
+            )
+        } == 0
+        {
+            return Err(io::Error::new(io::ErrorKind::Other, "CH347Uart_Init Fail"));
+        }
+
+        Ok(())
+    }
+
+    /// The vendor library has no modem line control
+    pub fn set_dtr(&mut self, _active: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "DTR control is not supported",
+        ))
+    }
+
+    pub fn set_rts(&mut self, _active: bool) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "RTS control is not supported",
+        ))
+    }
+
+    pub fn send_break(&mut self, _duration: Duration) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Break is not supported",
+        ))
+    }
+
+    fn apply_timeouts(&self) -> io::Result<()> {
+        if unsafe {
+            CH347Uart_SetTimeout(
+                self.dev.get_dev_index(),
+                timeout_ms(self.write_timeout),
+                timeout_ms(self.read_timeout),
+            )
+        } == 0
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "CH347Uart_SetTimeout Fail",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Read for Ch347Uart {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut len = buf.len() as ULONG;
+        if unsafe {
+            CH347Uart_Read(
+                self.dev.get_dev_index(),
+                buf.as_mut_ptr() as PVOID,
+                &mut len,
+            )
+        } == 0
+        {
+            return Err(io::Error::new(io::ErrorKind::Other, "CH347Uart_Read Fail"));
+        }
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "UART timeout"));
+        }
+
+        Ok(len as usize)
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Write for Ch347Uart {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut len = buf.len() as ULONG;
+        if unsafe { CH347Uart_Write(self.dev.get_dev_index(), buf.as_ptr() as PVOID, &mut len) }
+            == 0
+        {
+            return Err(io::Error::new(io::ErrorKind::Other, "CH347Uart_Write Fail"));
+        }
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "UART timeout"));
+        }
+
+        Ok(len as usize)
+    }
+
+    /// CH347Uart_Write returns once the data is sent
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,11 @@
+mod ch347_uart;
+mod uart_config;
+mod uart_one_wire;
+#[cfg(target_os = "linux")]
+mod uart_tty;
+
+pub use ch347_uart::*;
+pub use uart_config::*;
+pub use uart_one_wire::*;
+#[cfg(target_os = "linux")]
+pub use uart_tty::*;
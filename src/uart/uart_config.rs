@@ -0,0 +1,181 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    Mark,
+    Space,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    /// only valid with 5 data bits on Linux
+    OnePointFive,
+    Two,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowControl {
+    None,
+    /// RTS/CTS
+    Hardware,
+    /// XON/XOFF
+    Software,
+}
+
+/// Serial line settings, defaults to 115200 8N1 without flow control
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UartConfig {
+    pub baud: u32,
+    /// 5~8
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl Default for UartConfig {
+    fn default() -> Self {
+        UartConfig {
+            baud: 115200,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+impl UartConfig {
+    pub fn new(baud: u32) -> UartConfig {
+        UartConfig {
+            baud,
+            ..Default::default()
+        }
+    }
+
+    pub fn check(&self) -> Result<(), &'static str> {
+        if self.baud == 0 {
+            return Err("Baud rate must not be 0");
+        }
+        if !(5..=8).contains(&self.data_bits) {
+            return Err("Data bits must be 5~8");
+        }
+
+        Ok(())
+    }
+
+    /// Apply a frame format like `8N1`, `7E2` or `5N1.5`
+    pub fn set_frame(&mut self, frame: &str) -> Result<(), Box<dyn Error>> {
+        let mut chars = frame.chars();
+
+        self.data_bits = chars
+            .next()
+            .and_then(|c| c.to_digit(10))
+            .ok_or("Frame format must start with the data bits")? as u8;
+        self.parity = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            Some('N') => Parity::None,
+            Some('O') => Parity::Odd,
+            Some('E') => Parity::Even,
+            Some('M') => Parity::Mark,
+            Some('S') => Parity::Space,
+            _ => return Err("Parity must be one of N, O, E, M, S".into()),
+        };
+        self.stop_bits = match chars.as_str() {
+            "1" => StopBits::One,
+            "1.5" => StopBits::OnePointFive,
+            "2" => StopBits::Two,
+            _ => return Err("Stop bits must be 1, 1.5 or 2".into()),
+        };
+
+        self.check()?;
+        Ok(())
+    }
+}
+
+/// `115200`, `115200,8N1` or `9600,7E1,rtscts`
+impl FromStr for UartConfig {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',').map(str::trim);
+
+        let mut config = UartConfig::new(parts.next().unwrap_or_default().parse()?);
+        if let Some(frame) = parts.next() {
+            config.set_frame(frame)?;
+        }
+        if let Some(flow) = parts.next() {
+            config.flow_control = match flow.to_ascii_lowercase().as_str() {
+                "none" => FlowControl::None,
+                "rtscts" | "hw" => FlowControl::Hardware,
+                "xonxoff" | "sw" => FlowControl::Software,
+                _ => return Err(format!("Unknown flow control: {}", flow).into()),
+            };
+        }
+        if parts.next().is_some() {
+            return Err("Too many serial settings".into());
+        }
+
+        config.check()?;
+        Ok(config)
+    }
+}
+
+impl fmt::Display for UartConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{}{}{}",
+            self.baud,
+            self.data_bits,
+            match self.parity {
+                Parity::None => 'N',
+                Parity::Odd => 'O',
+                Parity::Even => 'E',
+                Parity::Mark => 'M',
+                Parity::Space => 'S',
+            },
+            match self.stop_bits {
+                StopBits::One => "1",
+                StopBits::OnePointFive => "1.5",
+                StopBits::Two => "2",
+            }
+        )?;
+
+        match self.flow_control {
+            FlowControl::None => Ok(()),
+            FlowControl::Hardware => write!(f, ",rtscts"),
+            FlowControl::Software => write!(f, ",xonxoff"),
+        }
+    }
+}
+
+#[test]
+pub fn test_uart_config() {
+    assert_eq!(
+        "115200".parse::<UartConfig>().unwrap(),
+        UartConfig::default()
+    );
+
+    let config: UartConfig = "9600, 7E2, rtscts".parse().unwrap();
+    assert_eq!(config.baud, 9600);
+    assert_eq!(config.data_bits, 7);
+    assert_eq!(config.parity, Parity::Even);
+    assert_eq!(config.stop_bits, StopBits::Two);
+    assert_eq!(config.flow_control, FlowControl::Hardware);
+    assert_eq!(config.to_string(), "9600,7E2,rtscts");
+
+    let config: UartConfig = "2000000,5s1.5".parse().unwrap();
+    assert_eq!(config.to_string(), "2000000,5S1.5");
+
+    assert!("0".parse::<UartConfig>().is_err());
+    assert!("9600,9N1".parse::<UartConfig>().is_err());
+    assert!("9600,8X1".parse::<UartConfig>().is_err());
+    assert!("9600,8N3".parse::<UartConfig>().is_err());
+    assert!("9600,8N1,dtr".parse::<UartConfig>().is_err());
+}
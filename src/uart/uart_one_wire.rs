@@ -0,0 +1,238 @@
+use std::cell::RefCell;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use super::{Ch347Uart, UartConfig};
+use crate::OneWireDrive;
+
+// Every 1-Wire time slot is one UART character on a line where TXD drives
+// DQ through an open drain buffer (or a diode) and RXD reads DQ back. At
+// 9600 baud the start bit and four low data bits of 0xF0 make a 520us
+// reset pulse, a presence pulse shows up in the echo. At 115200 baud 0x00
+// is a write 0 slot, 0xFF a write 1 or read slot whose start bit is the
+// 8.7us low pulse, a device answering 0 stretches it into the echo.
+
+const RESET_BAUD: u32 = 9600;
+const SLOT_BAUD: u32 = 115200;
+const RESET_BYTE: u8 = 0xF0;
+const SLOT_ONE: u8 = 0xFF;
+const SLOT_ZERO: u8 = 0x00;
+
+/// Longest wait for the echo of a character
+const ECHO_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Serial port with its RXD on the 1-Wire line
+trait OneWireUart: Read + Write {
+    fn set_baud(&mut self, baud: u32) -> io::Result<()>;
+}
+
+impl OneWireUart for Ch347Uart {
+    fn set_baud(&mut self, baud: u32) -> io::Result<()> {
+        if self.config().baud == baud {
+            return Ok(());
+        }
+
+        Ch347Uart::set_baud(self, baud)
+    }
+}
+
+/// Send `data` and read back what the line carried
+fn uart_echo<U: OneWireUart>(uart: &mut U, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    uart.write_all(data).map_err(|_| "1-Wire UART write Fail")?;
+
+    let mut echo = vec![0; data.len()];
+    uart.read_exact(&mut echo)
+        .map_err(|_| "No echo on the 1-Wire UART, RXD must be wired to DQ")?;
+
+    Ok(echo)
+}
+
+fn uart_reset<U: OneWireUart>(uart: &mut U) -> Result<bool, &'static str> {
+    uart.set_baud(RESET_BAUD)
+        .map_err(|_| "1-Wire UART baud rate Fail")?;
+    let echo = uart_echo(uart, &[RESET_BYTE])?[0];
+    uart.set_baud(SLOT_BAUD)
+        .map_err(|_| "1-Wire UART baud rate Fail")?;
+
+    match echo {
+        0x00 => Err("1-Wire bus is held low"),
+        RESET_BYTE => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/// One slot per bit, the levels read back in the write 1 slots are the bits
+/// sent by the devices
+fn uart_slots<U: OneWireUart>(uart: &mut U, bits: &[bool]) -> Result<Vec<bool>, &'static str> {
+    let slots: Vec<u8> = bits
+        .iter()
+        .map(|&b| if b { SLOT_ONE } else { SLOT_ZERO })
+        .collect();
+
+    Ok(uart_echo(uart, &slots)?
+        .into_iter()
+        .map(|e| e == SLOT_ONE)
+        .collect())
+}
+
+/// 1-Wire master on a CH347 UART, a byte transfer is one USB round trip
+pub struct UartOneWire {
+    uart: RefCell<Ch347Uart>,
+}
+
+impl UartOneWire {
+    /// Takes over the line settings and the read timeout of `uart`
+    pub fn new(mut uart: Ch347Uart) -> io::Result<UartOneWire> {
+        uart.set_config(&UartConfig::new(SLOT_BAUD))?;
+        uart.set_read_timeout(Some(ECHO_TIMEOUT))?;
+
+        Ok(UartOneWire {
+            uart: RefCell::new(uart),
+        })
+    }
+
+    pub fn into_inner(self) -> Ch347Uart {
+        self.uart.into_inner()
+    }
+}
+
+impl OneWireDrive for UartOneWire {
+    fn ow_reset(&self) -> Result<bool, &'static str> {
+        uart_reset(&mut *self.uart.borrow_mut())
+    }
+
+    fn ow_write_bit(&self, bit: bool) -> Result<(), &'static str> {
+        uart_slots(&mut *self.uart.borrow_mut(), &[bit])?;
+        Ok(())
+    }
+
+    fn ow_read_bit(&self) -> Result<bool, &'static str> {
+        Ok(uart_slots(&mut *self.uart.borrow_mut(), &[true])?[0])
+    }
+
+    fn ow_write_byte(&self, b: u8) -> Result<(), &'static str> {
+        let bits: Vec<bool> = (0..8).map(|i| b & (1 << i) != 0).collect();
+        uart_slots(&mut *self.uart.borrow_mut(), &bits)?;
+        Ok(())
+    }
+
+    fn ow_read_byte(&self) -> Result<u8, &'static str> {
+        let bits = uart_slots(&mut *self.uart.borrow_mut(), &[true; 8])?;
+
+        Ok(bits
+            .iter()
+            .enumerate()
+            .fold(0, |b, (i, &bit)| b | ((bit as u8) << i)))
+    }
+}
+
+#[test]
+pub fn test_uart_one_wire() {
+    use std::collections::VecDeque;
+
+    /// Loopback line with one device answering from `reply`
+    struct MockLine {
+        baud: u32,
+        present: bool,
+        reply: VecDeque<bool>,
+        sent: Vec<(u32, u8)>,
+        echo: VecDeque<u8>,
+    }
+
+    impl OneWireUart for MockLine {
+        fn set_baud(&mut self, baud: u32) -> io::Result<()> {
+            self.baud = baud;
+            Ok(())
+        }
+    }
+
+    impl Write for MockLine {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for &b in buf {
+                self.sent.push((self.baud, b));
+                let echo = match (self.baud, b) {
+                    (RESET_BAUD, RESET_BYTE) if self.present => 0xE0,
+                    (SLOT_BAUD, SLOT_ONE) if self.reply.pop_front() == Some(false) => 0xFE,
+                    _ => b,
+                };
+                self.echo.push_back(echo);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for MockLine {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.echo.len());
+            if n == 0 {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            for b in buf.iter_mut().take(n) {
+                *b = self.echo.pop_front().unwrap_or_default();
+            }
+            Ok(n)
+        }
+    }
+
+    let mut line = MockLine {
+        baud: SLOT_BAUD,
+        present: true,
+        reply: VecDeque::new(),
+        sent: Vec::new(),
+        echo: VecDeque::new(),
+    };
+
+    assert_eq!(uart_reset(&mut line), Ok(true));
+    assert_eq!(line.sent, vec![(RESET_BAUD, RESET_BYTE)]);
+    assert_eq!(line.baud, SLOT_BAUD);
+
+    // SKIP ROM, LSB first
+    line.sent.clear();
+    let bits: Vec<bool> = (0..8).map(|i| 0xCC & (1 << i) != 0).collect();
+    uart_slots(&mut line, &bits).unwrap();
+    let slots: Vec<u8> = line.sent.iter().map(|&(_, b)| b).collect();
+    assert_eq!(slots, vec![0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF, 0xFF]);
+
+    // the device sends 0xA5
+    line.reply = [true, false, true, false, false, true, false, true].into();
+    let bits = uart_slots(&mut line, &[true; 8]).unwrap();
+    let b = bits
+        .iter()
+        .enumerate()
+        .fold(0u8, |b, (i, &bit)| b | ((bit as u8) << i));
+    assert_eq!(b, 0xA5);
+
+    line.present = false;
+    assert_eq!(uart_reset(&mut line), Ok(false));
+
+    /// TXD not looped back to RXD
+    struct NoEcho;
+
+    impl OneWireUart for NoEcho {
+        fn set_baud(&mut self, _baud: u32) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Write for NoEcho {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for NoEcho {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::TimedOut.into())
+        }
+    }
+
+    assert!(uart_reset(&mut NoEcho).is_err());
+}
@@ -0,0 +1,209 @@
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use super::{FlowControl, Parity, StopBits, UartConfig};
+
+const CH347_VID: u16 = 0x1A86;
+
+/// Product ids of the CH347 modes that have UART interfaces
+const CH347_UART_PIDS: [u16; 4] = [0x55DA, 0x55DB, 0x55DD, 0x55DE];
+
+/// A CH347 UART interface bound to a tty driver (cdc_acm or the vendor
+/// ch343ser), found in sysfs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UartPort {
+    /// e.g. /dev/ttyACM0
+    pub path: PathBuf,
+    pub product_id: u16,
+    /// USB interface number
+    pub interface: u8,
+    pub product: String,
+    pub manufacturer: String,
+}
+
+fn read_sysfs(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+fn read_sysfs_hex(dir: &Path, name: &str) -> Option<u16> {
+    u16::from_str_radix(&read_sysfs(dir, name)?, 16).ok()
+}
+
+/// Walk up from the tty device to the USB interface and device
+fn probe_tty(name: &str) -> Option<UartPort> {
+    let mut dir = fs::canonicalize(Path::new("/sys/class/tty").join(name).join("device")).ok()?;
+    let mut interface = None;
+
+    loop {
+        if interface.is_none() {
+            interface = read_sysfs_hex(&dir, "bInterfaceNumber");
+        }
+        if dir.join("idVendor").exists() {
+            break;
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+
+    let product_id = read_sysfs_hex(&dir, "idProduct")?;
+    if read_sysfs_hex(&dir, "idVendor")? != CH347_VID || !CH347_UART_PIDS.contains(&product_id) {
+        return None;
+    }
+
+    Some(UartPort {
+        path: Path::new("/dev").join(name),
+        product_id,
+        interface: interface? as u8,
+        product: read_sysfs(&dir, "product").unwrap_or_default(),
+        manufacturer: read_sysfs(&dir, "manufacturer").unwrap_or_default(),
+    })
+}
+
+/// All CH347 UART ttys sorted by name, the position is the UART index
+pub fn enum_uart_ports() -> Vec<UartPort> {
+    let mut names: Vec<String> = match fs::read_dir("/sys/class/tty") {
+        Ok(dir) => dir
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    names.sort();
+
+    names.iter().filter_map(|name| probe_tty(name)).collect()
+}
+
+/// UART index and port of an open tty
+pub(crate) fn uart_port_of_fd(fd: i32) -> Option<(usize, UartPort)> {
+    let path = fs::read_link(format!("/proc/self/fd/{}", fd)).ok()?;
+
+    enum_uart_ports()
+        .into_iter()
+        .enumerate()
+        .find(|(_, port)| port.path == path)
+}
+
+pub(crate) fn open_tty(path: &Path) -> io::Result<i32> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+
+    let fd = unsafe {
+        libc::open(
+            path.as_ptr(),
+            libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(fd)
+}
+
+/// Raw mode with the line settings of `config`, any baud rate through BOTHER
+pub(crate) fn apply_termios(
+    tio: &mut libc::termios2,
+    config: &UartConfig,
+) -> Result<(), &'static str> {
+    config.check()?;
+
+    tio.c_iflag &= !(libc::IGNBRK
+        | libc::BRKINT
+        | libc::PARMRK
+        | libc::ISTRIP
+        | libc::INLCR
+        | libc::IGNCR
+        | libc::ICRNL
+        | libc::IXON
+        | libc::IXOFF
+        | libc::IXANY
+        | libc::INPCK);
+    tio.c_oflag &= !libc::OPOST;
+    tio.c_lflag &= !(libc::ECHO | libc::ECHONL | libc::ICANON | libc::ISIG | libc::IEXTEN);
+    tio.c_cflag &= !(libc::CBAUD
+        | libc::CSIZE
+        | libc::PARENB
+        | libc::PARODD
+        | libc::CMSPAR
+        | libc::CSTOPB
+        | libc::CRTSCTS);
+
+    tio.c_cflag |= libc::CREAD | libc::CLOCAL | libc::BOTHER;
+    tio.c_cflag |= match config.data_bits {
+        5 => libc::CS5,
+        6 => libc::CS6,
+        7 => libc::CS7,
+        _ => libc::CS8,
+    };
+    tio.c_cflag |= match config.parity {
+        Parity::None => 0,
+        Parity::Odd => libc::PARENB | libc::PARODD,
+        Parity::Even => libc::PARENB,
+        Parity::Mark => libc::PARENB | libc::CMSPAR | libc::PARODD,
+        Parity::Space => libc::PARENB | libc::CMSPAR,
+    };
+    if config.parity != Parity::None {
+        tio.c_iflag |= libc::INPCK;
+    }
+
+    // CSTOPB means 1.5 stop bits with 5 data bits
+    match (config.stop_bits, config.data_bits) {
+        (StopBits::One, _) => (),
+        (StopBits::OnePointFive, 5) | (StopBits::Two, 6..=8) => tio.c_cflag |= libc::CSTOPB,
+        (StopBits::OnePointFive, _) => return Err("1.5 stop bits need 5 data bits"),
+        (StopBits::Two, _) => return Err("2 stop bits need 6~8 data bits"),
+    }
+
+    match config.flow_control {
+        FlowControl::None => (),
+        FlowControl::Hardware => tio.c_cflag |= libc::CRTSCTS,
+        FlowControl::Software => tio.c_iflag |= libc::IXON | libc::IXOFF,
+    }
+
+    // reads never block in the driver, timeouts are done with poll()
+    tio.c_cc[libc::VMIN] = 0;
+    tio.c_cc[libc::VTIME] = 0;
+    tio.c_ispeed = config.baud;
+    tio.c_ospeed = config.baud;
+
+    Ok(())
+}
+
+#[test]
+pub fn test_apply_termios() {
+    let mut tio: libc::termios2 = unsafe { std::mem::zeroed() };
+    tio.c_cflag = libc::B9600 | libc::HUPCL;
+    tio.c_lflag = libc::ICANON | libc::ECHO;
+
+    let config: UartConfig = "250000,7O2,rtscts".parse().unwrap();
+    apply_termios(&mut tio, &config).unwrap();
+    assert_eq!(
+        tio.c_cflag,
+        libc::HUPCL
+            | libc::BOTHER
+            | libc::CREAD
+            | libc::CLOCAL
+            | libc::CS7
+            | libc::PARENB
+            | libc::PARODD
+            | libc::CSTOPB
+            | libc::CRTSCTS
+    );
+    assert_eq!(tio.c_iflag, libc::INPCK);
+    assert_eq!(tio.c_lflag, 0);
+    assert_eq!((tio.c_ispeed, tio.c_ospeed), (250000, 250000));
+
+    let mut config = UartConfig {
+        stop_bits: StopBits::OnePointFive,
+        ..Default::default()
+    };
+    assert!(apply_termios(&mut tio, &config).is_err());
+    config.data_bits = 5;
+    apply_termios(&mut tio, &config).unwrap();
+    assert_eq!(tio.c_cflag & libc::CSIZE, libc::CS5);
+}